@@ -0,0 +1,185 @@
+use crate::columns::{format_value, Column, Columns};
+use crate::models::Value;
+use std::collections::HashSet;
+
+/// Aggregate functions allowed in the SELECT list
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AggregateFn {
+    Count,
+    Sum,
+    Avg,
+    Min,
+    Max,
+}
+
+impl AggregateFn {
+    fn parse(name: &str) -> Option<Self> {
+        match name.to_uppercase().as_str() {
+            "COUNT" => Some(AggregateFn::Count),
+            "SUM" => Some(AggregateFn::Sum),
+            "AVG" => Some(AggregateFn::Avg),
+            "MIN" => Some(AggregateFn::Min),
+            "MAX" => Some(AggregateFn::Max),
+            _ => None,
+        }
+    }
+}
+
+/// Split a select field like `SUM(disk_usage)` into its function and column
+pub fn parse_aggregate(field: &str) -> Option<(AggregateFn, &str)> {
+    let (name, rest) = field.split_once('(')?;
+    let column = rest.strip_suffix(')')?.trim();
+    Some((AggregateFn::parse(name.trim())?, column))
+}
+
+/// Whether the SELECT list is made of aggregates, so the query yields one summary row
+pub fn is_aggregate_query(fields: &[String]) -> bool {
+    fields.iter().any(|field| parse_aggregate(field).is_some())
+}
+
+/// Compute the summary row for an aggregate SELECT list, formatted for display.
+///
+/// SUM and AVG over `disk_usage` and `blocks` count each underlying object
+/// once (see `Columns::shared_storage_key`), so hard links to one inode add
+/// their space once, like `du`. Every other column, `size` included, and
+/// COUNT add up the rows as shown.
+pub fn aggregate_row<T: Columns>(rows: &[T], fields: &[String]) -> Vec<String> {
+    aggregate_row_with(rows, fields, T::column)
+}
+
+/// Compute the summary row over columns that `resolve` finds, for rows whose
+/// columns are only known at run time
+pub fn aggregate_row_with<T: Columns>(
+    rows: &[T],
+    fields: &[String],
+    resolve: impl Fn(&str) -> Option<Column<T>>,
+) -> Vec<String> {
+    fields
+        .iter()
+        .map(|field| match parse_aggregate(field) {
+            Some((function, column)) => aggregate(rows, function, column, &resolve),
+            None => String::new(),
+        })
+        .collect()
+}
+
+fn aggregate<T: Columns>(
+    rows: &[T],
+    function: AggregateFn,
+    column_name: &str,
+    resolve: impl Fn(&str) -> Option<Column<T>>,
+) -> String {
+    if function == AggregateFn::Count && column_name == "*" {
+        return rows.len().to_string();
+    }
+    let column = match resolve(column_name) {
+        Some(column) => column,
+        None => return "NULL".to_string(),
+    };
+
+    // Allocated space is what hard links share; their sizes are each file's own
+    let totals_storage = matches!(column_name, "disk_usage" | "blocks")
+        && matches!(function, AggregateFn::Sum | AggregateFn::Avg);
+    let mut seen = HashSet::new();
+    let values: Vec<Value> = rows
+        .iter()
+        .filter(|row| {
+            !totals_storage || row.shared_storage_key().is_none_or(|key| seen.insert(key))
+        })
+        .map(|row| column.get(row))
+        .filter(|value| *value != Value::Null)
+        .collect();
+
+    let numbers = || {
+        values.iter().filter_map(|value| match value {
+            Value::Number(number) => Some(*number),
+            _ => None,
+        })
+    };
+    let result = match function {
+        AggregateFn::Count => return values.len().to_string(),
+        AggregateFn::Sum => Value::Number(numbers().sum()),
+        AggregateFn::Avg => match numbers().count() {
+            0 => Value::Null,
+            count => Value::Number(numbers().sum::<f64>() / count as f64),
+        },
+        AggregateFn::Min => values
+            .iter()
+            .min_by(|a, b| a.sort_cmp(b))
+            .cloned()
+            .unwrap_or(Value::Null),
+        AggregateFn::Max => values
+            .iter()
+            .max_by(|a, b| a.sort_cmp(b))
+            .cloned()
+            .unwrap_or(Value::Null),
+    };
+    format_value(column.kind, result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::FileInfo;
+
+    fn file(name: &str, size: u64, blocks: u64, inode: u64) -> FileInfo {
+        FileInfo {
+            name: name.to_string(),
+            size_bytes: size,
+            blocks: Some(blocks),
+            inode: Some(inode),
+            device: Some(1),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_aggregate_row() {
+        // b.txt is a hard link to a.txt
+        let files = vec![
+            file("a.txt", 100, 8, 1),
+            file("b.txt", 100, 8, 1),
+            file("c.bin", 5000, 16, 2),
+        ];
+        let fields: Vec<String> = [
+            "COUNT(*)",
+            "SUM(disk_usage)",
+            "SUM(size)",
+            "MAX(name)",
+            "MIN(size)",
+        ]
+        .iter()
+        .map(|f| f.to_string())
+        .collect();
+
+        assert!(is_aggregate_query(&fields));
+        assert!(!is_aggregate_query(&["name".to_string()]));
+        assert_eq!(
+            aggregate_row(&files, &fields),
+            vec!["3", "12 KB", "5.08 KB", "c.bin", "100 B"]
+        );
+        // Sizes add up every row, hard links included; allocated space doesn't
+        assert_eq!(
+            aggregate_row(
+                &files,
+                &["SUM(size)", "AVG(size)", "SUM(blocks)", "AVG(blocks)"].map(String::from)
+            ),
+            vec!["5.08 KB", "1.69 KB", "24", "12"]
+        );
+        assert_eq!(
+            aggregate_row(&files, &["AVG(nlink)".to_string()]),
+            vec!["NULL"]
+        );
+    }
+
+    #[test]
+    fn test_parse_aggregate() {
+        assert_eq!(
+            parse_aggregate("SUM(disk_usage)"),
+            Some((AggregateFn::Sum, "disk_usage"))
+        );
+        assert_eq!(parse_aggregate("count(*)"), Some((AggregateFn::Count, "*")));
+        assert_eq!(parse_aggregate("LOWER(name)"), None);
+        assert_eq!(parse_aggregate("name"), None);
+    }
+}
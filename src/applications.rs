@@ -1,6 +1,6 @@
-use crate::models::{ApplicationInfo, Condition, SqlQuery};
+use crate::models::{ApplicationInfo, SqlQuery};
 use crate::parser::parse_compound_conditions;
-use std::io::Cursor;
+use crate::predicate::compile_conditions;
 use std::path::Path;
 
 pub fn execute_application_query(query: &SqlQuery) -> Result<Vec<ApplicationInfo>, String> {
@@ -16,13 +16,15 @@ pub fn execute_application_query(query: &SqlQuery) -> Result<Vec<ApplicationInfo
         || query.select_fields.contains(&"*".to_string())
         || conditions.iter().any(|c| c.field == "size");
 
+    let predicate = compile_conditions::<ApplicationInfo>(&conditions);
+
     // Get all installed applications with optimized metadata loading
     let all_apps = get_installed_applications_optimized(needs_size)?;
 
     // Apply WHERE filtering
     let mut filtered_apps: Vec<ApplicationInfo> = all_apps
         .into_iter()
-        .filter(|app| evaluate_application_conditions(app, &conditions))
+        .filter(|app| predicate.matches(app))
         .collect();
 
     // Apply ORDER BY
@@ -180,7 +182,6 @@ fn parse_macos_app_bundle(path: &Path, needs_size: bool) -> Option<ApplicationIn
 #[cfg(target_os = "linux")]
 fn get_linux_applications(needs_size: bool) -> Result<Vec<ApplicationInfo>, String> {
     use std::fs;
-    use std::path::Path;
 
     let mut applications = Vec::new();
 
@@ -220,14 +221,16 @@ fn parse_linux_desktop_file(path: &Path, needs_size: bool) -> Option<Application
         let mut exec = None;
         let mut categories = None;
 
-        for line in content.lines() {
-            let line = line.trim();
-            if line.starts_with("Name=") {
-                name = Some(line[5..].to_string());
-            } else if line.starts_with("Exec=") {
-                exec = Some(line[5..].to_string());
-            } else if line.starts_with("Categories=") {
-                categories = Some(line[11..].to_string());
+        // Only the main group; [Desktop Action ...] groups have names of their own
+        for entry in crate::configs::parse_ini(&content) {
+            if entry.section.as_deref() != Some("Desktop Entry") {
+                continue;
+            }
+            match entry.key.as_str() {
+                "Name" => name = entry.value,
+                "Exec" => exec = entry.value,
+                "Categories" => categories = entry.value,
+                _ => {}
             }
         }
 
@@ -251,7 +254,7 @@ fn parse_linux_desktop_file(path: &Path, needs_size: bool) -> Option<Application
             // Get file size only if needed
             let size = if needs_size {
                 if Path::new(&resolved_path).exists() {
-                    Some(get_file_size(Path::new(&resolved_path)))
+                    Some(get_directory_size_fast(Path::new(&resolved_path)))
                 } else {
                     None
                 }
@@ -350,77 +353,8 @@ fn parse_windows_registry_entry(subkey: &RegKey, key_name: &str) -> Option<Appli
     ))
 }
 
-fn evaluate_application_conditions(app: &ApplicationInfo, conditions: &[Condition]) -> bool {
-    for condition in conditions {
-        let result = evaluate_single_application_condition(app, condition);
-        let final_result = if condition.negated { !result } else { result };
-
-        if !final_result {
-            return false;
-        }
-    }
-    true
-}
-
-fn evaluate_single_application_condition(app: &ApplicationInfo, condition: &Condition) -> bool {
-    use crate::utils::like_match;
-
-    match condition.field.as_str() {
-        "name" => {
-            if condition.operator == "LIKE" {
-                like_match(&app.name, &condition.value)
-            } else {
-                crate::utils::compare_strings(&app.name, &condition.operator, &condition.value)
-            }
-        }
-        "version" => {
-            if let Some(version) = &app.version {
-                if condition.operator == "LIKE" {
-                    like_match(version, &condition.value)
-                } else {
-                    crate::utils::compare_strings(version, &condition.operator, &condition.value)
-                }
-            } else {
-                false
-            }
-        }
-        "path" => {
-            if condition.operator == "LIKE" {
-                like_match(&app.path, &condition.value)
-            } else {
-                crate::utils::compare_strings(&app.path, &condition.operator, &condition.value)
-            }
-        }
-        "category" => {
-            if let Some(category) = &app.category {
-                if condition.operator == "LIKE" {
-                    like_match(category, &condition.value)
-                } else {
-                    crate::utils::compare_strings(category, &condition.operator, &condition.value)
-                }
-            } else {
-                false
-            }
-        }
-        "size" => {
-            if let Some(size_str) = &app.size {
-                // Parse size for comparison (this is a simplified implementation)
-                // In a full implementation, we'd need proper size parsing logic
-                if condition.operator == "LIKE" {
-                    like_match(size_str, &condition.value)
-                } else {
-                    crate::utils::compare_strings(size_str, &condition.operator, &condition.value)
-                }
-            } else {
-                false
-            }
-        }
-        _ => false,
-    }
-}
-
 fn sort_application_results(
-    apps: &mut Vec<ApplicationInfo>,
+    apps: &mut [ApplicationInfo],
     order_by: &str,
     direction: &crate::models::SortDirection,
 ) -> Result<(), String> {
@@ -495,7 +429,7 @@ mod tests {
     }
 
     #[test]
-    fn test_evaluate_application_conditions() {
+    fn test_application_conditions() {
         let app = ApplicationInfo::new(
             "Chrome",
             Some("100.0".to_string()),
@@ -511,7 +445,7 @@ mod tests {
             negated: false,
         }];
 
-        assert!(evaluate_application_conditions(&app, &conditions));
+        assert!(compile_conditions::<ApplicationInfo>(&conditions).matches(&app));
 
         let bad_conditions = vec![Condition {
             field: "name".to_string(),
@@ -520,6 +454,24 @@ mod tests {
             negated: false,
         }];
 
-        assert!(!evaluate_application_conditions(&app, &bad_conditions));
+        assert!(!compile_conditions::<ApplicationInfo>(&bad_conditions).matches(&app));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_parse_linux_desktop_file() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("files.desktop");
+        std::fs::write(
+            &path,
+            "[Desktop Entry]\nName=Files\nExec=/nonexistent/nautilus %U\n\
+             Categories=Utility;Core;\n\n[Desktop Action new-window]\nName=New Window\n",
+        )
+        .unwrap();
+
+        let app = parse_linux_desktop_file(&path, false).unwrap();
+        assert_eq!(app.name, "Files");
+        assert_eq!(app.category, Some("Utility".to_string()));
+        assert_eq!(app.path, path.to_string_lossy());
     }
 }
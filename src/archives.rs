@@ -0,0 +1,498 @@
+use crate::cancel::CancelToken;
+use crate::models::FileInfo;
+use crate::utils::open_regular_file;
+use chrono::{DateTime, NaiveDate, Utc};
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+
+/// Archive formats whose entries can be listed like the files of a directory
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArchiveFormat {
+    Zip,
+    Tar,
+    TarGz,
+    TarZst,
+}
+
+impl ArchiveFormat {
+    /// Format of an archive from its file name (`.jar` and `.whl` files are zips)
+    pub fn for_name(name: &str) -> Option<Self> {
+        let name = name.to_ascii_lowercase();
+        let format = if [".zip", ".jar", ".whl"].iter().any(|e| name.ends_with(e)) {
+            ArchiveFormat::Zip
+        } else if name.ends_with(".tar") {
+            ArchiveFormat::Tar
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            ArchiveFormat::TarGz
+        } else if name.ends_with(".tar.zst") || name.ends_with(".tzst") {
+            ArchiveFormat::TarZst
+        } else {
+            return None;
+        };
+        Some(format)
+    }
+}
+
+/// A FROM path that is an archive or a directory inside one, like `dist.zip/lib`
+#[derive(Debug, PartialEq)]
+pub struct ArchivePath {
+    pub archive: PathBuf,
+    pub format: ArchiveFormat,
+    // Path of the directory (or file) inside the archive, empty for the whole archive
+    pub inner: String,
+}
+
+impl ArchivePath {
+    /// Split a path at the first component that is an archive file.
+    /// `None` when no part of the path is one.
+    pub fn parse(path: &Path) -> Option<Self> {
+        let mut archive = PathBuf::new();
+        let mut components = path.components();
+        while let Some(component) = components.next() {
+            archive.push(component);
+            let format = archive
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(ArchiveFormat::for_name);
+            if let Some(format) = format {
+                if archive.is_file() {
+                    let inner = components.as_path().to_string_lossy();
+                    return Some(ArchivePath {
+                        inner: inner.trim_matches('/').to_string(),
+                        archive,
+                        format,
+                    });
+                }
+            }
+        }
+        None
+    }
+}
+
+/// One file, directory or link stored in an archive
+#[derive(Debug, Clone)]
+struct ArchiveEntry {
+    path: String, // Relative to the archive root, without leading `./` or trailing `/`
+    file_type: &'static str,
+    size: u64,
+    compressed_size: Option<u64>, // Zip stores each entry compressed on its own; tar doesn't
+    modified: Option<DateTime<Utc>>,
+    mode: Option<u32>,
+    link_target: Option<String>,
+}
+
+impl ArchiveEntry {
+    fn directory(path: String) -> Self {
+        ArchiveEntry {
+            path,
+            file_type: "directory",
+            size: 0,
+            compressed_size: None,
+            modified: None,
+            mode: None,
+            link_target: None,
+        }
+    }
+}
+
+fn clean_path(name: &str) -> String {
+    let mut path = name.trim_end_matches('/');
+    while let Some(rest) = path.strip_prefix("./") {
+        path = rest;
+    }
+    path.trim_start_matches('/').to_string()
+}
+
+// Archive timestamps without a timezone (zip) are taken as UTC, as `unzip -l` shows them
+fn zip_time(time: zip::DateTime) -> Option<DateTime<Utc>> {
+    NaiveDate::from_ymd_opt(time.year().into(), time.month().into(), time.day().into())?
+        .and_hms_opt(
+            time.hour().into(),
+            time.minute().into(),
+            time.second().into(),
+        )
+        .map(|time| time.and_utc())
+}
+
+/// Read the entry list of an archive, decompressing a tarball as it goes.
+/// Directories that are only implied by the paths of their files are added.
+fn read_entries(
+    archive: &Path,
+    format: ArchiveFormat,
+    cancel: &CancelToken,
+) -> Result<Vec<ArchiveEntry>, String> {
+    let file = open_regular_file(archive)
+        .ok_or_else(|| format!("Cannot read archive: {}", archive.display()))?;
+    let reader = BufReader::new(file);
+    let mut entries = match format {
+        ArchiveFormat::Zip => read_zip(reader, cancel),
+        ArchiveFormat::Tar => read_tar(reader, cancel),
+        ArchiveFormat::TarGz => read_tar(flate2::read::MultiGzDecoder::new(reader), cancel),
+        ArchiveFormat::TarZst => {
+            let decoder = ruzstd::StreamingDecoder::new(reader).map_err(|e| e.to_string())?;
+            read_tar(decoder, cancel)
+        }
+    }
+    .map_err(|e| format!("Failed to read archive {}: {}", archive.display(), e))?;
+
+    entries.retain(|entry| !entry.path.is_empty());
+    let mut known: HashSet<String> = entries.iter().map(|entry| entry.path.clone()).collect();
+    let mut implied = Vec::new();
+    for entry in &entries {
+        let mut parent = entry.path.as_str();
+        while let Some((dir, _)) = parent.rsplit_once('/') {
+            if known.insert(dir.to_string()) {
+                implied.push(ArchiveEntry::directory(dir.to_string()));
+            }
+            parent = dir;
+        }
+    }
+    entries.extend(implied);
+    Ok(entries)
+}
+
+/// Longest symlink target read from a zip entry (PATH_MAX on Linux)
+const MAX_LINK_TARGET: u64 = 4096;
+
+fn read_zip(reader: BufReader<File>, cancel: &CancelToken) -> Result<Vec<ArchiveEntry>, String> {
+    let mut archive = zip::ZipArchive::new(reader).map_err(|e| e.to_string())?;
+    let mut entries = Vec::with_capacity(archive.len());
+    for index in 0..archive.len() {
+        if cancel.is_cancelled() {
+            break;
+        }
+        // The raw entry gives the metadata without decompressing anything
+        let mut entry = {
+            let file = archive.by_index_raw(index).map_err(|e| e.to_string())?;
+            let file_type = if file.is_dir() {
+                "directory"
+            } else if file.is_symlink() {
+                "symlink"
+            } else {
+                "file"
+            };
+            ArchiveEntry {
+                path: clean_path(file.name()),
+                file_type,
+                size: file.size(),
+                compressed_size: Some(file.compressed_size()),
+                modified: file.last_modified().and_then(zip_time),
+                mode: file.unix_mode(),
+                link_target: None,
+            }
+        };
+        // A link's target is stored as its contents, which a crafted archive
+        // can make any size; a real one fits in PATH_MAX
+        if entry.file_type == "symlink" {
+            let mut target = Vec::new();
+            if let Ok(file) = archive.by_index(index) {
+                let read = file.take(MAX_LINK_TARGET + 1).read_to_end(&mut target);
+                if read.is_ok() && target.len() as u64 <= MAX_LINK_TARGET {
+                    entry.link_target = String::from_utf8(target).ok();
+                }
+            }
+        }
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+fn read_tar(reader: impl Read, cancel: &CancelToken) -> Result<Vec<ArchiveEntry>, String> {
+    let mut archive = tar::Archive::new(reader);
+    let mut entries = Vec::new();
+    for entry in archive.entries().map_err(|e| e.to_string())? {
+        if cancel.is_cancelled() {
+            break;
+        }
+        let entry = entry.map_err(|e| e.to_string())?;
+        let header = entry.header();
+        let file_type = match header.entry_type() {
+            tar::EntryType::Directory => "directory",
+            tar::EntryType::Symlink => "symlink",
+            tar::EntryType::XGlobalHeader | tar::EntryType::XHeader => continue,
+            _ => "file",
+        };
+        let path = entry.path().map_err(|e| e.to_string())?;
+        let link_target = match file_type {
+            "symlink" => entry.link_name().ok().flatten(),
+            _ => None,
+        };
+        entries.push(ArchiveEntry {
+            path: clean_path(&path.to_string_lossy()),
+            file_type,
+            size: header.size().unwrap_or(0),
+            compressed_size: None,
+            modified: header
+                .mtime()
+                .ok()
+                .and_then(|mtime| DateTime::from_timestamp(mtime as i64, 0)),
+            mode: header.mode().ok(),
+            link_target: link_target.map(|target| target.to_string_lossy().into_owned()),
+        });
+    }
+    Ok(entries)
+}
+
+// Tar headers hold only the permission bits; rows carry the file type bits too, as stat does
+fn full_mode(mode: u32, file_type: &str) -> u32 {
+    if mode & 0o170000 != 0 {
+        return mode;
+    }
+    mode | match file_type {
+        "directory" => 0o040000,
+        "symlink" => 0o120000,
+        _ => 0o100000,
+    }
+}
+
+/// How an archive's entries become rows: `full_path` of an entry (relative
+/// to the archive root) is the path the row is shown at, relative to `root`
+struct RowBuilder<'a> {
+    root: &'a Path,
+    // Directories only implied by their files have no time of their own
+    archive_modified: DateTime<Utc>,
+}
+
+impl<'a> RowBuilder<'a> {
+    fn new(root: &'a Path, archive: &Path) -> Self {
+        let archive_modified = std::fs::metadata(archive)
+            .and_then(|metadata| metadata.modified())
+            .map(DateTime::<Utc>::from)
+            .unwrap_or_default();
+        RowBuilder {
+            root,
+            archive_modified,
+        }
+    }
+
+    fn row(&self, entry: &ArchiveEntry, full_path: &Path) -> Option<FileInfo> {
+        let is_dir = entry.file_type == "directory";
+        let mut row = FileInfo::new_lightweight_with_type(full_path, self.root, is_dir)?;
+        row.file_type = entry.file_type.to_string();
+        row.size_bytes = entry.size;
+        row.size = FileInfo::format_size(entry.size);
+        row.compressed_size = entry.compressed_size;
+        row.modified_date = entry.modified.unwrap_or(self.archive_modified);
+        if let Some(mode) = entry.mode {
+            let mode = full_mode(mode, entry.file_type);
+            row.mode = Some(mode);
+            row.permissions = format!("{:o}", mode);
+        }
+        row.link_target = entry.link_target.clone();
+        Some(row)
+    }
+}
+
+// Whether an entry is a dot-file or lies below a dot-directory, for SKIP HIDDEN
+fn is_hidden_entry(path: &str) -> bool {
+    path.split('/').any(|part| part.starts_with('.'))
+}
+
+/// Rows for a FROM path inside an archive: the directory (or file) it names
+/// as the root row, followed by everything below it
+pub fn list_archive(
+    from: &ArchivePath,
+    root: &Path,
+    skip_hidden: bool,
+    cancel: &CancelToken,
+) -> Result<Vec<FileInfo>, String> {
+    let entries = read_entries(&from.archive, from.format, cancel)?;
+    let rows = RowBuilder::new(root, &from.archive);
+
+    let root_entry = if from.inner.is_empty() {
+        ArchiveEntry::directory(String::new())
+    } else {
+        entries
+            .iter()
+            .find(|entry| entry.path == from.inner)
+            .cloned()
+            .ok_or_else(|| format!("Path does not exist: {}", root.display()))?
+    };
+    let mut results: Vec<FileInfo> = rows.row(&root_entry, root).into_iter().collect();
+
+    let prefix = match from.inner.as_str() {
+        "" => String::new(),
+        inner => format!("{}/", inner),
+    };
+    for entry in &entries {
+        let Some(relative) = entry.path.strip_prefix(&prefix) else {
+            continue;
+        };
+        if skip_hidden && is_hidden_entry(relative) {
+            continue;
+        }
+        results.extend(rows.row(entry, &root.join(relative)));
+    }
+    Ok(results)
+}
+
+/// Rows for every entry of an archive met during a walk (INCLUDE ARCHIVES),
+/// shown below the archive's own path. Unreadable archives have no entries.
+pub fn archive_entry_rows(
+    archive: &Path,
+    root: &Path,
+    skip_hidden: bool,
+    cancel: &CancelToken,
+) -> Vec<FileInfo> {
+    let format = archive
+        .file_name()
+        .and_then(|name| name.to_str())
+        .and_then(ArchiveFormat::for_name);
+    let Some(Ok(entries)) = format.map(|format| read_entries(archive, format, cancel)) else {
+        return Vec::new();
+    };
+    let rows = RowBuilder::new(root, archive);
+    entries
+        .iter()
+        .filter(|entry| !(skip_hidden && is_hidden_entry(&entry.path)))
+        .filter_map(|entry| rows.row(entry, &archive.join(&entry.path)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    fn write_tar(path: &Path) {
+        let mut builder = tar::Builder::new(File::create(path).unwrap());
+        let mut header = tar::Header::new_gnu();
+        header.set_size(5);
+        header.set_mode(0o644);
+        header.set_mtime(1_700_000_000);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, "./lib/util.js", &b"hello"[..])
+            .unwrap();
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Symlink);
+        header.set_size(0);
+        header.set_mode(0o777);
+        builder
+            .append_link(&mut header, "latest.js", "lib/util.js")
+            .unwrap();
+        builder.finish().unwrap();
+    }
+
+    #[test]
+    fn test_archive_path() {
+        let temp_dir = TempDir::new().unwrap();
+        let archive = temp_dir.path().join("dist.zip");
+        File::create(&archive).unwrap();
+        let parsed = ArchivePath::parse(&temp_dir.path().join("dist.zip/lib/")).unwrap();
+        assert_eq!(parsed.archive, archive);
+        assert_eq!(parsed.format, ArchiveFormat::Zip);
+        assert_eq!(parsed.inner, "lib");
+        assert_eq!(ArchivePath::parse(&archive).unwrap().inner, "");
+
+        // A directory named like an archive is walked as usual
+        std::fs::create_dir(temp_dir.path().join("src.tar.gz")).unwrap();
+        assert_eq!(
+            ArchivePath::parse(&temp_dir.path().join("src.tar.gz")),
+            None
+        );
+        assert_eq!(
+            ArchiveFormat::for_name("Release.TGZ"),
+            Some(ArchiveFormat::TarGz)
+        );
+        assert_eq!(ArchiveFormat::for_name("notes.txt"), None);
+    }
+
+    #[test]
+    fn test_list_tar() {
+        let temp_dir = TempDir::new().unwrap();
+        let archive = temp_dir.path().join("release.tar");
+        write_tar(&archive);
+        let cancel = CancelToken::new();
+
+        let from = ArchivePath::parse(&archive).unwrap();
+        let rows = list_archive(&from, &archive, false, &cancel).unwrap();
+        let mut listed: Vec<_> = rows
+            .iter()
+            .map(|row| (row.path.as_str(), row.file_type.as_str(), row.depth))
+            .collect();
+        listed.sort();
+        assert_eq!(
+            listed,
+            vec![
+                ("", "directory", 0),
+                ("latest.js", "symlink", 1),
+                ("lib", "directory", 1),
+                ("lib/util.js", "file", 2),
+            ]
+        );
+        let util = rows.iter().find(|row| row.name == "util.js").unwrap();
+        assert_eq!(util.size_bytes, 5);
+        assert_eq!(util.permissions, "100644");
+        assert_eq!(util.modified_date.timestamp(), 1_700_000_000);
+        assert_eq!(util.compressed_size, None);
+        let link = rows.iter().find(|row| row.name == "latest.js").unwrap();
+        assert_eq!(link.link_target.as_deref(), Some("lib/util.js"));
+
+        // A directory inside the archive becomes the root
+        let lib = archive.join("lib");
+        let from = ArchivePath::parse(&lib).unwrap();
+        let rows = list_archive(&from, &lib, false, &cancel).unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1].path, "util.js");
+
+        let missing = archive.join("nope");
+        let from = ArchivePath::parse(&missing).unwrap();
+        assert!(list_archive(&from, &missing, false, &cancel).is_err());
+    }
+
+    #[test]
+    fn test_list_zip_and_compressed_tar() {
+        let temp_dir = TempDir::new().unwrap();
+        let cancel = CancelToken::new();
+
+        let archive = temp_dir.path().join("bundle.zip");
+        let mut zip = zip::ZipWriter::new(File::create(&archive).unwrap());
+        let options = zip::write::SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Deflated)
+            .unix_permissions(0o755);
+        zip.start_file("bin/run.sh", options).unwrap();
+        zip.write_all(&[b'x'; 4096]).unwrap();
+        zip.add_symlink("bin/run", "run.sh", options).unwrap();
+        zip.add_symlink("bin/bomb", "a/".repeat(50_000), options)
+            .unwrap();
+        zip.finish().unwrap();
+
+        let rows = archive_entry_rows(&archive, temp_dir.path(), false, &cancel);
+        let run = rows.iter().find(|row| row.name == "run.sh").unwrap();
+        assert_eq!(run.path, "bundle.zip/bin/run.sh");
+        assert_eq!(run.depth, 3);
+        assert_eq!(run.size_bytes, 4096);
+        assert!(run.compressed_size.unwrap() < 4096);
+        assert_eq!(run.permissions, "100755");
+        assert!(rows.iter().any(|row| row.path == "bundle.zip/bin"));
+        let link = |name: &str| rows.iter().find(|row| row.name == name).unwrap();
+        assert_eq!(link("run").link_target.as_deref(), Some("run.sh"));
+        // A target longer than any real path is left out rather than read whole
+        assert_eq!(link("bomb").file_type, "symlink");
+        assert_eq!(link("bomb").link_target, None);
+
+        let tarball = temp_dir.path().join("release.tar");
+        write_tar(&tarball);
+        let gzipped = temp_dir.path().join("release.tgz");
+        let mut encoder = flate2::write::GzEncoder::new(
+            File::create(&gzipped).unwrap(),
+            flate2::Compression::default(),
+        );
+        encoder
+            .write_all(&std::fs::read(&tarball).unwrap())
+            .unwrap();
+        encoder.finish().unwrap();
+        let rows = archive_entry_rows(&gzipped, temp_dir.path(), true, &cancel);
+        assert_eq!(rows.len(), 3);
+        assert!(rows.iter().any(|row| row.path == "release.tgz/lib/util.js"));
+
+        // Anything that isn't a readable archive just has no entries
+        std::fs::write(temp_dir.path().join("broken.zip"), "not a zip").unwrap();
+        let broken = temp_dir.path().join("broken.zip");
+        assert!(archive_entry_rows(&broken, temp_dir.path(), false, &cancel).is_empty());
+    }
+}
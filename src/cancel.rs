@@ -0,0 +1,88 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Cooperative cancellation shared between a running query and whoever may stop it.
+///
+/// Long-running work (directory traversal, process collection, web fetches) polls
+/// `is_cancelled` and stops early, returning whatever it has collected so far.
+/// Clones share the same flag, so the token can be handed to a Ctrl-C handler or
+/// a GUI button while the query runs.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+    deadline: Option<Instant>,
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A token that also cancels itself once `timeout` has elapsed
+    pub fn with_timeout(timeout: Duration) -> Self {
+        CancelToken {
+            cancelled: Arc::new(AtomicBool::new(false)),
+            deadline: Instant::now().checked_add(timeout),
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed) || self.timed_out()
+    }
+
+    /// Whether the deadline, if any, has passed
+    pub fn timed_out(&self) -> bool {
+        self.deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    /// Time left before the deadline, or `None` if there is no deadline
+    pub fn remaining(&self) -> Option<Duration> {
+        self.deadline
+            .map(|deadline| deadline.saturating_duration_since(Instant::now()))
+    }
+
+    /// Error for operations that cannot return partial results
+    pub fn error(&self) -> String {
+        if self.timed_out() {
+            "Query timed out".to_string()
+        } else {
+            "Query cancelled".to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cancel_is_shared_between_clones() {
+        let token = CancelToken::new();
+        let handle = token.clone();
+        assert!(!token.is_cancelled());
+        assert_eq!(token.remaining(), None);
+
+        handle.cancel();
+        assert!(token.is_cancelled());
+        assert!(!token.timed_out());
+        assert_eq!(token.error(), "Query cancelled");
+    }
+
+    #[test]
+    fn test_timeout_cancels_token() {
+        let token = CancelToken::with_timeout(Duration::ZERO);
+        assert!(token.is_cancelled());
+        assert!(token.timed_out());
+        assert_eq!(token.remaining(), Some(Duration::ZERO));
+        assert_eq!(token.error(), "Query timed out");
+
+        let token = CancelToken::with_timeout(Duration::from_secs(3600));
+        assert!(!token.is_cancelled());
+    }
+}
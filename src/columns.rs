@@ -0,0 +1,426 @@
+use crate::extensions;
+use crate::images::ImageInfo;
+use crate::mime;
+use crate::models::{ApplicationInfo, FileInfo, NetInfo, ProcessInfo, Value};
+use crate::permissions;
+use crate::users::{group_name, user_name};
+use crate::xattrs;
+use chrono::{DateTime, Utc};
+use std::borrow::Cow;
+
+/// How literals compared against a column are parsed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColumnKind {
+    Text,      // Compared as strings
+    Number,    // Plain numeric literal, e.g. depth = 2
+    Size,      // Size literal with optional unit, e.g. size > '1 MB'
+    Percent,   // Percentage literal, compared with a 0.1 tolerance
+    Timestamp, // Date literal, e.g. modified_date > '2025-01-01'
+    Boolean,   // true/false or 1/0, e.g. is_hidden = false
+}
+
+/// A resolved column: its kind and an accessor that reads it from a row
+pub struct Column<T> {
+    pub kind: ColumnKind,
+    get: Accessor<T>,
+}
+
+// Rows with a fixed set of columns read a field; rows of a table whose
+// columns are only known at run time (a CSV file's header) read a cell;
+// columns named in the query itself, like XATTR(path, 'user.tag'), read by key
+enum Accessor<T> {
+    Field(fn(&T) -> Value<'_>),
+    Cell(fn(&T, usize) -> Value<'_>, usize),
+    Keyed(for<'a> fn(&'a T, &str) -> Value<'a>, &'static str),
+}
+
+impl<T> Column<T> {
+    pub fn new(kind: ColumnKind, get: fn(&T) -> Value<'_>) -> Self {
+        Column {
+            kind,
+            get: Accessor::Field(get),
+        }
+    }
+
+    /// A column read from each row's cell at `index`
+    pub fn cell(kind: ColumnKind, get: fn(&T, usize) -> Value<'_>, index: usize) -> Self {
+        Column {
+            kind,
+            get: Accessor::Cell(get, index),
+        }
+    }
+
+    /// A column read from each row by `key`
+    pub fn keyed(
+        kind: ColumnKind,
+        get: for<'a> fn(&'a T, &str) -> Value<'a>,
+        key: &'static str,
+    ) -> Self {
+        Column {
+            kind,
+            get: Accessor::Keyed(get, key),
+        }
+    }
+
+    pub fn get<'a>(&self, row: &'a T) -> Value<'a> {
+        match self.get {
+            Accessor::Field(get) => get(row),
+            Accessor::Cell(get, index) => get(row, index),
+            Accessor::Keyed(get, key) => get(row, key),
+        }
+    }
+}
+
+// Manual impls: derive would require T: Clone/Copy
+impl<T> Clone for Column<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Column<T> {}
+
+impl<T> Clone for Accessor<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Accessor<T> {}
+
+/// Rows whose columns can be looked up by name once and then read per row
+pub trait Columns: Sized {
+    fn column(name: &str) -> Option<Column<Self>>;
+
+    /// Identifies the storage behind a row when several rows can share it
+    /// (hard links to one inode), so disk usage totals count it once
+    fn shared_storage_key(&self) -> Option<(u64, u64)> {
+        None
+    }
+}
+
+/// Display text for a column, formatted according to its kind.
+/// Returns `None` for unknown columns.
+pub fn display_value<T: Columns>(row: &T, field: &str) -> Option<String> {
+    let column = T::column(field)?;
+    Some(format_value(column.kind, column.get(row)))
+}
+
+/// Display text for a value of a column of the given kind
+pub fn format_value(kind: ColumnKind, value: Value<'_>) -> String {
+    match (kind, value) {
+        (ColumnKind::Size, Value::Number(bytes)) => FileInfo::format_size(bytes as u64),
+        (ColumnKind::Percent, Value::Number(percent)) => format!("{:.1}%", percent),
+        (_, value) => value.as_text().into_owned(),
+    }
+}
+
+fn text(value: &str) -> Value<'_> {
+    Value::Text(Cow::Borrowed(value))
+}
+
+fn optional_text(value: Option<&str>) -> Value<'_> {
+    value.map_or(Value::Null, text)
+}
+
+// Ports and PIDs are kept as text; empty means there is none
+fn numeric_text(value: &str) -> Value<'static> {
+    value.parse::<f64>().map_or(Value::Null, Value::Number)
+}
+
+fn number_value(number: Option<impl Into<u64>>) -> Value<'static> {
+    match number {
+        Some(number) => Value::Number(number.into() as f64),
+        None => Value::Null,
+    }
+}
+
+fn float_value(number: Option<f64>) -> Value<'static> {
+    match number {
+        Some(number) => Value::Number(number),
+        None => Value::Null,
+    }
+}
+
+fn image_number(file: &FileInfo, get: fn(&ImageInfo) -> Option<u64>) -> Value<'static> {
+    number_value(file.image.as_deref().and_then(get))
+}
+
+fn xattr_value<'a>(file: &'a FileInfo, name: &str) -> Value<'a> {
+    let attributes = file.xattrs.as_deref().unwrap_or_default();
+    match attributes.iter().find(|(attribute, _)| attribute == name) {
+        Some((_, value)) => Value::Text(Cow::Owned(xattrs::value_text(value))),
+        None => Value::Null,
+    }
+}
+
+fn mode_flag(mode: Option<u32>, flag: fn(u32) -> bool) -> Value<'static> {
+    match mode {
+        Some(mode) => Value::Bool(flag(mode)),
+        None => Value::Null,
+    }
+}
+
+fn timestamp_value(timestamp: Option<DateTime<Utc>>) -> Value<'static> {
+    match timestamp {
+        Some(timestamp) => Value::Timestamp(timestamp),
+        None => Value::Null,
+    }
+}
+
+impl Columns for FileInfo {
+    fn column(name: &str) -> Option<Column<Self>> {
+        let (kind, get): (ColumnKind, fn(&Self) -> Value<'_>) = match name {
+            "name" => (ColumnKind::Text, |f| text(&f.name)),
+            "type" => (ColumnKind::Text, |f| text(&f.file_type)),
+            "modified_date" => (ColumnKind::Timestamp, |f| Value::Timestamp(f.modified_date)),
+            "accessed_date" => (ColumnKind::Timestamp, |f| timestamp_value(f.accessed_date)),
+            "changed_date" => (ColumnKind::Timestamp, |f| timestamp_value(f.changed_date)),
+            "created_date" => (ColumnKind::Timestamp, |f| timestamp_value(f.created_date)),
+            "permissions" => (ColumnKind::Text, |f| text(&f.permissions)),
+            "size" => (ColumnKind::Size, |f| Value::Number(f.size_bytes as f64)),
+            "path" => (ColumnKind::Text, |f| text(&f.path)),
+            "depth" => (ColumnKind::Number, |f| Value::Number(f.depth as f64)),
+            "extension" => (ColumnKind::Text, |f| match &f.extension {
+                Some(extension) => text(extension),
+                None => Value::Null,
+            }),
+            "full_extension" => (ColumnKind::Text, |f| {
+                match extensions::full_extension(&f.name, f.file_type == "directory") {
+                    Some(extension) => Value::Text(Cow::Owned(extension)),
+                    None => Value::Null,
+                }
+            }),
+            "stem" => (ColumnKind::Text, |f| {
+                text(extensions::stem(&f.name, f.file_type == "directory"))
+            }),
+            "language" => (ColumnKind::Text, |f| {
+                match extensions::language(&f.name, f.extension.as_deref()) {
+                    Some(language) if f.file_type != "directory" => text(language),
+                    _ => Value::Null,
+                }
+            }),
+            "link_target" => (ColumnKind::Text, |f| match &f.link_target {
+                Some(target) => text(target),
+                None => Value::Null,
+            }),
+            "is_hidden" => (ColumnKind::Boolean, |f| Value::Bool(f.is_hidden)),
+            "mode" => (ColumnKind::Text, |f| match f.mode {
+                Some(mode) => Value::Text(Cow::Owned(permissions::mode_octal(mode))),
+                None => Value::Null,
+            }),
+            "mode_string" => (ColumnKind::Text, |f| match f.mode {
+                Some(mode) => Value::Text(Cow::Owned(permissions::mode_string(mode))),
+                None => Value::Null,
+            }),
+            "is_executable" => (ColumnKind::Boolean, |f| {
+                mode_flag(f.mode, permissions::is_executable)
+            }),
+            "is_world_writable" => (ColumnKind::Boolean, |f| {
+                mode_flag(f.mode, permissions::is_world_writable)
+            }),
+            "is_setuid" => (ColumnKind::Boolean, |f| {
+                mode_flag(f.mode, permissions::is_setuid)
+            }),
+            "is_setgid" => (ColumnKind::Boolean, |f| {
+                mode_flag(f.mode, permissions::is_setgid)
+            }),
+            "is_sticky" => (ColumnKind::Boolean, |f| {
+                mode_flag(f.mode, permissions::is_sticky)
+            }),
+            "inode" => (ColumnKind::Number, |f| number_value(f.inode)),
+            "nlink" => (ColumnKind::Number, |f| number_value(f.nlink)),
+            "device" => (ColumnKind::Number, |f| number_value(f.device)),
+            "blocks" => (ColumnKind::Number, |f| number_value(f.blocks)),
+            // Bytes actually allocated on disk (st_blocks is in 512-byte units)
+            "disk_usage" => (ColumnKind::Size, |f| {
+                number_value(f.blocks.map(|blocks| blocks * 512))
+            }),
+            "compressed_size" => (ColumnKind::Size, |f| number_value(f.compressed_size)),
+            "total_size" => (ColumnKind::Size, |f| number_value(f.total_size)),
+            "file_count" => (ColumnKind::Number, |f| number_value(f.file_count)),
+            "hash" => (ColumnKind::Text, |f| match &f.hash {
+                Some(hash) => text(hash),
+                None => Value::Null,
+            }),
+            "sha256" => (ColumnKind::Text, |f| match &f.sha256 {
+                Some(hash) => text(hash),
+                None => Value::Null,
+            }),
+            "mime_type" => (ColumnKind::Text, |f| f.mime_type.map_or(Value::Null, text)),
+            "is_binary" => (ColumnKind::Boolean, |f| {
+                match f.mime_type.and_then(mime::is_binary) {
+                    Some(binary) => Value::Bool(binary),
+                    None => Value::Null,
+                }
+            }),
+            "line_count" => (ColumnKind::Number, |f| number_value(f.line_count)),
+            "matching_lines" => (ColumnKind::Number, |f| number_value(f.matching_lines)),
+            "line_number" => (ColumnKind::Number, |f| number_value(f.line_number)),
+            "line" => (ColumnKind::Text, |f| match &f.line {
+                Some(line) => text(line),
+                None => Value::Null,
+            }),
+            "image_width" => (ColumnKind::Number, |f| image_number(f, |i| i.width)),
+            "image_height" => (ColumnKind::Number, |f| image_number(f, |i| i.height)),
+            "image_format" => (ColumnKind::Text, |f| {
+                f.image
+                    .as_ref()
+                    .and_then(|i| i.format)
+                    .map_or(Value::Null, text)
+            }),
+            "camera" => (ColumnKind::Text, |f| {
+                match f.image.as_ref().and_then(|i| i.camera.as_deref()) {
+                    Some(camera) => text(camera),
+                    None => Value::Null,
+                }
+            }),
+            "taken_at" => (ColumnKind::Timestamp, |f| {
+                timestamp_value(f.image.as_ref().and_then(|i| i.taken_at))
+            }),
+            "orientation" => (ColumnKind::Number, |f| image_number(f, |i| i.orientation)),
+            "gps_latitude" => (ColumnKind::Number, |f| {
+                float_value(f.image.as_ref().and_then(|i| i.gps_latitude))
+            }),
+            "gps_longitude" => (ColumnKind::Number, |f| {
+                float_value(f.image.as_ref().and_then(|i| i.gps_longitude))
+            }),
+            "elf_class" => (ColumnKind::Text, |f| {
+                f.elf
+                    .as_ref()
+                    .and_then(|e| e.class)
+                    .map_or(Value::Null, text)
+            }),
+            "elf_arch" => (ColumnKind::Text, |f| {
+                match f.elf.as_ref().and_then(|e| e.arch.as_deref()) {
+                    Some(arch) => text(arch),
+                    None => Value::Null,
+                }
+            }),
+            "elf_type" => (ColumnKind::Text, |f| {
+                f.elf
+                    .as_ref()
+                    .and_then(|e| e.file_type)
+                    .map_or(Value::Null, text)
+            }),
+            "is_stripped" => (ColumnKind::Boolean, |f| {
+                match f.elf.as_ref().and_then(|e| e.is_stripped) {
+                    Some(stripped) => Value::Bool(stripped),
+                    None => Value::Null,
+                }
+            }),
+            "interpreter" => (ColumnKind::Text, |f| {
+                match f.elf.as_ref().and_then(|e| e.interpreter.as_deref()) {
+                    Some(interpreter) => text(interpreter),
+                    None => Value::Null,
+                }
+            }),
+            // Comma-separated, so LIKE '%libssl%' finds binaries linking it
+            "needed_libraries" => (ColumnKind::Text, |f| {
+                match f.elf.as_ref().and_then(|e| e.needed_libraries.as_ref()) {
+                    Some(libraries) => Value::Text(Cow::Owned(libraries.join(", "))),
+                    None => Value::Null,
+                }
+            }),
+            "xattrs" => (ColumnKind::Text, |f| {
+                match f.xattrs.as_deref().and_then(xattrs::format_xattrs) {
+                    Some(attributes) => Value::Text(Cow::Owned(attributes)),
+                    None => Value::Null,
+                }
+            }),
+            "acl" => (ColumnKind::Text, |f| {
+                match f.xattrs.as_deref().and_then(xattrs::format_acl) {
+                    Some(acl) => Value::Text(Cow::Owned(acl)),
+                    None => Value::Null,
+                }
+            }),
+            "git_status" => (ColumnKind::Text, |f| {
+                f.git
+                    .as_ref()
+                    .and_then(|g| g.status)
+                    .map_or(Value::Null, text)
+            }),
+            "git_last_commit_date" => (ColumnKind::Timestamp, |f| {
+                timestamp_value(f.git.as_ref().and_then(|g| g.last_commit_date))
+            }),
+            "git_last_author" => (ColumnKind::Text, |f| {
+                match f.git.as_ref().and_then(|g| g.last_author.as_deref()) {
+                    Some(author) => text(author),
+                    None => Value::Null,
+                }
+            }),
+            "mount_point" => (ColumnKind::Text, |f| match &f.mount {
+                Some(mount) => text(&mount.mount_point),
+                None => Value::Null,
+            }),
+            "fs_type" => (ColumnKind::Text, |f| match &f.mount {
+                Some(mount) => text(&mount.fs_type),
+                None => Value::Null,
+            }),
+            "uid" => (ColumnKind::Number, |f| number_value(f.uid)),
+            "gid" => (ColumnKind::Number, |f| number_value(f.gid)),
+            // NULL when the id has no account, e.g. files of a deleted user
+            "owner" => (ColumnKind::Text, |f| match f.uid.and_then(user_name) {
+                Some(name) => text(name),
+                None => Value::Null,
+            }),
+            "group" => (ColumnKind::Text, |f| match f.gid.and_then(group_name) {
+                Some(name) => text(name),
+                None => Value::Null,
+            }),
+            _ => {
+                let name = xattrs::intern_name(xattrs::xattr_name(name)?);
+                return Some(Column::keyed(ColumnKind::Text, xattr_value, name));
+            }
+        };
+        Some(Column::new(kind, get))
+    }
+
+    // Hard links share one inode, identified by device and inode number
+    fn shared_storage_key(&self) -> Option<(u64, u64)> {
+        Some((self.device?, self.inode?))
+    }
+}
+
+impl Columns for ProcessInfo {
+    fn column(name: &str) -> Option<Column<Self>> {
+        let (kind, get): (ColumnKind, fn(&Self) -> Value<'_>) = match name {
+            "pid" => (ColumnKind::Number, |p| {
+                Value::Number(p.pid.parse::<f64>().unwrap_or(0.0))
+            }),
+            "name" => (ColumnKind::Text, |p| text(&p.name)),
+            "cpu_usage" => (ColumnKind::Percent, |p| Value::Number(p.cpu_percent as f64)),
+            "memory_usage" => (ColumnKind::Size, |p| Value::Number(p.memory_bytes as f64)),
+            "status" => (ColumnKind::Text, |p| text(&p.status)),
+            _ => return None,
+        };
+        Some(Column::new(kind, get))
+    }
+}
+
+impl Columns for NetInfo {
+    fn column(name: &str) -> Option<Column<Self>> {
+        let (kind, get): (ColumnKind, fn(&Self) -> Value<'_>) = match name {
+            "name" => (ColumnKind::Text, |n| text(&n.name)),
+            "port" => (ColumnKind::Number, |n| numeric_text(&n.port)),
+            "pid" => (ColumnKind::Number, |n| numeric_text(&n.pid)),
+            _ => return None,
+        };
+        Some(Column::new(kind, get))
+    }
+}
+
+impl Columns for ApplicationInfo {
+    fn column(name: &str) -> Option<Column<Self>> {
+        let (kind, get): (ColumnKind, fn(&Self) -> Value<'_>) = match name {
+            "name" => (ColumnKind::Text, |a| text(&a.name)),
+            "version" => (ColumnKind::Text, |a| optional_text(a.version.as_deref())),
+            "path" => (ColumnKind::Text, |a| text(&a.path)),
+            // Already formatted, e.g. '1.5 MB', so compared as text as before
+            "size" => (ColumnKind::Text, |a| optional_text(a.size.as_deref())),
+            "category" => (ColumnKind::Text, |a| optional_text(a.category.as_deref())),
+            _ => return None,
+        };
+        Some(Column::new(kind, get))
+    }
+}
@@ -0,0 +1,359 @@
+use crate::cancel::CancelToken;
+use crate::columns::ColumnKind;
+use crate::tables::{matching_files, open_table_file, TableColumn, TableRow};
+use std::io::Read;
+use std::path::Path;
+use yaml_rust2::{Yaml, YamlLoader};
+
+/// The syntax of a config file read with `toml()`, `yaml()` or `ini()`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConfigSyntax {
+    Toml,
+    Yaml,
+    Ini,
+}
+
+/// One setting in a config file. For TOML and YAML the key is the full
+/// dotted path from the top of the file (`package.version`) and the section
+/// is the table or mapping holding it; INI keys are as written in their section.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigEntry {
+    pub section: Option<String>,
+    pub key: String,
+    pub value: Option<String>,
+    pub kind: &'static str,
+}
+
+impl ConfigEntry {
+    fn new(section: Option<&str>, key: String, value: Option<String>, kind: &'static str) -> Self {
+        ConfigEntry {
+            section: section.map(str::to_string),
+            key,
+            value,
+            kind,
+        }
+    }
+}
+
+const CONFIG_COLUMNS: [&str; 5] = ["file", "section", "key", "value", "type"];
+
+/// The columns of every config table. Values are text, whatever their type
+/// in the file, since one column holds all of them.
+pub(crate) fn columns() -> Vec<TableColumn> {
+    CONFIG_COLUMNS
+        .iter()
+        .map(|name| TableColumn {
+            name: name.to_string(),
+            kind: ColumnKind::Text,
+        })
+        .collect()
+}
+
+/// Hand `keep` a row for every setting in every file `path` names
+pub(crate) fn for_each_row(
+    path: &Path,
+    syntax: ConfigSyntax,
+    columns: &[TableColumn],
+    cancel: &CancelToken,
+    keep: &mut impl FnMut(TableRow) -> Result<(), String>,
+) -> Result<(), String> {
+    for file in matching_files(path)? {
+        if cancel.is_cancelled() {
+            break;
+        }
+        let mut text = String::new();
+        open_table_file(&file)?
+            .read_to_string(&mut text)
+            .map_err(|e| format!("{}: {}", file.display(), e))?;
+        let entries =
+            parse_config(&text, syntax).map_err(|e| format!("{}: {}", file.display(), e))?;
+        let file = file.display().to_string();
+        for entry in entries {
+            let texts = [
+                Some(file.as_str()),
+                entry.section.as_deref(),
+                Some(entry.key.as_str()),
+                entry.value.as_deref(),
+                Some(entry.kind),
+            ];
+            keep(TableRow::from_texts(texts, columns))?;
+        }
+    }
+    Ok(())
+}
+
+pub fn parse_config(text: &str, syntax: ConfigSyntax) -> Result<Vec<ConfigEntry>, String> {
+    let mut entries = Vec::new();
+    match syntax {
+        ConfigSyntax::Toml => {
+            let table: toml::Table = text.parse().map_err(|e: toml::de::Error| e.to_string())?;
+            toml_entries(&table, None, &mut entries);
+        }
+        ConfigSyntax::Yaml => {
+            let documents = YamlLoader::load_from_str(text).map_err(|e| e.to_string())?;
+            for document in &documents {
+                yaml_entries(document, None, None, &mut entries);
+            }
+        }
+        ConfigSyntax::Ini => entries = parse_ini(text),
+    }
+    Ok(entries)
+}
+
+fn join_key(section: Option<&str>, key: &str) -> String {
+    match section {
+        Some(section) => format!("{}.{}", section, key),
+        None => key.to_string(),
+    }
+}
+
+fn toml_entries(table: &toml::Table, section: Option<&str>, entries: &mut Vec<ConfigEntry>) {
+    for (key, value) in table {
+        toml_value(value, section, join_key(section, key), entries);
+    }
+}
+
+// Arrays give an entry per element, all under the array's key; empty arrays
+// and tables are kept as a single entry without a value
+fn toml_value(
+    value: &toml::Value,
+    section: Option<&str>,
+    key: String,
+    entries: &mut Vec<ConfigEntry>,
+) {
+    let (value, kind) = match value {
+        toml::Value::Table(table) if !table.is_empty() => {
+            return toml_entries(table, Some(&key), entries);
+        }
+        toml::Value::Array(items) if !items.is_empty() => {
+            for item in items {
+                toml_value(item, section, key.clone(), entries);
+            }
+            return;
+        }
+        toml::Value::Table(_) => (None, "table"),
+        toml::Value::Array(_) => (None, "array"),
+        toml::Value::String(text) => (Some(text.clone()), "string"),
+        toml::Value::Integer(number) => (Some(number.to_string()), "integer"),
+        toml::Value::Float(number) => (Some(number.to_string()), "float"),
+        toml::Value::Boolean(flag) => (Some(flag.to_string()), "boolean"),
+        toml::Value::Datetime(datetime) => (Some(datetime.to_string()), "datetime"),
+    };
+    entries.push(ConfigEntry::new(section, key, value, kind));
+}
+
+// The same shape as TOML: mappings nest into dotted keys, sequences repeat their key
+fn yaml_entries(
+    value: &Yaml,
+    section: Option<&str>,
+    key: Option<String>,
+    entries: &mut Vec<ConfigEntry>,
+) {
+    let (value, kind) = match value {
+        Yaml::Hash(mapping) if !mapping.is_empty() => {
+            for (child, value) in mapping {
+                let child = match child {
+                    Yaml::String(text) | Yaml::Real(text) => text.clone(),
+                    Yaml::Integer(number) => number.to_string(),
+                    Yaml::Boolean(flag) => flag.to_string(),
+                    Yaml::Null => "null".to_string(),
+                    _ => continue, // Complex keys have no dotted form
+                };
+                let child = join_key(key.as_deref(), &child);
+                yaml_entries(value, key.as_deref(), Some(child), entries);
+            }
+            return;
+        }
+        Yaml::Array(items) if !items.is_empty() => {
+            for item in items {
+                yaml_entries(item, section, key.clone(), entries);
+            }
+            return;
+        }
+        Yaml::Hash(_) => (None, "mapping"),
+        Yaml::Array(_) => (None, "sequence"),
+        Yaml::String(text) => (Some(text.clone()), "string"),
+        Yaml::Integer(number) => (Some(number.to_string()), "integer"),
+        Yaml::Real(text) => (Some(text.clone()), "float"),
+        Yaml::Boolean(flag) => (Some(flag.to_string()), "boolean"),
+        Yaml::Null => (None, "null"),
+        Yaml::Alias(_) | Yaml::BadValue => return,
+    };
+    // A document that is a single scalar has no key of its own
+    let key = key.unwrap_or_else(|| "value".to_string());
+    entries.push(ConfigEntry::new(section, key, value, kind));
+}
+
+/// Parse INI-style text such as `.desktop`, `.gitconfig` or `php.ini` files.
+/// Keys are split from values at the first `=` or `:`, `;` and `#` start
+/// comment lines, and keys before any `[section]` header have no section.
+pub fn parse_ini(text: &str) -> Vec<ConfigEntry> {
+    let mut entries = Vec::new();
+    let mut section: Option<String> = None;
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with([';', '#']) {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            section = Some(name.trim().to_string());
+            continue;
+        }
+        let (key, value) = match line.find(['=', ':']) {
+            Some(separator) => (&line[..separator], Some(line[separator + 1..].trim())),
+            None => (line, None), // A bare key, as in `[mysqld] skip-networking`
+        };
+        entries.push(ConfigEntry::new(
+            section.as_deref(),
+            key.trim().to_string(),
+            value.map(str::to_string),
+            "string",
+        ));
+    }
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows(entries: &[ConfigEntry]) -> Vec<(Option<&str>, &str, Option<&str>, &str)> {
+        entries
+            .iter()
+            .map(|e| {
+                (
+                    e.section.as_deref(),
+                    e.key.as_str(),
+                    e.value.as_deref(),
+                    e.kind,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_toml() {
+        let text = r#"
+            edition = "2021"
+
+            [package]
+            name = "demo"
+            version = "0.3.1"
+            keywords = ["cli", "sql"]
+
+            [dependencies]
+            serde = { version = "1", features = [] }
+
+            [[bin]]
+            name = "q"
+            test = false
+        "#;
+        let entries = parse_config(text, ConfigSyntax::Toml).unwrap();
+        assert_eq!(
+            rows(&entries),
+            vec![
+                (None, "edition", Some("2021"), "string"),
+                (Some("package"), "package.name", Some("demo"), "string"),
+                (Some("package"), "package.version", Some("0.3.1"), "string"),
+                (Some("package"), "package.keywords", Some("cli"), "string"),
+                (Some("package"), "package.keywords", Some("sql"), "string"),
+                (
+                    Some("dependencies.serde"),
+                    "dependencies.serde.version",
+                    Some("1"),
+                    "string"
+                ),
+                (
+                    Some("dependencies.serde"),
+                    "dependencies.serde.features",
+                    None,
+                    "array"
+                ),
+                (Some("bin"), "bin.name", Some("q"), "string"),
+                (Some("bin"), "bin.test", Some("false"), "boolean"),
+            ]
+        );
+        assert!(parse_config("[package\nname = 1", ConfigSyntax::Toml).is_err());
+    }
+
+    #[test]
+    fn test_parse_yaml() {
+        let text = "
+services:
+  web:
+    image: nginx:1.25
+    ports: [80, 443]
+    healthy: true
+  cache: {}
+replicas: ~
+---
+second: 2.5
+";
+        let entries = parse_config(text, ConfigSyntax::Yaml).unwrap();
+        assert_eq!(
+            rows(&entries),
+            vec![
+                (
+                    Some("services.web"),
+                    "services.web.image",
+                    Some("nginx:1.25"),
+                    "string"
+                ),
+                (
+                    Some("services.web"),
+                    "services.web.ports",
+                    Some("80"),
+                    "integer"
+                ),
+                (
+                    Some("services.web"),
+                    "services.web.ports",
+                    Some("443"),
+                    "integer"
+                ),
+                (
+                    Some("services.web"),
+                    "services.web.healthy",
+                    Some("true"),
+                    "boolean"
+                ),
+                (Some("services"), "services.cache", None, "mapping"),
+                (None, "replicas", None, "null"),
+                (None, "second", Some("2.5"), "float"),
+            ]
+        );
+        assert!(parse_config("a: [1, 2", ConfigSyntax::Yaml).is_err());
+    }
+
+    #[test]
+    fn test_parse_ini() {
+        let text = "
+; a comment
+top = 1
+[Desktop Entry]
+Name=Files
+Exec=nautilus --new-window %U
+Name[de] = Dateien
+# another comment
+[mysqld]
+skip-networking
+bind-address: 127.0.0.1
+";
+        assert_eq!(
+            rows(&parse_ini(text)),
+            vec![
+                (None, "top", Some("1"), "string"),
+                (Some("Desktop Entry"), "Name", Some("Files"), "string"),
+                (
+                    Some("Desktop Entry"),
+                    "Exec",
+                    Some("nautilus --new-window %U"),
+                    "string"
+                ),
+                (Some("Desktop Entry"), "Name[de]", Some("Dateien"), "string"),
+                (Some("mysqld"), "skip-networking", None, "string"),
+                (Some("mysqld"), "bind-address", Some("127.0.0.1"), "string"),
+            ]
+        );
+    }
+}
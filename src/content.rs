@@ -0,0 +1,305 @@
+use crate::cancel::CancelToken;
+use crate::models::{Condition, FileInfo};
+use crate::utils::{compile_regexp, like_to_regex, open_regular_file};
+use rayon::prelude::*;
+use regex::Regex;
+use std::io::Read;
+use std::path::Path;
+
+/// Files with a NUL byte this close to the start are binary, as git and grep decide
+const BINARY_SNIFF_BYTES: usize = 8000;
+
+/// Fields that are computed by reading file contents
+pub fn is_content_field(field: &str) -> bool {
+    matches!(
+        field,
+        "content" | "line_count" | "matching_lines" | "line_number" | "line"
+    )
+}
+
+/// Selecting `line_number` or `line` turns each file into one row per matching line
+pub fn is_line_field(field: &str) -> bool {
+    matches!(field, "line_number" | "line")
+}
+
+pub fn looks_binary(bytes: &[u8]) -> bool {
+    bytes[..bytes.len().min(BINARY_SNIFF_BYTES)].contains(&0)
+}
+
+/// The first `max_bytes` of a file as text, or `None` if it is binary,
+/// not a regular file or can't be read
+pub fn read_text(path: &Path, max_bytes: u64) -> Option<String> {
+    let mut reader = open_regular_file(path)?.take(max_bytes);
+    let mut bytes = Vec::new();
+    // Check the start first, so a large binary costs one small read
+    (&mut reader)
+        .take(BINARY_SNIFF_BYTES as u64)
+        .read_to_end(&mut bytes)
+        .ok()?;
+    if looks_binary(&bytes) {
+        return None;
+    }
+    reader.read_to_end(&mut bytes).ok()?;
+    Some(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+enum ContentTest {
+    Pattern(Regex), // LIKE or REGEXP, tried against each line
+    IsNull,         // Directories, binary and unreadable files have no content
+}
+
+struct ContentCondition {
+    test: ContentTest,
+    negated: bool,
+}
+
+/// The `content` conditions of a query, compiled once and run against each file
+struct ContentSearch {
+    conditions: Vec<ContentCondition>,
+    // Whether any condition picks out lines; without one every line matches
+    selects_lines: bool,
+}
+
+impl ContentSearch {
+    fn new(conditions: &[Condition]) -> Result<Self, String> {
+        let conditions = conditions
+            .iter()
+            .map(|condition| {
+                let test = match condition.operator.as_str() {
+                    "LIKE" => ContentTest::Pattern(
+                        like_to_regex(&condition.value)
+                            .ok_or_else(|| format!("Invalid LIKE pattern '{}'", condition.value))?,
+                    ),
+                    "REGEXP" => ContentTest::Pattern(compile_regexp(&condition.value)?),
+                    "IS" => ContentTest::IsNull,
+                    operator => {
+                        return Err(format!(
+                            "content supports LIKE, REGEXP and IS NULL, not '{}'",
+                            operator
+                        ))
+                    }
+                };
+                Ok(ContentCondition {
+                    test,
+                    negated: condition.negated,
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        let selects_lines = conditions
+            .iter()
+            .any(|c| matches!(c.test, ContentTest::Pattern(_)) && !c.negated);
+
+        Ok(ContentSearch {
+            conditions,
+            selects_lines,
+        })
+    }
+
+    /// Read a file, fill in its line counts and check the conditions.
+    /// Returns `None` if the file doesn't match, or its matching lines
+    /// (numbered from 1) when `keep_lines` is set.
+    fn scan(
+        &self,
+        file: &mut FileInfo,
+        root: &Path,
+        keep_lines: bool,
+        max_bytes: u64,
+        cancel: &CancelToken,
+    ) -> Option<Vec<(u64, String)>> {
+        // Rows that weren't read before a cancel can't be said to match
+        if cancel.is_cancelled() {
+            return None;
+        }
+        let text = match file.file_type.as_str() {
+            "file" => read_text(&file.full_path(root), max_bytes),
+            _ => None,
+        };
+        // Without content only IS NULL holds; patterns fail even when negated
+        let Some(text) = text else {
+            let matches = self.conditions.iter().all(|c| match c.test {
+                ContentTest::Pattern(_) => false,
+                ContentTest::IsNull => !c.negated,
+            });
+            return matches.then(Vec::new);
+        };
+
+        let mut found = vec![false; self.conditions.len()];
+        let mut line_count = 0;
+        let mut matching_lines = 0;
+        let mut lines = Vec::new();
+        for line in text.lines() {
+            line_count += 1;
+            let mut selected = !self.selects_lines;
+            for (condition, found) in self.conditions.iter().zip(found.iter_mut()) {
+                if let ContentTest::Pattern(regex) = &condition.test {
+                    if regex.is_match(line) {
+                        *found = true;
+                        selected |= !condition.negated;
+                    }
+                }
+            }
+            if selected {
+                matching_lines += 1;
+                if keep_lines {
+                    lines.push((line_count, line.to_string()));
+                }
+            }
+        }
+        file.line_count = Some(line_count);
+        file.matching_lines = Some(matching_lines);
+
+        let matches = self
+            .conditions
+            .iter()
+            .zip(&found)
+            .all(|(condition, found)| match condition.test {
+                ContentTest::Pattern(_) => *found != condition.negated,
+                ContentTest::IsNull => condition.negated,
+            });
+        matches.then_some(lines)
+    }
+}
+
+/// Apply the `content` conditions to rows that passed every cheaper condition,
+/// reading the files in parallel.
+///
+/// Patterns are matched line by line, like grep: `content LIKE '%TODO%'` keeps
+/// files with a line containing TODO. Every scanned row gets `line_count` and
+/// `matching_lines`; with `per_line` each file is replaced by one row for
+/// each of its matching lines, with `line_number` and `line` set.
+pub fn apply_content_conditions(
+    rows: &mut Vec<FileInfo>,
+    root: &Path,
+    conditions: &[Condition],
+    per_line: bool,
+    max_bytes: u64,
+    cancel: &CancelToken,
+) -> Result<(), String> {
+    let search = ContentSearch::new(conditions)?;
+    *rows = std::mem::take(rows)
+        .into_par_iter()
+        .flat_map_iter(|mut file| {
+            let lines = search.scan(&mut file, root, per_line, max_bytes, cancel);
+            let rows: Vec<FileInfo> = match lines {
+                None => Vec::new(),
+                Some(lines) if per_line => lines
+                    .into_iter()
+                    .map(|(number, line)| FileInfo {
+                        line_number: Some(number),
+                        line: Some(line),
+                        ..file.clone()
+                    })
+                    .collect(),
+                Some(_) => vec![file],
+            };
+            rows
+        })
+        .collect();
+    Ok(())
+}
+
+/// Fill in `line_count` and `matching_lines` (every line) for rows that
+/// haven't been read yet, in parallel
+pub fn fill_line_counts(rows: &mut [FileInfo], root: &Path, max_bytes: u64, cancel: &CancelToken) {
+    let search = ContentSearch {
+        conditions: Vec::new(),
+        selects_lines: false,
+    };
+    rows.par_iter_mut()
+        .filter(|file| file.line_count.is_none())
+        .for_each(|file| {
+            search.scan(file, root, false, max_bytes, cancel);
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn condition(operator: &str, value: &str, negated: bool) -> Condition {
+        Condition {
+            field: "content".to_string(),
+            operator: operator.to_string(),
+            value: value.to_string(),
+            negated,
+        }
+    }
+
+    fn file(name: &str) -> FileInfo {
+        FileInfo {
+            name: name.to_string(),
+            path: name.to_string(),
+            file_type: "file".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_content_conditions() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::write(root.join("a.rs"), "fn main() {\n    // TODO: and more\n}\n").unwrap();
+        fs::write(root.join("b.rs"), "fn helper() {}\n").unwrap();
+        fs::write(root.join("c.bin"), b"TODO\0\x01\x02").unwrap();
+        let cancel = CancelToken::new();
+        let all = || vec![file("a.rs"), file("b.rs"), file("c.bin")];
+
+        let mut rows = all();
+        let todo = [condition("LIKE", "%TODO%", false)];
+        apply_content_conditions(&mut rows, root, &todo, false, 1024, &cancel).unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].name, "a.rs");
+        assert_eq!(rows[0].line_count, Some(3));
+        assert_eq!(rows[0].matching_lines, Some(1));
+
+        // Binary files have no content, so they don't match negated patterns either
+        let mut rows = all();
+        let no_todo = [condition("LIKE", "%TODO%", true)];
+        apply_content_conditions(&mut rows, root, &no_todo, false, 1024, &cancel).unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].name, "b.rs");
+
+        let mut rows = all();
+        let is_null = [condition("IS", "NULL", false)];
+        apply_content_conditions(&mut rows, root, &is_null, false, 1024, &cancel).unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].name, "c.bin");
+
+        let mut rows = all();
+        let functions = [condition("REGEXP", r"^fn \w+\(", false)];
+        apply_content_conditions(&mut rows, root, &functions, true, 1024, &cancel).unwrap();
+        let lines: Vec<_> = rows
+            .iter()
+            .map(|f| {
+                (
+                    f.name.as_str(),
+                    f.line_number.unwrap(),
+                    f.line.as_deref().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            lines,
+            vec![("a.rs", 1, "fn main() {"), ("b.rs", 1, "fn helper() {}")]
+        );
+
+        // Only the first max_bytes are searched
+        let mut rows = all();
+        apply_content_conditions(&mut rows, root, &todo, false, 12, &cancel).unwrap();
+        assert!(rows.is_empty());
+
+        // A FIFO is typed as a file by the walk, but is skipped rather than blocking
+        let fifo = std::ffi::CString::new(root.join("pipe").to_str().unwrap()).unwrap();
+        assert_eq!(unsafe { libc::mkfifo(fifo.as_ptr(), 0o644) }, 0);
+        let mut rows = vec![file("pipe")];
+        apply_content_conditions(&mut rows, root, &is_null, false, 1024, &cancel).unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(read_text(&root.join("pipe"), 1024), None);
+
+        let mut rows = all();
+        let invalid = [condition("REGEXP", "(", false)];
+        assert!(apply_content_conditions(&mut rows, root, &invalid, false, 1024, &cancel).is_err());
+    }
+}
@@ -0,0 +1,238 @@
+use crate::columns::ColumnKind;
+use crate::models::{Condition, FileInfo, SqlQuery, Value};
+use crate::tables::{filter_rows, order_and_limit, Table, TableColumn, TableRow};
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// A mounted filesystem
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mount {
+    pub mount_point: String,
+    pub device: String, // What was mounted: /dev/sda1, server:/export, tmpfs, ...
+    pub fs_type: String,
+    pub read_only: bool,
+}
+
+/// The columns of `FROM disks`, in order
+const DISK_COLUMNS: [(&str, ColumnKind); 8] = [
+    ("mount_point", ColumnKind::Text),
+    ("device", ColumnKind::Text),
+    ("fs_type", ColumnKind::Text),
+    ("total", ColumnKind::Size),
+    ("available", ColumnKind::Size),
+    ("used_percent", ColumnKind::Percent),
+    ("is_removable", ColumnKind::Boolean),
+    ("read_only", ColumnKind::Boolean),
+];
+
+/// Fields of filesystem rows that come from the mount a file is on
+pub fn is_mount_field(field: &str) -> bool {
+    matches!(field, "mount_point" | "fs_type")
+}
+
+/// Every mount, in the order the kernel lists them, so one mounted over
+/// another comes after it
+#[cfg(target_os = "linux")]
+pub fn read_mounts() -> Vec<Mount> {
+    match std::fs::read_to_string("/proc/self/mountinfo") {
+        Ok(text) => parse_mountinfo(&text),
+        Err(_) => Vec::new(),
+    }
+}
+
+// Without mountinfo, sysinfo's disks are the mounts there are
+#[cfg(not(target_os = "linux"))]
+pub fn read_mounts() -> Vec<Mount> {
+    sysinfo::Disks::new_with_refreshed_list()
+        .iter()
+        .map(|disk| Mount {
+            mount_point: disk.mount_point().to_string_lossy().into_owned(),
+            device: disk.name().to_string_lossy().into_owned(),
+            fs_type: disk.file_system().to_string_lossy().into_owned(),
+            read_only: false,
+        })
+        .collect()
+}
+
+/// Parse `/proc/self/mountinfo`: `id parent major:minor root mount_point
+/// options [optional fields] - fs_type source super_options` per line
+pub fn parse_mountinfo(text: &str) -> Vec<Mount> {
+    text.lines()
+        .filter_map(|line| {
+            let (mount, filesystem) = line.split_once(" - ")?;
+            let mount: Vec<&str> = mount.split(' ').collect();
+            let filesystem: Vec<&str> = filesystem.split(' ').collect();
+            let (mount_point, options) = (mount.get(4)?, mount.get(5)?);
+            let (fs_type, device) = (filesystem.first()?, filesystem.get(1)?);
+            let super_options = filesystem.get(2).copied().unwrap_or_default();
+            let is_ro = |options: &str| options.split(',').any(|option| option == "ro");
+            Some(Mount {
+                mount_point: unescape(mount_point),
+                device: unescape(device),
+                fs_type: fs_type.to_string(),
+                read_only: is_ro(options) || is_ro(super_options),
+            })
+        })
+        .collect()
+}
+
+// Spaces, tabs, newlines and backslashes in paths are written as octal escapes, e.g. `\040`
+fn unescape(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut unescaped = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let octal = bytes.get(i + 1..i + 4).and_then(|digits| {
+            let digits = std::str::from_utf8(digits).ok()?;
+            u8::from_str_radix(digits, 8).ok()
+        });
+        match (bytes[i], octal) {
+            (b'\\', Some(byte)) => {
+                unescaped.push(byte);
+                i += 4;
+            }
+            (byte, _) => {
+                unescaped.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&unescaped).into_owned()
+}
+
+/// The mounts a query's rows are looked up in, read once per query
+pub struct MountTable {
+    mounts: Vec<Arc<Mount>>,
+    // The FROM path with symlinks resolved, which row paths are relative to
+    root: PathBuf,
+}
+
+impl MountTable {
+    pub fn for_root(root: &Path) -> Self {
+        MountTable {
+            mounts: read_mounts().into_iter().map(Arc::new).collect(),
+            root: std::fs::canonicalize(root)
+                .or_else(|_| std::path::absolute(root))
+                .unwrap_or_else(|_| root.to_path_buf()),
+        }
+    }
+
+    /// The mount a row is on: the deepest mount point above its path. Only
+    /// the FROM path's symlinks are resolved, so a followed link below it
+    /// is placed by where it appears rather than where it leads.
+    pub fn mount_of(&self, file: &FileInfo) -> Option<Arc<Mount>> {
+        let path = file.full_path(&self.root);
+        let mut best: Option<&Arc<Mount>> = None;
+        for mount in &self.mounts {
+            let deeper = best.is_none_or(|best| mount.mount_point.len() >= best.mount_point.len());
+            if deeper && path.starts_with(&mount.mount_point) {
+                best = Some(mount);
+            }
+        }
+        best.cloned()
+    }
+}
+
+/// Fill in the mount of every row that doesn't have it yet
+pub fn fill_mounts(rows: &mut [FileInfo], root: &Path) {
+    if rows.iter().all(|file| file.mount.is_some()) {
+        return;
+    }
+    let mounts = MountTable::for_root(root);
+    for file in rows.iter_mut().filter(|file| file.mount.is_none()) {
+        file.mount = mounts.mount_of(file);
+    }
+}
+
+pub(crate) fn columns() -> Vec<TableColumn> {
+    DISK_COLUMNS
+        .iter()
+        .map(|&(name, kind)| TableColumn {
+            name: name.to_string(),
+            kind,
+        })
+        .collect()
+}
+
+/// Run a SELECT over `FROM disks`: a row per mount, with sizes from
+/// sysinfo for the disks it reports and NULL for pseudo and network
+/// filesystems it leaves out
+pub fn execute_disk_query(query: &SqlQuery, conditions: &[Condition]) -> Result<Table, String> {
+    let disks = sysinfo::Disks::new_with_refreshed_list();
+    let rows = read_mounts()
+        .into_iter()
+        .map(|mount| {
+            let disk = disks
+                .iter()
+                .rev()
+                .find(|disk| disk.mount_point() == Path::new(&mount.mount_point));
+            let total = disk.map(|disk| disk.total_space());
+            let available = disk.map(|disk| disk.available_space());
+            let used_percent = match (total, available) {
+                (Some(total), Some(available)) if total > 0 => {
+                    Value::Number(total.saturating_sub(available) as f64 * 100.0 / total as f64)
+                }
+                _ => Value::Null,
+            };
+            let size = |bytes: Option<u64>| bytes.map_or(Value::Null, |b| Value::Number(b as f64));
+            TableRow::from_values(vec![
+                Value::Text(Cow::Owned(mount.mount_point)),
+                Value::Text(Cow::Owned(mount.device)),
+                Value::Text(Cow::Owned(mount.fs_type)),
+                size(total),
+                size(available),
+                used_percent,
+                disk.map_or(Value::Null, |disk| Value::Bool(disk.is_removable())),
+                Value::Bool(mount.read_only),
+            ])
+        })
+        .collect();
+    let table = filter_rows(columns(), rows, conditions)?;
+    order_and_limit(query, table)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_mountinfo() {
+        let mounts = parse_mountinfo(
+            "23 28 0:22 / /proc rw,relatime - proc proc rw\n\
+             28 1 254:0 / / rw,relatime - ext4 /dev/vda rw,discard\n\
+             29 28 254:16 / /mnt/usb\\040drive ro,nosuid shared:5 - vfat /dev/sdb1 ro\n\
+             30 28 0:50 / /srv/data rw,relatime master:1 - nfs4 fs1:/export rw,vers=4.2\n",
+        );
+        assert_eq!(mounts.len(), 4);
+        assert_eq!(
+            mounts[2],
+            Mount {
+                mount_point: "/mnt/usb drive".to_string(),
+                device: "/dev/sdb1".to_string(),
+                fs_type: "vfat".to_string(),
+                read_only: true,
+            }
+        );
+        assert_eq!(mounts[3].device, "fs1:/export");
+        assert!(!mounts[3].read_only);
+
+        let table = MountTable {
+            mounts: mounts.into_iter().map(Arc::new).collect(),
+            root: PathBuf::from("/srv"),
+        };
+        let file = |path: &str| {
+            FileInfo::new_lightweight_with_type(Path::new(path), Path::new("/srv"), false)
+        };
+        let fs_type = |path: &str| {
+            table
+                .mount_of(&file(path).unwrap())
+                .unwrap()
+                .fs_type
+                .clone()
+        };
+        assert_eq!(fs_type("/srv/data/report.txt"), "nfs4");
+        assert_eq!(fs_type("/srv/data2/report.txt"), "ext4"); // Not below /srv/data
+        assert_eq!(fs_type("/srv"), "ext4");
+    }
+}
@@ -0,0 +1,386 @@
+use crate::cancel::CancelToken;
+use crate::models::FileInfo;
+use crate::utils::open_regular_file;
+use rayon::prelude::*;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// Largest header table, interpreter path or string table read from a file.
+/// Real ones are a few KB; anything bigger is a corrupt or hostile file.
+const MAX_TABLE_BYTES: u64 = 1 << 20;
+
+const PT_DYNAMIC: u32 = 2;
+const PT_INTERP: u32 = 3;
+const PT_LOAD: u32 = 1;
+const SHT_SYMTAB: u32 = 2;
+const DT_NULL: u64 = 0;
+const DT_NEEDED: u64 = 1;
+const DT_STRTAB: u64 = 5;
+const DT_STRSZ: u64 = 10;
+
+/// What the headers of an ELF executable, shared library or object file say.
+/// Every field is `None` for files that aren't ELF.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ElfInfo {
+    pub class: Option<&'static str>,           // ELF32 or ELF64
+    pub arch: Option<String>,                  // Named as Rust names targets: x86_64, aarch64, ...
+    pub file_type: Option<&'static str>,       // rel, exec, dyn or core
+    pub is_stripped: Option<bool>,             // No .symtab section
+    pub interpreter: Option<String>,           // PT_INTERP, e.g. /lib64/ld-linux-x86-64.so.2
+    pub needed_libraries: Option<Vec<String>>, // DT_NEEDED, None without a dynamic section
+}
+
+/// Fields that are computed by reading an ELF file's headers
+pub fn is_elf_field(field: &str) -> bool {
+    matches!(
+        field,
+        "elf_class" | "elf_arch" | "elf_type" | "is_stripped" | "interpreter" | "needed_libraries"
+    )
+}
+
+// Reads integers of the file's byte order and word size
+#[derive(Clone, Copy)]
+struct Layout {
+    little_endian: bool,
+    wide: bool, // ELF64
+}
+
+impl Layout {
+    fn half(self, bytes: &[u8], offset: usize) -> Option<u64> {
+        let bytes: [u8; 2] = bytes.get(offset..offset + 2)?.try_into().ok()?;
+        let value = if self.little_endian {
+            u16::from_le_bytes(bytes)
+        } else {
+            u16::from_be_bytes(bytes)
+        };
+        Some(value as u64)
+    }
+
+    fn word(self, bytes: &[u8], offset: usize) -> Option<u64> {
+        let bytes: [u8; 4] = bytes.get(offset..offset + 4)?.try_into().ok()?;
+        let value = if self.little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        };
+        Some(value as u64)
+    }
+
+    // An address, offset or size: 4 bytes in ELF32, 8 in ELF64
+    fn addr(self, bytes: &[u8], offset: usize) -> Option<u64> {
+        if !self.wide {
+            return self.word(bytes, offset);
+        }
+        let bytes: [u8; 8] = bytes.get(offset..offset + 8)?.try_into().ok()?;
+        let value = if self.little_endian {
+            u64::from_le_bytes(bytes)
+        } else {
+            u64::from_be_bytes(bytes)
+        };
+        Some(value)
+    }
+
+    // Offset of a field that sits at `narrow` in ELF32 and `wide` in ELF64
+    fn at(self, narrow: usize, wide: usize) -> usize {
+        if self.wide {
+            wide
+        } else {
+            narrow
+        }
+    }
+}
+
+// A program header: segment type, file offset, virtual address and file size
+struct Segment {
+    kind: u32,
+    offset: u64,
+    address: u64,
+    size: u64,
+}
+
+fn read_at(file: &mut File, offset: u64, length: u64) -> Option<Vec<u8>> {
+    if length > MAX_TABLE_BYTES {
+        return None;
+    }
+    file.seek(SeekFrom::Start(offset)).ok()?;
+    let mut bytes = Vec::new();
+    file.take(length).read_to_end(&mut bytes).ok()?;
+    (bytes.len() as u64 == length).then_some(bytes)
+}
+
+// The NUL-terminated string at `offset` in a string table
+fn string_at(table: &[u8], offset: u64) -> Option<String> {
+    let bytes = table.get(usize::try_from(offset).ok()?..)?;
+    let end = bytes.iter().position(|&b| b == 0)?;
+    Some(String::from_utf8_lossy(&bytes[..end]).into_owned())
+}
+
+/// Read the ELF headers of `file`, or `None` if it doesn't start with the
+/// ELF magic number. Only the header tables, the interpreter path and the
+/// dynamic section are read, never the code.
+pub fn read_elf(file: &mut File) -> Option<ElfInfo> {
+    let header = read_at(file, 0, 64).or_else(|| read_at(file, 0, 52))?;
+    if !header.starts_with(b"\x7fELF") {
+        return None;
+    }
+    let layout = Layout {
+        wide: match header[4] {
+            1 => false,
+            2 => true,
+            _ => return None,
+        },
+        little_endian: match header[5] {
+            1 => true,
+            2 => false,
+            _ => return None,
+        },
+    };
+    let mut info = ElfInfo {
+        class: Some(if layout.wide { "ELF64" } else { "ELF32" }),
+        arch: Some(arch_name(layout.half(&header, 18)?, layout.wide)),
+        file_type: match layout.half(&header, 16)? {
+            1 => Some("rel"),
+            2 => Some("exec"),
+            3 => Some("dyn"),
+            4 => Some("core"),
+            _ => None,
+        },
+        ..ElfInfo::default()
+    };
+
+    let segments = read_segments(file, &header, layout).unwrap_or_default();
+    if let Some(interp) = segments.iter().find(|s| s.kind == PT_INTERP) {
+        info.interpreter = read_at(file, interp.offset, interp.size)
+            .and_then(|bytes| string_at(&bytes, 0))
+            .filter(|path| !path.is_empty());
+    }
+    if let Some(dynamic) = segments.iter().find(|s| s.kind == PT_DYNAMIC) {
+        info.needed_libraries = read_needed(file, dynamic, &segments, layout);
+    }
+    info.is_stripped = has_symbol_table(file, &header, layout).map(|found| !found);
+    Some(info)
+}
+
+fn read_segments(file: &mut File, header: &[u8], layout: Layout) -> Option<Vec<Segment>> {
+    let table = layout.addr(header, layout.at(28, 32))?;
+    let entry_size = layout.half(header, layout.at(42, 54))?;
+    let count = layout.half(header, layout.at(44, 56))?;
+    if table == 0 || count == 0 {
+        return Some(Vec::new());
+    }
+    let bytes = read_at(file, table, entry_size * count)?;
+    (0..count as usize)
+        .map(|i| {
+            let entry = bytes.get(i * entry_size as usize..)?;
+            Some(Segment {
+                kind: layout.word(entry, 0)? as u32,
+                offset: layout.addr(entry, layout.at(4, 8))?,
+                address: layout.addr(entry, layout.at(8, 16))?,
+                size: layout.addr(entry, layout.at(16, 32))?,
+            })
+        })
+        .collect()
+}
+
+// The DT_NEEDED entries of the dynamic section. Their names are in the
+// string table DT_STRTAB points at by address, found through the loaded
+// segments, so this also works when the section headers were removed.
+fn read_needed(
+    file: &mut File,
+    dynamic: &Segment,
+    segments: &[Segment],
+    layout: Layout,
+) -> Option<Vec<String>> {
+    let bytes = read_at(file, dynamic.offset, dynamic.size)?;
+    let entry_size = if layout.wide { 16 } else { 8 };
+    let mut needed = Vec::new();
+    let (mut strtab, mut strsz) = (None, None);
+    for entry in bytes.chunks_exact(entry_size) {
+        let value = layout.addr(entry, entry_size / 2)?;
+        match layout.addr(entry, 0)? {
+            DT_NULL => break,
+            DT_NEEDED => needed.push(value),
+            DT_STRTAB => strtab = Some(value),
+            DT_STRSZ => strsz = Some(value),
+            _ => {}
+        }
+    }
+    if needed.is_empty() {
+        return Some(Vec::new());
+    }
+    let (address, size) = (strtab?, strsz?);
+    let load = segments
+        .iter()
+        .find(|s| s.kind == PT_LOAD && s.address <= address && address - s.address < s.size)?;
+    let strings = read_at(file, load.offset.checked_add(address - load.address)?, size)?;
+    needed
+        .iter()
+        .map(|&name| string_at(&strings, name))
+        .collect()
+}
+
+// Whether any section is a symbol table. Files without section headers
+// have been stripped of those too.
+fn has_symbol_table(file: &mut File, header: &[u8], layout: Layout) -> Option<bool> {
+    let table = layout.addr(header, layout.at(32, 40))?;
+    let entry_size = layout.half(header, layout.at(46, 58))?;
+    let mut count = layout.half(header, layout.at(48, 60))?;
+    if table == 0 {
+        return Some(false);
+    }
+    // With 0xff00 or more sections, the count is in the size of section 0
+    if count == 0 {
+        let first = read_at(file, table, entry_size)?;
+        count = layout.addr(&first, layout.at(20, 32))?;
+    }
+    let bytes = read_at(file, table, entry_size.checked_mul(count)?)?;
+    Some(
+        bytes
+            .chunks_exact(entry_size.max(8) as usize)
+            .any(|section| layout.word(section, 4) == Some(SHT_SYMTAB as u64)),
+    )
+}
+
+// e_machine as Rust's target architecture names, for the common ones
+fn arch_name(machine: u64, wide: bool) -> String {
+    let name = match (machine, wide) {
+        (3, _) => "x86",
+        (62, _) => "x86_64",
+        (40, _) => "arm",
+        (183, _) => "aarch64",
+        (243, false) => "riscv32",
+        (243, true) => "riscv64",
+        (20, _) => "powerpc",
+        (21, _) => "powerpc64",
+        (8, false) => "mips",
+        (8, true) => "mips64",
+        (22, false) => "s390",
+        (22, true) => "s390x",
+        (2, _) => "sparc",
+        (43, _) => "sparc64",
+        (258, _) => "loongarch64",
+        (247, _) => "bpf",
+        (0, _) => "none",
+        _ => return format!("unknown ({})", machine),
+    };
+    name.to_string()
+}
+
+/// ELF metadata of the file at `path`, empty if it isn't a readable ELF file
+pub fn read_elf_file(path: &Path) -> ElfInfo {
+    open_regular_file(path)
+        .and_then(|mut file| read_elf(&mut file))
+        .unwrap_or_default()
+}
+
+/// Fill in the ELF metadata of every row that doesn't have it yet, in parallel
+pub fn fill_elf_info(rows: &mut [FileInfo], root: &Path, cancel: &CancelToken) {
+    rows.par_iter_mut()
+        .filter(|file| file.elf.is_none())
+        .for_each(|file| {
+            if cancel.is_cancelled() {
+                return;
+            }
+            let info = match file.file_type.as_str() {
+                "file" => read_elf_file(&file.full_path(root)),
+                _ => ElfInfo::default(),
+            };
+            file.elf = Some(Box::new(info));
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    // A stripped 64-bit little-endian executable with no section headers:
+    // the ELF header, four program headers, the interpreter path, a
+    // dynamic section and its string table
+    fn stripped_executable() -> Vec<u8> {
+        let strings = b"\0libc.so.6\0libm.so.6\0";
+        let interp = b"/lib/ld-linux-aarch64.so.1\0";
+        let (interp_at, dynamic_at) = (64 + 4 * 56, 64 + 4 * 56 + interp.len());
+        let strings_at = dynamic_at + 5 * 16;
+        let base = 0x400000u64;
+
+        let mut elf = b"\x7fELF\x02\x01\x01".to_vec();
+        elf.resize(16, 0);
+        elf.extend(2u16.to_le_bytes()); // e_type: exec
+        elf.extend(183u16.to_le_bytes()); // e_machine: aarch64
+        elf.extend(1u32.to_le_bytes());
+        elf.extend(base.to_le_bytes()); // e_entry
+        elf.extend(64u64.to_le_bytes()); // e_phoff
+        elf.extend(0u64.to_le_bytes()); // e_shoff
+        elf.extend(0u32.to_le_bytes());
+        for half in [64u16, 56, 4, 64, 0, 0] {
+            elf.extend(half.to_le_bytes());
+        }
+        let total = (strings_at + strings.len()) as u64;
+        let segment = |elf: &mut Vec<u8>, kind: u32, offset: u64, size: u64| {
+            elf.extend(kind.to_le_bytes());
+            elf.extend(4u32.to_le_bytes());
+            for value in [offset, base + offset, base + offset, size, size, 8] {
+                elf.extend(value.to_le_bytes());
+            }
+        };
+        segment(&mut elf, PT_LOAD, 0, total);
+        segment(&mut elf, PT_INTERP, interp_at as u64, interp.len() as u64);
+        segment(&mut elf, PT_DYNAMIC, dynamic_at as u64, 5 * 16);
+        segment(&mut elf, 0x6474e551, 0, 0); // PT_GNU_STACK
+        elf.extend(interp);
+        for (tag, value) in [
+            (DT_NEEDED, 1),
+            (DT_NEEDED, 11),
+            (DT_STRTAB, base + strings_at as u64),
+            (DT_STRSZ, strings.len() as u64),
+            (DT_NULL, 0),
+        ] {
+            elf.extend(tag.to_le_bytes());
+            elf.extend(value.to_le_bytes());
+        }
+        elf.extend(strings);
+        elf
+    }
+
+    #[test]
+    fn test_read_stripped_executable() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("app");
+        fs::write(&path, stripped_executable()).unwrap();
+        let info = read_elf_file(&path);
+        assert_eq!(info.class, Some("ELF64"));
+        assert_eq!(info.arch.as_deref(), Some("aarch64"));
+        assert_eq!(info.file_type, Some("exec"));
+        assert_eq!(info.is_stripped, Some(true));
+        assert_eq!(
+            info.interpreter.as_deref(),
+            Some("/lib/ld-linux-aarch64.so.1")
+        );
+        assert_eq!(
+            info.needed_libraries,
+            Some(vec!["libc.so.6".to_string(), "libm.so.6".to_string()])
+        );
+
+        let truncated = temp_dir.path().join("truncated");
+        fs::write(&truncated, &stripped_executable()[..100]).unwrap();
+        let info = read_elf_file(&truncated);
+        assert_eq!(info.arch.as_deref(), Some("aarch64"));
+        assert_eq!(info.interpreter, None);
+
+        fs::write(&path, "#!/bin/sh\necho hi\n").unwrap();
+        assert_eq!(read_elf_file(&path), ElfInfo::default());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_read_test_binary() {
+        // Test binaries are built with their symbol tables
+        let info = read_elf_file(&std::env::current_exe().unwrap());
+        assert_eq!(info.arch.as_deref(), Some(std::env::consts::ARCH));
+        assert_eq!(info.is_stripped, Some(false));
+        assert!(matches!(info.file_type, Some("exec" | "dyn")));
+    }
+}
@@ -0,0 +1,262 @@
+use std::sync::OnceLock;
+
+/// Extensions made of several dot-separated parts that belong together,
+/// so `archive.tar.gz` has the full extension `tar.gz` rather than `gz`
+pub const DEFAULT_COMPOUND_EXTENSIONS: &[&str] = &[
+    // Compressed tarballs
+    "tar.gz",
+    "tar.bz2",
+    "tar.xz",
+    "tar.zst",
+    "tar.lz",
+    "tar.lz4",
+    "tar.lzma",
+    "tar.z",
+    "pkg.tar.zst",
+    "pkg.tar.xz",
+    // Web build output and type declarations
+    "min.js",
+    "min.css",
+    "js.map",
+    "css.map",
+    "d.ts",
+    "d.mts",
+    "d.cts",
+    // Test files named after the module they test
+    "test.js",
+    "test.ts",
+    "test.jsx",
+    "test.tsx",
+    "spec.js",
+    "spec.ts",
+    "spec.jsx",
+    "spec.tsx",
+    // Backups and templates
+    "tfstate.backup",
+    "html.erb",
+    "blade.php",
+];
+
+/// The compound extensions in use, longest first so `pkg.tar.zst` wins over `tar.zst`
+static COMPOUND_EXTENSIONS: OnceLock<Vec<String>> = OnceLock::new();
+
+/// Add compound extensions (e.g. `--compound-extension bak.gz`) on top of the
+/// defaults. Only takes effect before the first extension has been looked up.
+pub fn add_compound_extensions(extra: &[String]) {
+    let _ = COMPOUND_EXTENSIONS.set(compound_table(extra));
+}
+
+fn compound_extensions() -> &'static [String] {
+    COMPOUND_EXTENSIONS.get_or_init(|| compound_table(&[]))
+}
+
+/// The default compound extensions plus `extra`, normalised and longest first
+pub fn compound_table(extra: &[String]) -> Vec<String> {
+    let mut table: Vec<String> = DEFAULT_COMPOUND_EXTENSIONS
+        .iter()
+        .map(|extension| extension.to_string())
+        .chain(
+            extra
+                .iter()
+                .map(|extension| extension.trim().trim_start_matches('.').to_lowercase()),
+        )
+        .filter(|extension| !extension.is_empty())
+        .collect();
+    table.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+    table.dedup();
+    table
+}
+
+// Byte position of the dot that starts the full extension, if the name has one
+fn extension_start(name: &str, table: &[String]) -> Option<usize> {
+    let compound = table.iter().find_map(|extension| {
+        let start = name.len().checked_sub(extension.len() + 1)?;
+        let suffix = name.get(start..)?;
+        let matches = suffix.starts_with('.') && suffix[1..].eq_ignore_ascii_case(extension);
+        // Something has to come before it, or the whole name is a dotfile like `.tar.gz`
+        (matches && start > 0).then_some(start)
+    });
+    // Otherwise the last segment, with the same rules as `FileInfo::extract_extension`
+    compound.or_else(|| {
+        name.rfind('.')
+            .filter(|&dot| dot > 0 && dot < name.len() - 1)
+    })
+}
+
+/// Everything after the known compound extension or the last dot, lowercased
+/// (`tar.gz`, `d.ts`, `rs`). `None` for directories, dotfiles and names without one.
+pub fn full_extension(name: &str, is_directory: bool) -> Option<String> {
+    if is_directory {
+        return None;
+    }
+    let start = extension_start(name, compound_extensions())?;
+    Some(name[start + 1..].to_lowercase())
+}
+
+/// The name without its full extension (`archive` for `archive.tar.gz`).
+/// Directories and names without an extension are their own stem.
+pub fn stem(name: &str, is_directory: bool) -> &str {
+    if is_directory {
+        return name;
+    }
+    match extension_start(name, compound_extensions()) {
+        Some(start) => &name[..start],
+        None => name,
+    }
+}
+
+/// Programming or markup language of a source file, from its name or extension
+pub fn language(name: &str, extension: Option<&str>) -> Option<&'static str> {
+    let by_name = match name.to_lowercase().as_str() {
+        "makefile" | "gnumakefile" => Some("Makefile"),
+        "dockerfile" | "containerfile" => Some("Dockerfile"),
+        "cmakelists.txt" => Some("CMake"),
+        "rakefile" | "gemfile" => Some("Ruby"),
+        "justfile" => Some("Just"),
+        _ => None,
+    };
+    by_name.or_else(|| {
+        Some(match extension? {
+            "rs" => "Rust",
+            "c" | "h" => "C",
+            "cc" | "cpp" | "cxx" | "c++" | "hh" | "hpp" | "hxx" => "C++",
+            "cs" => "C#",
+            "m" => "Objective-C",
+            "mm" => "Objective-C++",
+            "go" => "Go",
+            "java" => "Java",
+            "kt" | "kts" => "Kotlin",
+            "scala" | "sc" => "Scala",
+            "groovy" | "gradle" => "Groovy",
+            "swift" => "Swift",
+            "dart" => "Dart",
+            "zig" => "Zig",
+            "nim" => "Nim",
+            "py" | "pyi" | "pyw" => "Python",
+            "rb" => "Ruby",
+            "pl" | "pm" => "Perl",
+            "php" => "PHP",
+            "lua" => "Lua",
+            "r" => "R",
+            "jl" => "Julia",
+            "js" | "mjs" | "cjs" => "JavaScript",
+            "jsx" => "JSX",
+            "ts" | "mts" | "cts" => "TypeScript",
+            "tsx" => "TSX",
+            "vue" => "Vue",
+            "svelte" => "Svelte",
+            "html" | "htm" => "HTML",
+            "css" => "CSS",
+            "scss" => "SCSS",
+            "sass" => "Sass",
+            "less" => "Less",
+            "hs" => "Haskell",
+            "ml" | "mli" => "OCaml",
+            "fs" | "fsi" | "fsx" => "F#",
+            "ex" | "exs" => "Elixir",
+            "erl" | "hrl" => "Erlang",
+            "clj" | "cljs" | "cljc" | "edn" => "Clojure",
+            "el" => "Emacs Lisp",
+            "lisp" | "lsp" => "Common Lisp",
+            "scm" | "ss" => "Scheme",
+            "elm" => "Elm",
+            "sh" | "bash" | "zsh" | "ksh" => "Shell",
+            "fish" => "Fish",
+            "ps1" | "psm1" => "PowerShell",
+            "bat" | "cmd" => "Batchfile",
+            "vim" => "Vim Script",
+            "sql" => "SQL",
+            "s" | "asm" => "Assembly",
+            "f" | "f90" | "f95" | "for" => "Fortran",
+            "pas" | "pp" => "Pascal",
+            "cmake" => "CMake",
+            "mk" | "mak" => "Makefile",
+            "nix" => "Nix",
+            "tf" | "hcl" => "HCL",
+            "proto" => "Protocol Buffers",
+            "graphql" | "gql" => "GraphQL",
+            "md" | "markdown" => "Markdown",
+            "rst" => "reStructuredText",
+            "tex" => "TeX",
+            "json" => "JSON",
+            "yaml" | "yml" => "YAML",
+            "toml" => "TOML",
+            "xml" | "xsd" | "xsl" => "XML",
+            _ => return None,
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_full_extension_and_stem() {
+        assert_eq!(
+            full_extension("archive.tar.gz", false),
+            Some("tar.gz".to_string())
+        );
+        assert_eq!(stem("archive.tar.gz", false), "archive");
+        assert_eq!(
+            full_extension("Release.TAR.XZ", false),
+            Some("tar.xz".to_string())
+        );
+        assert_eq!(stem("Release.TAR.XZ", false), "Release");
+        assert_eq!(
+            full_extension("core.pkg.tar.zst", false),
+            Some("pkg.tar.zst".to_string())
+        );
+        assert_eq!(
+            full_extension("index.d.ts", false),
+            Some("d.ts".to_string())
+        );
+
+        // Only known compound extensions; otherwise the last segment as before
+        assert_eq!(
+            full_extension("my.notes.txt", false),
+            Some("txt".to_string())
+        );
+        assert_eq!(stem("my.notes.txt", false), "my.notes");
+        assert_eq!(full_extension("data.gz", false), Some("gz".to_string()));
+
+        // Dotfiles keep their leading dot in the stem
+        assert_eq!(full_extension(".bashrc", false), None);
+        assert_eq!(stem(".bashrc", false), ".bashrc");
+        assert_eq!(
+            full_extension(".eslintrc.json", false),
+            Some("json".to_string())
+        );
+        assert_eq!(stem(".eslintrc.json", false), ".eslintrc");
+        assert_eq!(full_extension(".tar.gz", false), Some("gz".to_string()));
+
+        assert_eq!(full_extension("Makefile", false), None);
+        assert_eq!(stem("Makefile", false), "Makefile");
+        assert_eq!(full_extension("file.", false), None);
+        assert_eq!(full_extension("backup.tar.gz", true), None);
+        assert_eq!(stem("backup.tar.gz", true), "backup.tar.gz");
+    }
+
+    #[test]
+    fn test_compound_table() {
+        let table = compound_table(&[".BAK.gz".to_string(), "tar.gz".to_string(), " ".to_string()]);
+        assert!(table.contains(&"bak.gz".to_string()));
+        assert_eq!(table.iter().filter(|e| *e == "tar.gz").count(), 1);
+        assert!(!table.iter().any(|e| e.is_empty()));
+        // Longest first, so the most specific extension is found first
+        assert_eq!(table[0], "tfstate.backup");
+        assert_eq!(extension_start("db.bak.gz", &table), Some(2));
+        assert_eq!(extension_start("db.bak.gz", &compound_table(&[])), Some(6));
+    }
+
+    #[test]
+    fn test_language() {
+        assert_eq!(language("main.rs", Some("rs")), Some("Rust"));
+        assert_eq!(language("index.d.ts", Some("ts")), Some("TypeScript"));
+        assert_eq!(language("vector.hpp", Some("hpp")), Some("C++"));
+        assert_eq!(language("Makefile", None), Some("Makefile"));
+        assert_eq!(language("CMakeLists.txt", Some("txt")), Some("CMake"));
+        assert_eq!(language("notes.txt", Some("txt")), None);
+        assert_eq!(language("README", None), None);
+    }
+}
@@ -1,21 +1,81 @@
+use crate::aggregate::{is_aggregate_query, parse_aggregate};
 use crate::applications::execute_application_query;
-use crate::models::{Condition, FileInfo, ProcessInfo, QueryResult, QueryType, SqlQuery};
+use crate::archives::{archive_entry_rows, list_archive, ArchiveFormat, ArchivePath};
+use crate::cancel::CancelToken;
+use crate::content::{apply_content_conditions, fill_line_counts, is_content_field, is_line_field};
+use crate::disks::{execute_disk_query, fill_mounts, is_mount_field, MountTable};
+use crate::elf::{fill_elf_info, is_elf_field};
+use crate::git::{fill_git_info, is_git_field, is_history_field};
+use crate::hashing::{apply_hash_conditions, fill_hashes, HashKind};
+use crate::ignores::IgnoreRules;
+use crate::images::{fill_image_info, is_image_field};
+use crate::limits::QueryLimits;
+use crate::mime::{fill_mime_types, is_mime_field};
+use crate::models::{
+    Condition, FileInfo, FromOptions, ProcessInfo, QueryResult, QueryType, SqlQuery,
+};
 use crate::network::execute_network_query;
-use crate::parser::parse_compound_conditions;
+use crate::parser::{parse_compound_conditions, parse_table_source};
+use crate::predicate::{compile_conditions, Predicate};
 use crate::processes::execute_process_query;
-use crate::utils::{compare_strings, evaluate_single_condition, like_match, sort_results};
+use crate::tables::execute_table_query;
+use crate::utils::sort_results;
 use crate::web::{execute_web_query, is_url};
+use crate::xattrs::{fill_xattrs, is_xattr_field};
 use rayon::prelude::*;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::io::{self, Write};
-use std::path::Path;
-use std::sync::Mutex;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, OnceLock};
 
 pub fn execute_query(query: &SqlQuery) -> Result<QueryResult, String> {
+    execute_query_with_cancel(query, &CancelToken::new())
+}
+
+/// Execute a query that stops early once `cancel` fires.
+///
+/// Traversal and process collection return the rows gathered so far, so callers
+/// should check `cancel.is_cancelled()` to tell partial results from complete ones.
+pub fn execute_query_with_cancel(
+    query: &SqlQuery,
+    cancel: &CancelToken,
+) -> Result<QueryResult, String> {
+    execute_query_with_limits(query, cancel, &QueryLimits::default())
+}
+
+/// Execute a query under explicit resource limits; exceeding one is an error
+pub fn execute_query_with_limits(
+    query: &SqlQuery,
+    cancel: &CancelToken,
+    limits: &QueryLimits,
+) -> Result<QueryResult, String> {
+    execute_query_at_depth(query, cancel, limits, 0)
+}
+
+fn execute_query_at_depth(
+    query: &SqlQuery,
+    cancel: &CancelToken,
+    limits: &QueryLimits,
+    depth: usize,
+) -> Result<QueryResult, String> {
+    limits.check_subquery_depth(depth)?;
+    let results = execute_single_query(query, cancel, limits, depth)?;
+    limits.check_rows(results.len())?;
+    Ok(results)
+}
+
+fn execute_single_query(
+    query: &SqlQuery,
+    cancel: &CancelToken,
+    limits: &QueryLimits,
+    depth: usize,
+) -> Result<QueryResult, String> {
     // Handle DELETE queries
     if query.query_type == QueryType::Delete {
-        return execute_delete_query(query);
+        return execute_delete_query(query, cancel, limits);
     }
 
     // Execute subqueries first and store their results
@@ -24,7 +84,7 @@ pub fn execute_query(query: &SqlQuery) -> Result<QueryResult, String> {
 
     // Execute WHERE subqueries
     for subquery in &query.where_subqueries {
-        let result = execute_query(&subquery.query)?;
+        let result = execute_query_at_depth(&subquery.query, cancel, limits, depth + 1)?;
         subquery_results.insert(format!("__SUBQUERY_{}__", subquery_idx), result.clone());
         subquery_results.insert(
             format!("__EXISTS_SUBQUERY_{}__", subquery_idx),
@@ -36,19 +96,19 @@ pub fn execute_query(query: &SqlQuery) -> Result<QueryResult, String> {
 
     // Execute SELECT subqueries
     for subquery in &query.select_subqueries {
-        let result = execute_query(&subquery.query)?;
+        let result = execute_query_at_depth(&subquery.query, cancel, limits, depth + 1)?;
         subquery_results.insert(format!("__SELECT_SUBQUERY_{}__", subquery_idx), result);
         subquery_idx += 1;
     }
 
     // Check if this is a web query
     if is_url(&query.from_path) {
-        return execute_web_query(query);
+        return execute_web_query(query, cancel);
     }
 
     // Check if this is a process query
     if query.from_path == "ps" {
-        let results = execute_process_query_with_subqueries(query, &subquery_results)?;
+        let results = execute_process_query_with_subqueries(query, &subquery_results, cancel)?;
         return Ok(QueryResult::Processes(results));
     }
 
@@ -64,11 +124,6 @@ pub fn execute_query(query: &SqlQuery) -> Result<QueryResult, String> {
         return Ok(QueryResult::Applications(results));
     }
 
-    let root_path = std::path::PathBuf::from(&query.from_path);
-    if !root_path.exists() {
-        return Err(format!("Path does not exist: {}", query.from_path));
-    }
-
     // Parse WHERE conditions for early filtering, processing subquery placeholders
     let conditions = if let Some(where_clause) = &query.where_clause {
         let processed_where = process_where_subquery_placeholders(where_clause, &subquery_results);
@@ -77,29 +132,113 @@ pub fn execute_query(query: &SqlQuery) -> Result<QueryResult, String> {
         Vec::new()
     };
 
-    let mut results = collect_files_recursive(&root_path, &root_path, &conditions)?;
+    // Check if this is a query over the mounted filesystems
+    if query.from_path == "disks" {
+        let table = execute_disk_query(query, &conditions)?;
+        return Ok(QueryResult::Table(table));
+    }
+
+    // Check if this is a table file such as csv('data.csv')
+    if let Some(source) = parse_table_source(&query.from_path)? {
+        let table = execute_table_query(query, &source, &conditions, cancel, limits)?;
+        return Ok(QueryResult::Table(table));
+    }
+
+    // A path inside an archive doesn't exist on disk, but is listed like a directory
+    let root_path = std::path::PathBuf::from(&query.from_path);
+    if !root_path.exists() && ArchivePath::parse(&root_path).is_none() {
+        return Err(format!("Path does not exist: {}", query.from_path));
+    }
+
+    // Selecting `line_number` or `line` gives one row per matching line
+    let columns: Vec<&str> = query
+        .select_fields
+        .iter()
+        .chain(&query.order_by)
+        .map(|field| parse_aggregate(field).map_or(field.as_str(), |(_, column)| column))
+        .collect();
+    let per_line = columns.iter().any(|column| is_line_field(column));
+    let totals = columns.iter().any(|column| is_total_field(column));
+
+    let mut results = collect_matching_files(
+        &root_path,
+        conditions,
+        &query.from_options,
+        per_line,
+        totals,
+        cancel,
+        limits,
+    )?;
 
     // Apply ORDER BY (only remaining filtering needed)
     if let Some(order_by) = &query.order_by {
+        fill_file_column(&mut results, &root_path, order_by, cancel, limits);
         sort_results(&mut results, order_by, &query.order_direction)?;
     }
 
-    // Apply LIMIT
+    // Apply LIMIT; an aggregate query summarises all rows instead
     if let Some(limit) = query.limit {
-        results.truncate(limit);
+        if !is_aggregate_query(&query.select_fields) {
+            results.truncate(limit);
+        }
+    }
+
+    // Columns read from the files that are only displayed are computed for the rows that are left
+    for field in &query.select_fields {
+        let column = parse_aggregate(field).map_or(field.as_str(), |(_, column)| column);
+        fill_file_column(&mut results, &root_path, column, cancel, limits);
     }
 
     Ok(QueryResult::Files(results))
 }
 
-fn execute_delete_query(query: &SqlQuery) -> Result<QueryResult, String> {
+/// Compute a column that is read from the files themselves, for the rows
+/// that don't have it yet
+fn fill_file_column(
+    results: &mut [FileInfo],
+    root_path: &Path,
+    field: &str,
+    cancel: &CancelToken,
+    limits: &QueryLimits,
+) {
+    if let Some(kind) = HashKind::for_field(field) {
+        fill_hashes(results, root_path, kind, cancel);
+    } else if is_mime_field(field) {
+        fill_mime_types(results, root_path, cancel);
+    } else if is_image_field(field) {
+        fill_image_info(results, root_path, cancel);
+    } else if is_elf_field(field) {
+        fill_elf_info(results, root_path, cancel);
+    } else if is_xattr_field(field) {
+        fill_xattrs(results, root_path, cancel);
+    } else if is_git_field(field) {
+        fill_git_info(results, root_path, is_history_field(field), cancel);
+    } else if is_mount_field(field) {
+        fill_mounts(results, root_path);
+    } else if matches!(field, "line_count" | "matching_lines") {
+        fill_line_counts(results, root_path, limits.max_read_bytes, cancel);
+    }
+}
+
+fn execute_delete_query(
+    query: &SqlQuery,
+    cancel: &CancelToken,
+    limits: &QueryLimits,
+) -> Result<QueryResult, String> {
     // Handle process deletion
     if query.from_path == "ps" {
         return execute_delete_process_query(query);
     }
 
+    if parse_table_source(&query.from_path)?.is_some() {
+        return Err("Cannot delete rows from a table file".to_string());
+    }
+
     // Handle filesystem deletion
     let root_path = std::path::PathBuf::from(&query.from_path);
+    if query.from_options.include_archives || ArchivePath::parse(&root_path).is_some() {
+        return Err("Cannot delete entries inside archives".to_string());
+    }
     if !root_path.exists() {
         return Err(format!("Path does not exist: {}", query.from_path));
     }
@@ -112,7 +251,20 @@ fn execute_delete_query(query: &SqlQuery) -> Result<QueryResult, String> {
     };
 
     // Collect files to delete
-    let files_to_delete = collect_files_recursive(&root_path, &root_path, &conditions)?;
+    let files_to_delete = collect_matching_files(
+        &root_path,
+        conditions,
+        &query.from_options,
+        false,
+        false,
+        cancel,
+        limits,
+    )?;
+
+    // A partial match set is fine to display but not to delete
+    if cancel.is_cancelled() {
+        return Err(format!("{}; nothing was deleted", cancel.error()));
+    }
 
     if files_to_delete.is_empty() {
         return Ok(QueryResult::Files(Vec::new()));
@@ -138,7 +290,8 @@ fn execute_delete_query(query: &SqlQuery) -> Result<QueryResult, String> {
             .read_line(&mut input)
             .map_err(|e| format!("Failed to read input: {}", e))?;
 
-        if !input.trim().to_lowercase().starts_with('y') {
+        // Ctrl-C at the prompt cancels the token rather than killing the process
+        if cancel.is_cancelled() || !input.trim().to_lowercase().starts_with('y') {
             println!("Deletion cancelled.");
             return Ok(QueryResult::Files(Vec::new()));
         }
@@ -148,7 +301,9 @@ fn execute_delete_query(query: &SqlQuery) -> Result<QueryResult, String> {
     let mut deleted_files = Vec::new();
     for file_info in &files_to_delete {
         let full_path = root_path.join(&file_info.path);
-        if full_path.is_dir() {
+        // Check the entry itself so deleting a link never touches its target
+        let is_dir = fs::symlink_metadata(&full_path).is_ok_and(|m| m.is_dir());
+        if is_dir {
             if let Err(e) = fs::remove_dir_all(&full_path) {
                 eprintln!("Failed to delete directory {}: {}", full_path.display(), e);
             } else {
@@ -181,6 +336,7 @@ fn execute_delete_process_query(query: &SqlQuery) -> Result<QueryResult, String>
             .without_environ(),
     );
 
+    let predicate = compile_conditions::<ProcessInfo>(&conditions);
     let mut processes_to_kill = Vec::new();
 
     for (pid, process) in system.processes() {
@@ -199,7 +355,7 @@ fn execute_delete_process_query(query: &SqlQuery) -> Result<QueryResult, String>
             },
         );
 
-        if evaluate_process_conditions(&process_info, &conditions) {
+        if predicate.matches(&process_info) {
             processes_to_kill.push((pid, process_info));
         }
     }
@@ -225,190 +381,540 @@ fn execute_delete_process_query(query: &SqlQuery) -> Result<QueryResult, String>
     Ok(QueryResult::Processes(killed_processes))
 }
 
-fn evaluate_process_conditions(
-    process: &crate::models::ProcessInfo,
+/// Walk `root_path` and return the entries matching every condition,
+/// split into one row per matching line when `per_line` is set and with
+/// directory totals when `totals` is set or a condition uses them
+fn collect_matching_files(
+    root_path: &Path,
+    conditions: Vec<Condition>,
+    options: &FromOptions,
+    per_line: bool,
+    totals: bool,
+    cancel: &CancelToken,
+    limits: &QueryLimits,
+) -> Result<Vec<FileInfo>, String> {
+    // Conditions on file contents are checked after the walk, so only files
+    // that passed every cheaper condition are read
+    let (hash_conditions, conditions): (Vec<Condition>, Vec<Condition>) = conditions
+        .into_iter()
+        .partition(|c| HashKind::for_field(&c.field).is_some());
+    let (mime_conditions, conditions): (Vec<Condition>, Vec<Condition>) = conditions
+        .into_iter()
+        .partition(|c| is_mime_field(&c.field));
+    let (image_conditions, conditions): (Vec<Condition>, Vec<Condition>) = conditions
+        .into_iter()
+        .partition(|c| is_image_field(&c.field));
+    let (elf_conditions, conditions): (Vec<Condition>, Vec<Condition>) =
+        conditions.into_iter().partition(|c| is_elf_field(&c.field));
+    let (xattr_conditions, conditions): (Vec<Condition>, Vec<Condition>) = conditions
+        .into_iter()
+        .partition(|c| is_xattr_field(&c.field));
+    let (git_conditions, conditions): (Vec<Condition>, Vec<Condition>) =
+        conditions.into_iter().partition(|c| is_git_field(&c.field));
+    let (content_conditions, conditions): (Vec<Condition>, Vec<Condition>) = conditions
+        .into_iter()
+        .partition(|c| is_content_field(&c.field));
+
+    let totals = totals || conditions.iter().any(|c| is_total_field(&c.field));
+    let mut results = match ArchivePath::parse(root_path) {
+        // An archive's entry list holds all the metadata there is, so every
+        // condition is checked at once
+        Some(archive) => {
+            let predicate = compile_conditions::<FileInfo>(&conditions);
+            let mut rows = list_archive(&archive, root_path, options.skip_hidden, cancel)?;
+            if conditions.iter().any(|c| is_mount_field(&c.field)) {
+                fill_mounts(&mut rows, root_path);
+            }
+            rows.retain(|row| predicate.matches(row));
+            limits.check_rows(rows.len())?;
+            rows
+        }
+        None => collect_files_recursive(
+            root_path,
+            root_path,
+            &conditions,
+            options,
+            totals,
+            cancel,
+            limits,
+        )?,
+    };
+
+    // Sniffing reads a few KB per file, so it goes before the full reads below
+    if !mime_conditions.is_empty() {
+        fill_mime_types(&mut results, root_path, cancel);
+        let predicate = compile_conditions::<FileInfo>(&mime_conditions);
+        results.retain(|file| predicate.matches(file));
+    }
+    if !image_conditions.is_empty() {
+        fill_image_info(&mut results, root_path, cancel);
+        let predicate = compile_conditions::<FileInfo>(&image_conditions);
+        results.retain(|file| predicate.matches(file));
+    }
+    if !elf_conditions.is_empty() {
+        fill_elf_info(&mut results, root_path, cancel);
+        let predicate = compile_conditions::<FileInfo>(&elf_conditions);
+        results.retain(|file| predicate.matches(file));
+    }
+    if !xattr_conditions.is_empty() {
+        fill_xattrs(&mut results, root_path, cancel);
+        let predicate = compile_conditions::<FileInfo>(&xattr_conditions);
+        results.retain(|file| predicate.matches(file));
+    }
+    // One status read per repository, and the history only if it is asked about
+    if !git_conditions.is_empty() {
+        let history = git_conditions.iter().any(|c| is_history_field(&c.field));
+        fill_git_info(&mut results, root_path, history, cancel);
+        let predicate = compile_conditions::<FileInfo>(&git_conditions);
+        results.retain(|file| predicate.matches(file));
+    }
+
+    if per_line || !content_conditions.is_empty() {
+        // `content` patterns are run by the search; conditions on the columns
+        // it fills in, like line_count > 1000, are checked afterwards
+        let (searches, line_conditions): (Vec<Condition>, Vec<Condition>) = content_conditions
+            .into_iter()
+            .partition(|c| c.field == "content");
+        let max_bytes = limits.max_read_bytes;
+        apply_content_conditions(
+            &mut results,
+            root_path,
+            &searches,
+            per_line,
+            max_bytes,
+            cancel,
+        )?;
+        let predicate = compile_conditions::<FileInfo>(&line_conditions);
+        results.retain(|file| predicate.matches(file));
+        limits.check_rows(results.len())?;
+    }
+
+    apply_hash_conditions(&mut results, root_path, &hash_conditions, cancel);
+    Ok(results)
+}
+
+fn collect_files_recursive(
+    root_path: &Path,
+    current_path: &Path,
     conditions: &[Condition],
-) -> bool {
-    for condition in conditions {
-        let result = evaluate_single_process_condition(process, condition);
-        let final_result = if condition.negated { !result } else { result };
+    options: &FromOptions,
+    totals: bool,
+    cancel: &CancelToken,
+    limits: &QueryLimits,
+) -> Result<Vec<FileInfo>, String> {
+    // The starting path has no directory entry to take a file type from, so it
+    // costs one stat; everything below it is typed from `read_dir` entries
+    let metadata = match fs::metadata(current_path) {
+        Ok(m) => m,
+        Err(_) => return Ok(Vec::new()), // Treat permission errors like file doesn't exist
+    };
+
+    let walk = WalkContext::new(root_path, conditions, options, totals, cancel, limits);
+    let inherited = Inherited {
+        dir_ids: Vec::new(),
+        ignores: options
+            .respect_ignores
+            .then(|| IgnoreRules::for_root(&walk.absolute_root)),
+    };
+    walk.collect_entry(
+        current_path,
+        metadata.file_type(),
+        Some(metadata),
+        &inherited,
+    );
+
+    if let Some(error) = walk.limit_error.into_inner() {
+        return Err(error);
+    }
+    Ok(walk.results.into_inner().unwrap())
+}
+
+/// Device and inode of a directory, used to spot symlink cycles
+type DirId = (u64, u64);
+
+fn dir_id(metadata: &fs::Metadata) -> DirId {
+    (metadata.dev(), metadata.ino())
+}
+
+/// Disk usage and number of files below a directory, added up bottom-up
+#[derive(Debug, Clone, Copy, Default)]
+struct Totals {
+    bytes: u64,
+    files: u64,
+}
 
-        if !final_result {
-            return false;
+impl std::ops::Add for Totals {
+    type Output = Totals;
+
+    fn add(self, other: Totals) -> Totals {
+        Totals {
+            bytes: self.bytes + other.bytes,
+            files: self.files + other.files,
         }
     }
-    true
 }
 
-fn evaluate_single_process_condition(
-    process: &crate::models::ProcessInfo,
-    condition: &Condition,
-) -> bool {
-    match condition.field.as_str() {
-        "pid" => {
-            if condition.operator == "LIKE" {
-                like_match(&process.pid, &condition.value)
-            } else {
-                compare_strings(&process.pid, &condition.operator, &condition.value)
-            }
+fn is_total_field(field: &str) -> bool {
+    matches!(field, "total_size" | "file_count")
+}
+
+/// What a directory hands down to the entries below it
+struct Inherited {
+    // Identities of the enclosing directories, tracked when following symlinks
+    dir_ids: Vec<DirId>,
+    // Ignore rules in effect, when respecting ignore files
+    ignores: Option<IgnoreRules>,
+}
+
+/// State shared by every directory visited during one traversal
+struct WalkContext<'a> {
+    root_path: &'a Path,
+    // The FROM path made absolute, for matching ignore files
+    absolute_root: PathBuf,
+    // Path conditions that, once failed, fail for every path below too
+    path_filter: Predicate<FileInfo>,
+    // Mounts to place entries on, when conditions name mount_point or fs_type
+    mounts: Option<MountTable>,
+    // Conditions excluding a mount, which also exclude everything below a
+    // directory that fails them, like `find -fstype nfs -prune`
+    mount_filter: Predicate<FileInfo>,
+    // Conditions on name/path/type/depth/extension, checked before any stat
+    cheap_filter: Predicate<FileInfo>,
+    // Everything else, checked once the entry's metadata has been fetched
+    full_filter: Predicate<FileInfo>,
+    follow_symlinks: bool,
+    skip_hidden: bool,
+    include_archives: bool,
+    // Whether directories get `total_size` and `file_count`, which needs every entry stat'ed
+    totals: bool,
+    // Hard-linked files already added to a total, so each inode counts once as in du
+    linked_files: Mutex<HashSet<(u64, u64)>>,
+    cancel: &'a CancelToken,
+    limits: &'a QueryLimits,
+    dirs_opened: AtomicUsize,
+    // First limit exceeded; once set the walk unwinds and the query fails
+    limit_error: OnceLock<String>,
+    results: Mutex<Vec<FileInfo>>,
+}
+
+impl<'a> WalkContext<'a> {
+    fn new(
+        root_path: &'a Path,
+        conditions: &[Condition],
+        options: &FromOptions,
+        totals: bool,
+        cancel: &'a CancelToken,
+        limits: &'a QueryLimits,
+    ) -> Self {
+        let (cheap_conditions, full_conditions): (Vec<Condition>, Vec<Condition>) = conditions
+            .iter()
+            .cloned()
+            .partition(|c| FileInfo::is_lightweight_field(&c.field) || is_mount_field(&c.field));
+        let (mount_conditions, cheap_conditions): (Vec<Condition>, Vec<Condition>) =
+            cheap_conditions.into_iter().partition(|c| {
+                let excludes =
+                    matches!(c.operator.as_str(), "!=" | "<>") || (c.negated && c.operator != "IS");
+                is_mount_field(&c.field) && excludes
+            });
+        let mounts = conditions
+            .iter()
+            .any(|c| is_mount_field(&c.field))
+            .then(|| MountTable::for_root(root_path));
+        // If a directory's path matches a pattern ending in '%', so does every
+        // path below it, so `path NOT LIKE 'x%'` can skip whole subtrees
+        let path_conditions: Vec<Condition> = cheap_conditions
+            .iter()
+            .filter(|c| {
+                c.field == "path" && c.operator == "LIKE" && c.negated && c.value.ends_with('%')
+            })
+            .cloned()
+            .collect();
+
+        WalkContext {
+            root_path,
+            absolute_root: std::path::absolute(root_path)
+                .unwrap_or_else(|_| root_path.to_path_buf()),
+            path_filter: compile_conditions(&path_conditions),
+            mounts,
+            mount_filter: compile_conditions(&mount_conditions),
+            cheap_filter: compile_conditions(&cheap_conditions),
+            full_filter: compile_conditions(&full_conditions),
+            follow_symlinks: options.follow_symlinks,
+            skip_hidden: options.skip_hidden,
+            include_archives: options.include_archives,
+            totals,
+            linked_files: Mutex::new(HashSet::new()),
+            cancel,
+            limits,
+            dirs_opened: AtomicUsize::new(0),
+            limit_error: OnceLock::new(),
+            results: Mutex::new(Vec::new()),
         }
-        "name" => {
-            if condition.operator == "LIKE" {
-                like_match(&process.name, &condition.value)
-            } else {
-                compare_strings(&process.name, &condition.operator, &condition.value)
-            }
-        }
-        "cpu_usage" => {
-            // Extract numeric value from "X.X%" format
-            if let Some(cpu_str) = process.cpu_usage.strip_suffix('%') {
-                if let Ok(cpu_val) = cpu_str.parse::<f32>() {
-                    if let Ok(compare_val) = condition.value.parse::<f32>() {
-                        match condition.operator.as_str() {
-                            "=" => (cpu_val - compare_val).abs() < 0.1, // Allow small floating point differences
-                            "!=" => (cpu_val - compare_val).abs() >= 0.1,
-                            ">" => cpu_val > compare_val,
-                            "<" => cpu_val < compare_val,
-                            ">=" => cpu_val >= compare_val,
-                            "<=" => cpu_val <= compare_val,
-                            _ => false,
-                        }
-                    } else {
-                        false
-                    }
-                } else {
-                    false
-                }
-            } else {
+    }
+
+    fn stopped(&self) -> bool {
+        self.cancel.is_cancelled() || self.limit_error.get().is_some()
+    }
+
+    // Record a limit violation, returning false so callers can bail out
+    fn within_limit(&self, check: Result<(), String>) -> bool {
+        match check {
+            Ok(()) => true,
+            Err(error) => {
+                let _ = self.limit_error.set(error);
                 false
             }
         }
-        "memory_usage" => {
-            // For memory comparison, extract numeric value
-            if let Ok(process_memory) = crate::processes::parse_memory(&process.memory_usage) {
-                if let Ok(compare_memory) = crate::processes::parse_memory(&condition.value) {
-                    match condition.operator.as_str() {
-                        "=" => process_memory == compare_memory,
-                        "!=" => process_memory != compare_memory,
-                        ">" => process_memory > compare_memory,
-                        "<" => process_memory < compare_memory,
-                        ">=" => process_memory >= compare_memory,
-                        "<=" => process_memory <= compare_memory,
-                        _ => false,
-                    }
-                } else {
-                    false
+    }
+
+    fn absolute_path(&self, path: &Path) -> PathBuf {
+        match path.strip_prefix(self.root_path) {
+            Ok(relative) => self.absolute_root.join(relative),
+            Err(_) => path.to_path_buf(),
+        }
+    }
+
+    fn collect_entry(
+        &self,
+        current_path: &Path,
+        file_type: fs::FileType,
+        metadata: Option<fs::Metadata>,
+        inherited: &Inherited,
+    ) -> Totals {
+        // Stop descending once cancelled or over a limit; rows already pushed are kept
+        if self.stopped() {
+            return Totals::default();
+        }
+
+        // A followed symlink is reported as its target, so it is the only entry that
+        // needs a stat before the cheap phase can tell files from directories.
+        // Unfollowed and dangling links are reported as the link itself.
+        let is_symlink = file_type.is_symlink();
+        let (is_dir, metadata, as_link) = if !is_symlink {
+            (file_type.is_dir(), metadata, false)
+        } else if self.follow_symlinks {
+            match fs::metadata(current_path) {
+                Ok(m) => (m.is_dir(), Some(m), false),
+                Err(_) => (false, None, true),
+            }
+        } else {
+            (false, None, true)
+        };
+
+        let mut file_info =
+            match FileInfo::new_lightweight_with_type(current_path, self.root_path, is_dir) {
+                Some(file_info) => file_info,
+                None => return Totals::default(),
+            };
+        if as_link {
+            file_info.file_type = "symlink".to_string();
+        }
+
+        // Hidden and ignored entries are left out along with everything below
+        // them, like search tools do; the FROM path itself is always read
+        if file_info.depth > 0 {
+            if self.skip_hidden && file_info.is_hidden {
+                return Totals::default();
+            }
+            if let Some(ignores) = &inherited.ignores {
+                if ignores.is_ignored(&self.absolute_path(current_path), is_dir) {
+                    return Totals::default();
                 }
+            }
+        }
+
+        // Early filtering: skip this path entirely
+        if !self.path_filter.matches(&file_info) {
+            return Totals::default();
+        }
+        if let Some(mounts) = &self.mounts {
+            file_info.mount = mounts.mount_of(&file_info);
+            if !self.mount_filter.matches(&file_info) {
+                return Totals::default();
+            }
+        }
+
+        let mut should_recurse = is_dir;
+        let depth = file_info.depth;
+
+        // When following links, each directory records its identity for its
+        // descendants; a link back to an ancestor is reported but not entered
+        let mut dir_ids = None;
+        if should_recurse && self.follow_symlinks {
+            let id = match &metadata {
+                Some(metadata) => Some(dir_id(metadata)),
+                None => fs::metadata(current_path).ok().map(|m| dir_id(&m)),
+            };
+            if id.is_some_and(|id| inherited.dir_ids.contains(&id)) {
+                should_recurse = false;
             } else {
-                false
+                let mut chain = inherited.dir_ids.clone();
+                chain.extend(id);
+                dir_ids = Some(chain);
             }
         }
-        "status" => compare_strings(&process.status, &condition.operator, &condition.value),
-        _ => false,
-    }
-}
 
-fn collect_files_recursive(
-    root_path: &Path,
-    current_path: &Path,
-    conditions: &[Condition],
-) -> Result<Vec<FileInfo>, String> {
-    let results = Mutex::new(Vec::new());
-
-    // Create a lightweight FileInfo for early filtering
-    let temp_file_info = FileInfo::new_lightweight(current_path, root_path);
-
-    // Early filtering: check conditions that can be evaluated with lightweight info
-    // Skip depth filtering in early phase since it's not performance-critical
-    let should_process = if let Some(ref file_info) = temp_file_info {
-        let mut matches = true;
-        for condition in conditions {
-            // Only check path conditions in early filtering (depth is handled later)
-            if condition.field == "path" {
-                let result = evaluate_single_condition(file_info, condition);
-                let final_result = if condition.negated { !result } else { result };
-                if !final_result {
-                    matches = false;
-                    break;
-                }
+        // Upgrade to full metadata only for entries that survived the cheap phase,
+        // reusing the stat we already have for the root and for symlinks. Directory
+        // totals need the size of everything, so then every entry is stat'ed.
+        let cheap_match = self.cheap_filter.matches(&file_info);
+        if cheap_match || self.totals {
+            let metadata = metadata.or_else(|| fs::symlink_metadata(current_path).ok());
+            if let Some(metadata) = metadata {
+                file_info.apply_metadata(&metadata);
+            }
+            if is_symlink {
+                file_info.link_target = fs::read_link(current_path)
+                    .ok()
+                    .map(|target| target.to_string_lossy().into_owned());
             }
         }
-        matches
-    } else {
-        true // If we can't get file info, process anyway (permission errors)
-    };
 
-    if !should_process {
-        return Ok(Vec::new()); // Skip this path entirely
-    }
-
-    // For directories, check if we should recurse based on path filters
-    let should_recurse = if current_path.is_dir() {
-        // If we have path conditions that exclude certain directories, check them
-        let mut recurse = true;
-        for condition in conditions {
-            if condition.field == "path" && condition.operator == "LIKE" && !condition.negated {
-                // For LIKE conditions, if the path pattern would exclude subdirectories, we might skip
-                // This is a simplified check - in practice we'd need more sophisticated analysis
-                if condition.value.contains("%target/%") {
-                    // Skip recursing into target directories
-                    recurse = false;
-                    break;
-                }
+        // Archive entries are listed below the archive's row; they take no
+        // space of their own, so they don't add to any totals
+        if self.include_archives
+            && !is_dir
+            && !as_link
+            && ArchiveFormat::for_name(&file_info.name).is_some()
+        {
+            self.collect_archive_entries(current_path);
+        }
+
+        if !self.totals {
+            if cheap_match {
+                self.push_if_matches(file_info);
+            }
+            if should_recurse {
+                self.collect_children(current_path, depth, dir_ids, inherited);
             }
+            return Totals::default();
         }
-        recurse
-    } else {
-        false
-    };
 
-    // Add current file/directory if it passes all filtering conditions
-    if let Some(mut file_info) = temp_file_info {
-        let mut matches = true;
-        for condition in conditions {
-            let result = evaluate_single_condition(&file_info, condition);
-            let final_result = if condition.negated { !result } else { result };
-            if !final_result {
-                matches = false;
-                break;
+        // A directory's totals are known once its children have been walked,
+        // so its row is checked and added after them
+        let mut totals = self.own_totals(&file_info, is_dir);
+        if should_recurse {
+            totals = totals + self.collect_children(current_path, depth, dir_ids, inherited);
+        }
+        if cheap_match {
+            if is_dir {
+                file_info.total_size = Some(totals.bytes);
+                file_info.file_count = Some(totals.files);
             }
+            self.push_if_matches(file_info);
         }
+        totals
+    }
 
-        if matches {
-            // Upgrade to full metadata only for files that match our criteria
-            file_info.upgrade_to_full(current_path);
-            results.lock().unwrap().push(file_info);
+    fn collect_archive_entries(&self, archive: &Path) {
+        for mut row in archive_entry_rows(archive, self.root_path, self.skip_hidden, self.cancel) {
+            if let Some(mounts) = &self.mounts {
+                row.mount = mounts.mount_of(&row);
+            }
+            if self.path_filter.matches(&row) && self.cheap_filter.matches(&row) {
+                self.push_if_matches(row);
+            }
         }
     }
 
-    // If it's a directory and we should recurse, process children in parallel
-    if should_recurse {
-        if let Ok(entries) = fs::read_dir(current_path) {
-            let child_paths: Vec<std::path::PathBuf> = entries
-                .filter_map(|entry| entry.ok())
-                .map(|entry| entry.path())
-                .collect();
+    fn push_if_matches(&self, file_info: FileInfo) {
+        if self.full_filter.matches(&file_info) {
+            let mut results = self.results.lock().unwrap();
+            if self.within_limit(self.limits.check_rows(results.len() + 1)) {
+                results.push(file_info);
+            }
+        }
+    }
 
-            // Process children in parallel
-            child_paths.into_par_iter().for_each(|path| {
-                if let Ok(mut sub_results) = collect_files_recursive(root_path, &path, conditions) {
-                    results.lock().unwrap().append(&mut sub_results);
+    // What an entry adds to the totals of the directories above it
+    fn own_totals(&self, file_info: &FileInfo, is_dir: bool) -> Totals {
+        let mut bytes = file_info.blocks.unwrap_or(0) * 512;
+        if !is_dir && file_info.nlink.unwrap_or(1) > 1 {
+            if let (Some(device), Some(inode)) = (file_info.device, file_info.inode) {
+                if !self.linked_files.lock().unwrap().insert((device, inode)) {
+                    bytes = 0;
                 }
-            });
+            }
+        }
+        Totals {
+            bytes,
+            files: u64::from(!is_dir),
         }
     }
 
-    Ok(results.into_inner().unwrap())
+    // Walk a directory's entries in parallel, returning what they add up to
+    fn collect_children(
+        &self,
+        current_path: &Path,
+        depth: usize,
+        dir_ids: Option<Vec<DirId>>,
+        inherited: &Inherited,
+    ) -> Totals {
+        let opened = self.dirs_opened.fetch_add(1, Ordering::Relaxed) + 1;
+        if !self.within_limit(self.limits.check_open_dirs(opened)) {
+            return Totals::default();
+        }
+
+        let entries = match fs::read_dir(current_path) {
+            Ok(entries) => entries,
+            Err(_) => return Totals::default(),
+        };
+        let children: Vec<(std::path::PathBuf, fs::FileType)> = entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| entry.file_type().ok().map(|t| (entry.path(), t)))
+            .collect();
+
+        // Only a directory with entries takes the walk a level deeper
+        if let Some((child, _)) = children.first() {
+            let child_path = child.strip_prefix(self.root_path).unwrap_or(child);
+            let check = self
+                .limits
+                .check_traversal_depth(depth + 1, &child_path.to_string_lossy());
+            if !self.within_limit(check) {
+                return Totals::default();
+            }
+        }
+
+        // Children see this directory's own ignore files on top of its parents'
+        let ignores = inherited
+            .ignores
+            .as_ref()
+            .and_then(|ignores| ignores.descend(&self.absolute_path(current_path)));
+        let own_state;
+        let children_inherit = if dir_ids.is_some() || ignores.is_some() {
+            own_state = Inherited {
+                dir_ids: dir_ids.unwrap_or_else(|| inherited.dir_ids.clone()),
+                ignores: ignores.or_else(|| inherited.ignores.clone()),
+            };
+            &own_state
+        } else {
+            inherited
+        };
+
+        // Process children in parallel
+        children
+            .into_par_iter()
+            .map(|(path, file_type)| self.collect_entry(&path, file_type, None, children_inherit))
+            .reduce(Totals::default, |a, b| a + b)
+    }
 }
 
 /// Execute process query with subquery support
 fn execute_process_query_with_subqueries(
     query: &SqlQuery,
     _subquery_results: &HashMap<String, QueryResult>,
+    cancel: &CancelToken,
 ) -> Result<Vec<ProcessInfo>, String> {
     // For now, delegate to the existing process query execution
     // This would need to be enhanced to handle subqueries similar to filesystem queries
-    execute_process_query(query)
+    execute_process_query(query, cancel)
+}
+
+// The first column of a table row as a quoted literal, skipping empty cells
+fn table_first_cell(table: &crate::tables::Table, row: &crate::tables::TableRow) -> Option<String> {
+    let column = table.columns.first()?;
+    let value = table.display_value(row, &column.name)?;
+    (value != "NULL").then(|| format!("'{}'", value.replace("'", "''")))
 }
 
 /// Process WHERE clause subquery placeholders and replace them with actual values
@@ -451,6 +957,18 @@ fn process_where_subquery_placeholders(
                     format!("({})", values.join(", "))
                 };
                 processed = processed.replace(placeholder, &replacement);
+            } else if let QueryResult::Table(table) = result {
+                let values: Vec<String> = table
+                    .rows
+                    .iter()
+                    .filter_map(|row| table_first_cell(table, row))
+                    .collect();
+                let replacement = if values.is_empty() {
+                    "NULL".to_string()
+                } else {
+                    format!("({})", values.join(", "))
+                };
+                processed = processed.replace(placeholder, &replacement);
             }
         }
     }
@@ -463,6 +981,7 @@ fn process_where_subquery_placeholders(
                 QueryResult::Processes(processes) => !processes.is_empty(),
                 QueryResult::Network(network_info) => !network_info.is_empty(),
                 QueryResult::Applications(apps) => !apps.is_empty(),
+                QueryResult::Table(table) => !table.rows.is_empty(),
             };
             let replacement = if has_results { "TRUE" } else { "FALSE" };
             processed = processed.replace(placeholder, replacement);
@@ -505,6 +1024,11 @@ fn process_where_subquery_placeholders(
                         format!("'{}'", apps[0].name.replace("'", "''"))
                     }
                 }
+                QueryResult::Table(table) => table
+                    .rows
+                    .first()
+                    .and_then(|row| table_first_cell(table, row))
+                    .unwrap_or_else(|| "NULL".to_string()),
             };
             processed = processed.replace(placeholder, &replacement);
         }
@@ -517,6 +1041,7 @@ fn process_where_subquery_placeholders(
 mod tests {
     use super::*;
     use crate::models::{Condition, QueryType, SqlQuery};
+    use crate::utils::{compare_strings, like_match};
     use std::fs;
     use tempfile::TempDir;
 
@@ -535,7 +1060,15 @@ mod tests {
         let target_path = Path::new("/tmp/target/debug/main.rs");
         let root_path = Path::new("/tmp");
 
-        let result = collect_files_recursive(root_path, target_path, &conditions);
+        let result = collect_files_recursive(
+            root_path,
+            target_path,
+            &conditions,
+            &FromOptions::default(),
+            false,
+            &CancelToken::new(),
+            &QueryLimits::default(),
+        );
         assert!(result.is_ok());
         // Should return empty vec since path is filtered
         assert!(result.unwrap().is_empty());
@@ -555,9 +1088,10 @@ mod tests {
             order_direction: crate::models::SortDirection::Ascending,
             limit: None,
             distinct: false,
+            from_options: Default::default(),
         };
 
-        let result = execute_delete_query(&query);
+        let result = execute_delete_query(&query, &CancelToken::new(), &QueryLimits::default());
         assert!(result.is_err());
         assert!(result.unwrap_err().contains("Path does not exist"));
     }
@@ -579,9 +1113,10 @@ mod tests {
             order_direction: crate::models::SortDirection::Ascending,
             limit: None,
             distinct: false,
+            from_options: Default::default(),
         };
 
-        let result = execute_delete_query(&query);
+        let result = execute_delete_query(&query, &CancelToken::new(), &QueryLimits::default());
         assert!(result.is_ok());
         let query_result = result.unwrap();
         match query_result {
@@ -611,9 +1146,10 @@ mod tests {
             order_direction: crate::models::SortDirection::Ascending,
             limit: None,
             distinct: false,
+            from_options: Default::default(),
         };
 
-        let result = execute_delete_query(&query);
+        let result = execute_delete_query(&query, &CancelToken::new(), &QueryLimits::default());
         assert!(result.is_ok());
         let query_result = result.unwrap();
         match query_result {
@@ -627,6 +1163,56 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_execute_delete_query_hash_condition() {
+        let temp_dir = TempDir::new().unwrap();
+        let temp_path = temp_dir.path();
+        fs::write(temp_path.join("a.txt"), "same").unwrap();
+        fs::write(temp_path.join("b.txt"), "same").unwrap();
+        fs::write(temp_path.join("c.txt"), "diff").unwrap();
+
+        let query = crate::parser::parse_query(&format!(
+            "DELETE FROM {} WHERE type = 'file' AND hash NOT IN (duplicates)",
+            temp_path.display()
+        ))
+        .unwrap();
+        match execute_delete_query(&query, &CancelToken::new(), &QueryLimits::default()) {
+            Ok(QueryResult::Files(files)) => {
+                assert_eq!(files.len(), 1);
+                assert_eq!(files[0].name, "c.txt");
+            }
+            other => panic!("Expected Files result, got {:?}", other.map(|_| ())),
+        }
+        assert!(temp_path.join("a.txt").exists());
+        assert!(!temp_path.join("c.txt").exists());
+    }
+
+    #[test]
+    fn test_execute_delete_query_hash_condition_keeps_directories() {
+        let temp_dir = TempDir::new().unwrap();
+        let temp_path = temp_dir.path();
+        fs::create_dir(temp_path.join("sub")).unwrap();
+        fs::write(temp_path.join("a"), "same").unwrap();
+        fs::write(temp_path.join("sub").join("b"), "same").unwrap();
+        fs::write(temp_path.join("c"), "diff").unwrap();
+
+        let query = crate::parser::parse_query(&format!(
+            "DELETE FROM {} WHERE hash NOT IN (duplicates)",
+            temp_path.display()
+        ))
+        .unwrap();
+        match execute_delete_query(&query, &CancelToken::new(), &QueryLimits::default()) {
+            Ok(QueryResult::Files(files)) => {
+                let paths: Vec<&str> = files.iter().map(|f| f.path.as_str()).collect();
+                assert_eq!(paths, vec!["c"]);
+            }
+            other => panic!("Expected Files result, got {:?}", other.map(|_| ())),
+        }
+        assert!(temp_path.join("a").exists());
+        assert!(temp_path.join("sub").join("b").exists());
+        assert!(!temp_path.join("c").exists());
+    }
+
     #[test]
     fn test_execute_delete_query_directory() {
         let temp_dir = TempDir::new().unwrap();
@@ -650,9 +1236,10 @@ mod tests {
             order_direction: crate::models::SortDirection::Ascending,
             limit: None,
             distinct: false,
+            from_options: Default::default(),
         };
 
-        let result = execute_delete_query(&query);
+        let result = execute_delete_query(&query, &CancelToken::new(), &QueryLimits::default());
         assert!(result.is_ok());
         let query_result = result.unwrap();
         match query_result {
@@ -677,7 +1264,7 @@ mod tests {
             negated: false,
         }];
 
-        assert!(evaluate_process_conditions(&process, &conditions));
+        assert!(compile_conditions::<ProcessInfo>(&conditions).matches(&process));
 
         // Test non-matching condition
         let bad_conditions = vec![Condition {
@@ -687,7 +1274,7 @@ mod tests {
             negated: false,
         }];
 
-        assert!(!evaluate_process_conditions(&process, &bad_conditions));
+        assert!(!compile_conditions::<ProcessInfo>(&bad_conditions).matches(&process));
     }
 
     #[test]
@@ -719,9 +1306,11 @@ mod tests {
             modified_date: chrono::Utc::now(),
             permissions: "644".to_string(),
             size: "100 B".to_string(),
+            size_bytes: 100,
             path: "test1.txt".to_string(),
             depth: 1,
             extension: Some("txt".to_string()),
+            ..Default::default()
         };
         let file2 = FileInfo {
             name: "test2.txt".to_string(),
@@ -729,9 +1318,11 @@ mod tests {
             modified_date: chrono::Utc::now(),
             permissions: "644".to_string(),
             size: "200 B".to_string(),
+            size_bytes: 200,
             path: "test2.txt".to_string(),
             depth: 1,
             extension: Some("txt".to_string()),
+            ..Default::default()
         };
         let files = vec![file1, file2];
         subquery_results.insert("__SUBQUERY_0__".to_string(), QueryResult::Files(files));
@@ -756,9 +1347,11 @@ mod tests {
             modified_date: chrono::Utc::now(),
             permissions: "644".to_string(),
             size: "50 B".to_string(),
+            size_bytes: 50,
             path: "scalar.txt".to_string(),
             depth: 1,
             extension: Some("txt".to_string()),
+            ..Default::default()
         };
         let scalar_files = vec![scalar_file];
         subquery_results.insert(
@@ -811,7 +1404,16 @@ mod tests {
             negated: false,
         }];
 
-        let results = collect_files_recursive(temp_path, temp_path, &conditions).unwrap();
+        let results = collect_files_recursive(
+            temp_path,
+            temp_path,
+            &conditions,
+            &FromOptions::default(),
+            false,
+            &CancelToken::new(),
+            &QueryLimits::default(),
+        )
+        .unwrap();
         assert_eq!(results.len(), 1);
         assert_eq!(results[0].name, "dir1");
         assert_eq!(results[0].depth, 1);
@@ -824,7 +1426,16 @@ mod tests {
             negated: false,
         }];
 
-        let results = collect_files_recursive(temp_path, temp_path, &conditions).unwrap();
+        let results = collect_files_recursive(
+            temp_path,
+            temp_path,
+            &conditions,
+            &FromOptions::default(),
+            false,
+            &CancelToken::new(),
+            &QueryLimits::default(),
+        )
+        .unwrap();
         assert_eq!(results.len(), 1);
         assert_eq!(results[0].name, "file.txt");
         assert_eq!(results[0].depth, 3);
@@ -837,12 +1448,828 @@ mod tests {
             negated: false,
         }];
 
-        let results = collect_files_recursive(temp_path, temp_path, &conditions).unwrap();
+        let results = collect_files_recursive(
+            temp_path,
+            temp_path,
+            &conditions,
+            &FromOptions::default(),
+            false,
+            &CancelToken::new(),
+            &QueryLimits::default(),
+        )
+        .unwrap();
         assert_eq!(results.len(), 1);
         assert_eq!(results[0].name, "file.txt");
         assert_eq!(results[0].depth, 3);
     }
 
+    #[test]
+    fn test_metadata_conditions_after_cheap_phase() {
+        let temp_dir = TempDir::new().unwrap();
+        let temp_path = temp_dir.path();
+
+        fs::write(temp_path.join("small.txt"), "x").unwrap();
+        fs::write(temp_path.join("large.txt"), vec![b'x'; 4096]).unwrap();
+
+        // size is only known after the full phase, so it must not be checked
+        // against the lightweight placeholder values
+        let conditions = vec![
+            Condition {
+                field: "type".to_string(),
+                operator: "=".to_string(),
+                value: "file".to_string(),
+                negated: false,
+            },
+            Condition {
+                field: "size".to_string(),
+                operator: ">".to_string(),
+                value: "1 KB".to_string(),
+                negated: false,
+            },
+        ];
+
+        let results = collect_files_recursive(
+            temp_path,
+            temp_path,
+            &conditions,
+            &FromOptions::default(),
+            false,
+            &CancelToken::new(),
+            &QueryLimits::default(),
+        )
+        .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].name, "large.txt");
+        assert_eq!(results[0].size, "4 KB");
+    }
+
+    #[test]
+    fn test_cancelled_query_stops_traversal() {
+        let temp_dir = TempDir::new().unwrap();
+        let temp_path = temp_dir.path();
+        fs::write(temp_path.join("file.txt"), "x").unwrap();
+
+        let cancel = CancelToken::new();
+        cancel.cancel();
+
+        let query =
+            crate::parser::parse_query(&format!("SELECT name FROM {}", temp_path.display()))
+                .unwrap();
+        match execute_query_with_cancel(&query, &cancel).unwrap() {
+            QueryResult::Files(files) => assert!(files.is_empty()),
+            _ => panic!("Expected file results"),
+        }
+
+        // A cancelled DELETE must not act on a partial match set
+        let query = crate::parser::parse_query(&format!(
+            "DELETE FROM {} WHERE name = 'file.txt'",
+            temp_path.display()
+        ))
+        .unwrap();
+        let result = execute_query_with_cancel(&query, &cancel);
+        assert_eq!(result.unwrap_err(), "Query cancelled; nothing was deleted");
+        assert!(temp_path.join("file.txt").exists());
+    }
+
+    #[test]
+    fn test_respect_ignores_and_skip_hidden() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir(root.join(".git")).unwrap();
+        fs::write(root.join(".gitignore"), "target/\n*.log\n").unwrap();
+        fs::create_dir_all(root.join("target").join("debug")).unwrap();
+        fs::write(root.join("target").join("debug").join("q"), "x").unwrap();
+        fs::create_dir(root.join("src")).unwrap();
+        fs::write(root.join("src").join("main.rs"), "x").unwrap();
+        fs::write(root.join("src").join("trace.log"), "x").unwrap();
+        fs::write(root.join("src").join(".ignore"), "!trace.log\n").unwrap();
+        fs::write(root.join("debug.log"), "x").unwrap();
+
+        let walk = |options: FromOptions| {
+            let mut paths: Vec<String> = collect_files_recursive(
+                root,
+                root,
+                &[],
+                &options,
+                false,
+                &CancelToken::new(),
+                &QueryLimits::default(),
+            )
+            .unwrap()
+            .into_iter()
+            .map(|f| f.path)
+            .collect();
+            paths.sort();
+            paths
+        };
+
+        assert_eq!(walk(FromOptions::default()).len(), 11);
+
+        let options = FromOptions {
+            respect_ignores: true,
+            skip_hidden: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            walk(options),
+            vec!["", "src", "src/main.rs", "src/trace.log"]
+        );
+
+        // Hidden entries stay visible unless SKIP HIDDEN is given
+        let options = FromOptions {
+            respect_ignores: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            walk(options),
+            vec![
+                "",
+                ".git",
+                ".gitignore",
+                "src",
+                "src/.ignore",
+                "src/main.rs",
+                "src/trace.log"
+            ]
+        );
+
+        let hidden = collect_files_recursive(
+            root,
+            root,
+            &parse_compound_conditions("is_hidden = true AND depth > 0").unwrap(),
+            &FromOptions::default(),
+            false,
+            &CancelToken::new(),
+            &QueryLimits::default(),
+        )
+        .unwrap();
+        assert_eq!(hidden.len(), 3);
+    }
+
+    #[test]
+    fn test_ownership_columns() {
+        use crate::columns::display_value;
+
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::write(root.join("mine.txt"), "x").unwrap();
+        let uid = fs::metadata(root).unwrap().uid();
+
+        let condition = format!("uid = {} AND name = 'mine.txt'", uid);
+        let results = collect_files_recursive(
+            root,
+            root,
+            &parse_compound_conditions(&condition).unwrap(),
+            &FromOptions::default(),
+            false,
+            &CancelToken::new(),
+            &QueryLimits::default(),
+        )
+        .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(display_value(&results[0], "uid"), Some(uid.to_string()));
+        assert_eq!(
+            display_value(&results[0], "owner").unwrap(),
+            crate::users::user_name(uid).unwrap_or("NULL")
+        );
+    }
+
+    #[test]
+    fn test_hard_links_share_disk_usage() {
+        use crate::aggregate::aggregate_row;
+
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::write(root.join("data.bin"), vec![1u8; 64 * 1024]).unwrap();
+        fs::hard_link(root.join("data.bin"), root.join("link.bin")).unwrap();
+
+        let files = collect_files_recursive(
+            root,
+            root,
+            &parse_compound_conditions("type = 'file'").unwrap(),
+            &FromOptions::default(),
+            false,
+            &CancelToken::new(),
+            &QueryLimits::default(),
+        )
+        .unwrap();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].inode, files[1].inode);
+        assert_eq!(files[0].nlink, Some(2));
+
+        let fields = vec!["COUNT(*)".to_string(), "SUM(disk_usage)".to_string()];
+        let disk_usage = files[0].blocks.unwrap() * 512;
+        assert_eq!(
+            aggregate_row(&files, &fields),
+            vec!["2".to_string(), FileInfo::format_size(disk_usage)]
+        );
+    }
+
+    #[test]
+    fn test_hash_columns_and_duplicates() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir(root.join("sub")).unwrap();
+        fs::write(root.join("a.txt"), "hello").unwrap();
+        fs::write(root.join("sub").join("b.txt"), "hello").unwrap();
+        fs::write(root.join("c.txt"), "world").unwrap(); // Same size, other contents
+        fs::write(root.join("empty1"), "").unwrap();
+        fs::write(root.join("empty2"), "").unwrap();
+
+        let run = |sql: &str| {
+            let sql = sql.replace("ROOT", &root.display().to_string());
+            match execute_query(&crate::parser::parse_query(&sql).unwrap()).unwrap() {
+                QueryResult::Files(files) => files,
+                _ => panic!("expected file results"),
+            }
+        };
+
+        let duplicates =
+            run("SELECT path, hash FROM ROOT WHERE hash IN (duplicates) ORDER BY path");
+        let paths: Vec<&str> = duplicates.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(paths, vec!["a.txt", "sub/b.txt"]);
+        assert!(duplicates[0].hash.is_some());
+        assert_eq!(duplicates[0].hash, duplicates[1].hash);
+
+        let unique = run("SELECT name FROM ROOT WHERE type = 'file' AND hash NOT IN (duplicates)");
+        assert_eq!(unique.len(), 3);
+
+        // Directories, the root included, have no hash and match neither
+        let unique = run("SELECT path FROM ROOT WHERE hash NOT IN (duplicates) ORDER BY path");
+        let paths: Vec<&str> = unique.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(paths, vec!["c.txt", "empty1", "empty2"]);
+
+        // Hashes are only computed when a query refers to them
+        let plain = run("SELECT name, size FROM ROOT WHERE name = 'c.txt'");
+        assert_eq!(plain[0].hash, None);
+
+        let sha = run("SELECT sha256 FROM ROOT WHERE name = 'a.txt'");
+        assert_eq!(
+            sha[0].sha256.as_deref(),
+            Some("2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824")
+        );
+    }
+
+    #[test]
+    fn test_content_search_rows() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::write(root.join("notes.txt"), "one\nTODO two\nthree\nTODO four\n").unwrap();
+        fs::write(root.join("done.txt"), "nothing left\n").unwrap();
+
+        let run = |sql: &str| {
+            let sql = sql.replace("ROOT", &root.display().to_string());
+            match execute_query(&crate::parser::parse_query(&sql).unwrap()).unwrap() {
+                QueryResult::Files(files) => files,
+                _ => panic!("expected file results"),
+            }
+        };
+
+        let lines = run("SELECT line_number, line FROM ROOT WHERE content LIKE 'TODO%'");
+        let lines: Vec<_> = lines
+            .iter()
+            .map(|f| (f.line_number.unwrap(), f.line.clone().unwrap()))
+            .collect();
+        assert_eq!(
+            lines,
+            vec![(2, "TODO two".to_string()), (4, "TODO four".to_string())]
+        );
+
+        // Conditions on the line columns are checked after the search
+        let lines = run("SELECT line FROM ROOT WHERE content LIKE 'TODO%' AND line_number > 2");
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0].line.as_deref(), Some("TODO four"));
+
+        // Line counts that are only selected are read for the rows that are left
+        let counts = run("SELECT name, line_count FROM ROOT WHERE name = 'notes.txt'");
+        assert_eq!(counts[0].line_count, Some(4));
+        let counts = run("SELECT name FROM ROOT WHERE name = 'notes.txt'");
+        assert_eq!(counts[0].line_count, None);
+    }
+
+    #[test]
+    fn test_mime_type_ignores_extension() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::write(root.join("image.txt"), b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR").unwrap();
+        fs::write(root.join("notes.png"), "not an image\n").unwrap();
+
+        let query = crate::parser::parse_query(&format!(
+            "SELECT name, is_binary FROM {} WHERE mime_type = 'image/png'",
+            root.display()
+        ))
+        .unwrap();
+        match execute_query(&query).unwrap() {
+            QueryResult::Files(files) => {
+                assert_eq!(files.len(), 1);
+                assert_eq!(files[0].name, "image.txt");
+                assert_eq!(files[0].mime_type, Some("image/png"));
+            }
+            _ => panic!("Expected Files result"),
+        }
+    }
+
+    #[test]
+    fn test_image_columns() {
+        use crate::columns::display_value;
+
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+        png.extend(3000u32.to_be_bytes());
+        png.extend(2000u32.to_be_bytes());
+        png.extend([8, 6, 0, 0, 0, 0, 0, 0, 0]);
+        fs::write(root.join("large.png"), png).unwrap();
+        fs::write(root.join("icon.gif"), b"GIF89a\x10\x00\x10\x00\x80\x00\x00").unwrap();
+        fs::write(root.join("notes.txt"), "not an image\n").unwrap();
+
+        let query = crate::parser::parse_query(&format!(
+            "SELECT name, image_format, camera FROM {} WHERE image_width > 1000",
+            root.display()
+        ))
+        .unwrap();
+        match execute_query(&query).unwrap() {
+            QueryResult::Files(files) => {
+                assert_eq!(files.len(), 1);
+                assert_eq!(files[0].name, "large.png");
+                assert_eq!(display_value(&files[0], "image_height").unwrap(), "2000");
+                assert_eq!(display_value(&files[0], "image_format").unwrap(), "png");
+                assert_eq!(display_value(&files[0], "camera").unwrap(), "NULL");
+            }
+            _ => panic!("Expected Files result"),
+        }
+
+        let query = crate::parser::parse_query(&format!(
+            "SELECT name, image_format FROM {} WHERE type = 'file' ORDER BY image_width",
+            root.display()
+        ))
+        .unwrap();
+        match execute_query(&query).unwrap() {
+            QueryResult::Files(files) => {
+                let formats: Vec<String> = files
+                    .iter()
+                    .map(|f| display_value(f, "image_format").unwrap())
+                    .collect();
+                // Files that aren't images have no width, and NULL sorts last
+                assert_eq!(formats, vec!["gif", "png", "NULL"]);
+            }
+            _ => panic!("Expected Files result"),
+        }
+    }
+
+    #[test]
+    fn test_git_columns() {
+        use crate::columns::display_value;
+        use git2::{Repository, Signature, Time};
+
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let repository = Repository::init(root).unwrap();
+        fs::write(root.join("kept.txt"), "v1\n").unwrap();
+        fs::write(root.join("edited.txt"), "v1\n").unwrap();
+        let mut index = repository.index().unwrap();
+        index.add_path(Path::new("kept.txt")).unwrap();
+        index.add_path(Path::new("edited.txt")).unwrap();
+        index.write().unwrap();
+        let tree = repository.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = Signature::new("Ada", "ada@example.com", &Time::new(0, 0)).unwrap();
+        repository
+            .commit(Some("HEAD"), &signature, &signature, "init", &tree, &[])
+            .unwrap();
+        fs::write(root.join("edited.txt"), "v2\n").unwrap();
+        fs::write(root.join("new.txt"), "v1\n").unwrap();
+
+        let query = crate::parser::parse_query(&format!(
+            "SELECT name, git_last_author FROM {} \
+             WHERE extension = 'txt' AND git_status != 'clean' ORDER BY name",
+            root.display()
+        ))
+        .unwrap();
+        match execute_query(&query).unwrap() {
+            QueryResult::Files(files) => {
+                let rows: Vec<(String, String, String)> = files
+                    .iter()
+                    .map(|f| {
+                        (
+                            f.name.clone(),
+                            display_value(f, "git_status").unwrap(),
+                            display_value(f, "git_last_author").unwrap(),
+                        )
+                    })
+                    .collect();
+                assert_eq!(
+                    rows,
+                    vec![
+                        ("edited.txt".into(), "modified".into(), "Ada".into()),
+                        ("new.txt".into(), "untracked".into(), "NULL".into()),
+                    ]
+                );
+            }
+            _ => panic!("Expected Files result"),
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_mount_columns() {
+        use crate::columns::display_value;
+
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir(root.join("sub")).unwrap();
+        fs::write(root.join("sub/file.txt"), "x").unwrap();
+        let select = |condition: &str| {
+            let query = crate::parser::parse_query(&format!(
+                "SELECT name, mount_point, fs_type FROM {} WHERE {}",
+                root.display(),
+                condition
+            ))
+            .unwrap();
+            match execute_query(&query).unwrap() {
+                QueryResult::Files(files) => files,
+                _ => panic!("Expected Files result"),
+            }
+        };
+
+        let files = select("name = 'file.txt'");
+        assert_eq!(files.len(), 1);
+        let fs_type = display_value(&files[0], "fs_type").unwrap();
+        let mount_point = display_value(&files[0], "mount_point").unwrap();
+        assert_ne!(fs_type, "NULL");
+        assert!(root.canonicalize().unwrap().starts_with(&mount_point));
+
+        assert_eq!(select(&format!("fs_type = '{}'", fs_type)).len(), 3);
+        // Excluding the filesystem the walk starts on leaves nothing to descend into
+        assert!(select(&format!("fs_type != '{}'", fs_type)).is_empty());
+    }
+
+    #[test]
+    fn test_full_extension_and_language() {
+        use crate::columns::display_value;
+
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir(root.join("backups.tar.gz")).unwrap();
+        fs::write(root.join("site.tar.gz"), "").unwrap();
+        fs::write(root.join("data.gz"), "").unwrap();
+        fs::write(root.join("main.rs"), "").unwrap();
+
+        let query = crate::parser::parse_query(&format!(
+            "SELECT name, stem FROM {} WHERE full_extension = 'tar.gz'",
+            root.display()
+        ))
+        .unwrap();
+        match execute_query(&query).unwrap() {
+            QueryResult::Files(files) => {
+                assert_eq!(files.len(), 1);
+                assert_eq!(files[0].name, "site.tar.gz");
+                assert_eq!(display_value(&files[0], "stem").unwrap(), "site");
+            }
+            _ => panic!("Expected Files result"),
+        }
+
+        let query = crate::parser::parse_query(&format!(
+            "SELECT name FROM {} WHERE language = 'Rust'",
+            root.display()
+        ))
+        .unwrap();
+        match execute_query(&query).unwrap() {
+            QueryResult::Files(files) => {
+                assert_eq!(files.len(), 1);
+                assert_eq!(files[0].name, "main.rs");
+            }
+            _ => panic!("Expected Files result"),
+        }
+    }
+
+    #[test]
+    fn test_query_inside_archive() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let mut builder = tar::Builder::new(fs::File::create(root.join("site.tar")).unwrap());
+        for (path, contents) in [("site/index.html", "<html>"), ("site/js/app.js", "run()")] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, path, contents.as_bytes())
+                .unwrap();
+        }
+        builder.finish().unwrap();
+
+        let run = |sql: &str| -> Result<Vec<String>, String> {
+            let sql = sql.replace("ROOT", &root.display().to_string());
+            match execute_query(&crate::parser::parse_query(&sql).unwrap())? {
+                QueryResult::Files(files) => {
+                    Ok(files.into_iter().map(|f| f.path).collect::<Vec<_>>())
+                }
+                _ => panic!("expected file results"),
+            }
+        };
+        assert_eq!(
+            run("SELECT path FROM ROOT/site.tar/site WHERE size > 5").unwrap(),
+            vec!["index.html"]
+        );
+        // Without INCLUDE ARCHIVES a walk only sees the archive itself
+        assert!(run("SELECT path FROM ROOT WHERE name = 'app.js'")
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            run("SELECT path FROM ROOT INCLUDE ARCHIVES WHERE name = 'app.js'").unwrap(),
+            vec!["site.tar/site/js/app.js"]
+        );
+        assert!(run("DELETE FROM ROOT/site.tar WHERE name = 'app.js'").is_err());
+        assert!(root.join("site.tar").exists());
+    }
+
+    #[test]
+    fn test_query_table_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::write(
+            root.join("sales.csv"),
+            "region,amount\nnorth,120\nsouth,80\neast,300\nwest,\n",
+        )
+        .unwrap();
+        fs::write(root.join("regions.txt"), "north;cold\neast;dry\n").unwrap();
+
+        let run = |sql: &str| -> Result<Vec<String>, String> {
+            let sql = sql.replace("ROOT", &root.display().to_string());
+            match execute_query(&crate::parser::parse_query(&sql)?)? {
+                QueryResult::Table(table) => Ok(table
+                    .rows
+                    .iter()
+                    .map(|row| table.display_value(row, &table.columns[0].name).unwrap())
+                    .collect()),
+                _ => panic!("expected table results"),
+            }
+        };
+        assert_eq!(
+            run("SELECT region FROM csv('ROOT/sales.csv') WHERE amount > 100 ORDER BY amount DESC")
+                .unwrap(),
+            vec!["east", "north"]
+        );
+        assert_eq!(
+            run("SELECT * FROM csv('ROOT/sales.csv') WHERE amount IS NULL").unwrap(),
+            vec!["west"]
+        );
+        assert_eq!(
+            run("SELECT region FROM csv('ROOT/sales.csv') ORDER BY amount LIMIT 1").unwrap(),
+            vec!["south"]
+        );
+        assert_eq!(
+            run(
+                "SELECT * FROM csv('ROOT/regions.txt', delimiter = ';', header = false) \
+                 WHERE column2 = 'dry'"
+            )
+            .unwrap(),
+            vec!["east"]
+        );
+        fs::write(
+            root.join("events.ndjson"),
+            "{\"level\": \"info\", \"user\": {\"id\": 1}}\n\
+             {\"level\": \"error\", \"user\": {\"id\": 2}}\n",
+        )
+        .unwrap();
+        assert_eq!(
+            run("SELECT level FROM json('ROOT/events.ndjson') WHERE user.id > 1").unwrap(),
+            vec!["error"]
+        );
+        let error = run("SELECT regoin FROM csv('ROOT/sales.csv')").unwrap_err();
+        assert!(error.contains("region, amount"));
+        assert!(run("SELECT region FROM csv('ROOT/missing.csv')").is_err());
+        assert!(run("DELETE FROM csv('ROOT/sales.csv') WHERE amount > 100").is_err());
+    }
+
+    #[test]
+    fn test_directory_totals() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("a").join("b")).unwrap();
+        fs::create_dir(root.join("empty")).unwrap();
+        fs::write(root.join("a").join("x.bin"), vec![1u8; 8192]).unwrap();
+        fs::write(root.join("a").join("b").join("y.bin"), vec![2u8; 8192]).unwrap();
+        fs::hard_link(
+            root.join("a").join("b").join("y.bin"),
+            root.join("a").join("b").join("link.bin"),
+        )
+        .unwrap();
+
+        let run = |sql: &str| {
+            let sql = sql.replace("ROOT", &root.display().to_string());
+            match execute_query(&crate::parser::parse_query(&sql).unwrap()).unwrap() {
+                QueryResult::Files(files) => files,
+                _ => panic!("expected file results"),
+            }
+        };
+        let usage = |path: &Path| fs::symlink_metadata(path).unwrap().blocks() * 512;
+
+        let dirs = run("SELECT path, total_size, file_count FROM ROOT WHERE path = 'a'");
+        assert_eq!(dirs.len(), 1);
+        assert_eq!(dirs[0].file_count, Some(3));
+        // The hard link adds its blocks once
+        let expected = usage(&root.join("a"))
+            + usage(&root.join("a").join("b"))
+            + usage(&root.join("a").join("x.bin"))
+            + usage(&root.join("a").join("b").join("y.bin"));
+        assert_eq!(dirs[0].total_size, Some(expected));
+
+        // Conditions on the totals work without selecting them, and files have none
+        let empty = run("SELECT path FROM ROOT WHERE file_count = 0");
+        assert_eq!(empty.len(), 1);
+        assert_eq!(empty[0].path, "empty");
+        let files = run("SELECT name, total_size FROM ROOT WHERE name = 'x.bin'");
+        assert_eq!(files[0].total_size, None);
+    }
+
+    #[test]
+    fn test_positive_path_condition_does_not_prune_parents() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("src").join("bin")).unwrap();
+        fs::write(root.join("src").join("bin").join("q.rs"), "x").unwrap();
+
+        let conditions = parse_compound_conditions("path LIKE 'src/%'").unwrap();
+        let results = collect_files_recursive(
+            root,
+            root,
+            &conditions,
+            &FromOptions::default(),
+            false,
+            &CancelToken::new(),
+            &QueryLimits::default(),
+        )
+        .unwrap();
+        assert_eq!(results.len(), 2);
+    }
+
+    #[test]
+    fn test_symlinks_reported_and_followed_without_cycles() {
+        use std::os::unix::fs::symlink;
+
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir(root.join("dir")).unwrap();
+        fs::write(root.join("dir").join("file.txt"), "x").unwrap();
+        symlink("dir", root.join("link")).unwrap();
+        symlink("..", root.join("dir").join("back")).unwrap(); // Cycle back to the root
+        symlink("missing", root.join("dangling")).unwrap();
+
+        let walk = |follow_symlinks: bool| {
+            let options = FromOptions {
+                follow_symlinks,
+                ..Default::default()
+            };
+            let mut files = collect_files_recursive(
+                root,
+                root,
+                &[],
+                &options,
+                false,
+                &CancelToken::new(),
+                &QueryLimits::default(),
+            )
+            .unwrap();
+            files.sort_by(|a, b| a.path.cmp(&b.path));
+            files
+                .into_iter()
+                .map(|f| (f.path, f.file_type, f.link_target))
+                .collect::<Vec<_>>()
+        };
+        let entry = |path: &str, file_type: &str, target: Option<&str>| {
+            (
+                path.to_string(),
+                file_type.to_string(),
+                target.map(str::to_string),
+            )
+        };
+
+        // By default links are reported as themselves and never entered
+        assert_eq!(
+            walk(false),
+            vec![
+                entry("", "directory", None),
+                entry("dangling", "symlink", Some("missing")),
+                entry("dir", "directory", None),
+                entry("dir/back", "symlink", Some("..")),
+                entry("dir/file.txt", "file", None),
+                entry("link", "symlink", Some("dir")),
+            ]
+        );
+
+        // Followed links take their target's type; links back to an ancestor are
+        // reported but not entered, and dangling links stay symlinks
+        assert_eq!(
+            walk(true),
+            vec![
+                entry("", "directory", None),
+                entry("dangling", "symlink", Some("missing")),
+                entry("dir", "directory", None),
+                entry("dir/back", "directory", Some("..")),
+                entry("dir/file.txt", "file", None),
+                entry("link", "directory", Some("dir")),
+                entry("link/back", "directory", Some("..")),
+                entry("link/file.txt", "file", None),
+            ]
+        );
+    }
+
+    fn limits_with(update: impl FnOnce(&mut QueryLimits)) -> QueryLimits {
+        let mut limits = QueryLimits::default();
+        update(&mut limits);
+        limits
+    }
+
+    #[test]
+    fn test_subquery_depth_limit() {
+        let temp_dir = TempDir::new().unwrap();
+        let select = format!("SELECT name FROM {}", temp_dir.path().display());
+
+        // Nest four levels of EXISTS subqueries under the outer query
+        let mut query = crate::parser::parse_query(&select).unwrap();
+        for _ in 0..4 {
+            let mut outer = crate::parser::parse_query(&select).unwrap();
+            outer.where_subqueries.push(crate::models::Subquery {
+                query: Box::new(query),
+                subquery_type: crate::models::SubqueryType::Exists,
+            });
+            query = outer;
+        }
+
+        let error = execute_query(&query).unwrap_err();
+        assert_eq!(
+            error,
+            "Subqueries nested more than 3 levels deep (max subquery depth)"
+        );
+
+        let limits = limits_with(|l| l.max_subquery_depth = 4);
+        assert!(execute_query_with_limits(&query, &CancelToken::new(), &limits).is_ok());
+    }
+
+    #[test]
+    fn test_max_rows_limit() {
+        let temp_dir = TempDir::new().unwrap();
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            fs::write(temp_dir.path().join(name), "x").unwrap();
+        }
+
+        // Rows are counted as they are materialized, before LIMIT applies
+        let query = crate::parser::parse_query(&format!(
+            "SELECT name FROM {} WHERE type = 'file' LIMIT 1",
+            temp_dir.path().display()
+        ))
+        .unwrap();
+        let limits = limits_with(|l| l.max_rows = 2);
+        let error = execute_query_with_limits(&query, &CancelToken::new(), &limits).unwrap_err();
+        assert!(error.starts_with("Query produced more than 2 rows (max rows)"));
+
+        let limits = limits_with(|l| l.max_rows = 3);
+        assert!(execute_query_with_limits(&query, &CancelToken::new(), &limits).is_ok());
+    }
+
+    #[test]
+    fn test_max_traversal_depth_limit() {
+        let temp_dir = TempDir::new().unwrap();
+        let deep = temp_dir.path().join("a").join("b").join("c");
+        fs::create_dir_all(&deep).unwrap();
+        fs::write(deep.join("file.txt"), "x").unwrap();
+
+        let query =
+            crate::parser::parse_query(&format!("SELECT name FROM {}", temp_dir.path().display()))
+                .unwrap();
+        let limits = limits_with(|l| l.max_traversal_depth = 3);
+        let error = execute_query_with_limits(&query, &CancelToken::new(), &limits).unwrap_err();
+        assert_eq!(
+            error,
+            "'a/b/c/file.txt' is more than 3 levels below the FROM path (max traversal depth)"
+        );
+
+        let limits = limits_with(|l| l.max_traversal_depth = 4);
+        assert!(execute_query_with_limits(&query, &CancelToken::new(), &limits).is_ok());
+    }
+
+    #[test]
+    fn test_max_open_dirs_limit() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::create_dir(temp_dir.path().join("one")).unwrap();
+        fs::create_dir(temp_dir.path().join("two")).unwrap();
+
+        // The root and both subdirectories are opened
+        let query =
+            crate::parser::parse_query(&format!("SELECT name FROM {}", temp_dir.path().display()))
+                .unwrap();
+        let limits = limits_with(|l| l.max_open_dirs = 2);
+        let error = execute_query_with_limits(&query, &CancelToken::new(), &limits).unwrap_err();
+        assert_eq!(
+            error,
+            "Traversal opened more than 2 directories (max open directories)"
+        );
+
+        let limits = limits_with(|l| l.max_open_dirs = 3);
+        assert!(execute_query_with_limits(&query, &CancelToken::new(), &limits).is_ok());
+    }
+
     #[test]
     fn test_select_subquery_parsing() {
         // For now, test that basic parsing still works
@@ -0,0 +1,411 @@
+use crate::cancel::CancelToken;
+use crate::models::FileInfo;
+use chrono::{DateTime, Utc};
+use git2::{Repository, Sort, Status, StatusOptions};
+use std::collections::{HashMap, HashSet};
+use std::path::{Component, Path, PathBuf};
+
+/// What the repository holding a file says about it. Every field is `None`
+/// for files outside a repository.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GitInfo {
+    pub status: Option<&'static str>, // untracked, modified, staged, ignored or clean
+    pub last_commit_date: Option<DateTime<Utc>>, // Author date of the last commit changing it
+    pub last_author: Option<String>,
+    history_read: bool, // History is only walked when a query uses it
+}
+
+/// Fields that are computed from the repository a file is in
+pub fn is_git_field(field: &str) -> bool {
+    field == "git_status" || is_history_field(field)
+}
+
+/// Fields that need the commit history, not just the index and work tree
+pub fn is_history_field(field: &str) -> bool {
+    matches!(field, "git_last_commit_date" | "git_last_author")
+}
+
+/// Fill in the git columns of every row that doesn't have them yet, and
+/// the last commit too when `history` is set. Rows are grouped by the
+/// repository they are in, found by looking for `.git` above them, and each
+/// repository is read once.
+pub fn fill_git_info(rows: &mut [FileInfo], root: &Path, history: bool, cancel: &CancelToken) {
+    let pending = |file: &FileInfo| match &file.git {
+        None => true,
+        Some(info) => history && !info.history_read,
+    };
+    let Ok(root) = std::fs::canonicalize(root) else {
+        for file in rows.iter_mut().filter(|file| pending(file)) {
+            file.git = Some(Box::default());
+        }
+        return;
+    };
+
+    // Rows by repository, with their paths relative to its work tree
+    let mut workdirs: HashMap<PathBuf, Option<PathBuf>> = HashMap::new();
+    let mut repositories: HashMap<PathBuf, Vec<(usize, String)>> = HashMap::new();
+    for (index, file) in rows.iter_mut().enumerate() {
+        if !pending(file) {
+            continue;
+        }
+        let path = file.full_path(&root);
+        let start = match file.file_type.as_str() {
+            "directory" => Some(path.as_path()),
+            _ => path.parent(),
+        };
+        let workdir = start.and_then(|start| find_workdir(start, &mut workdirs));
+        let relative = workdir
+            .as_ref()
+            .and_then(|workdir| worktree_path(&path, workdir));
+        match (workdir, relative) {
+            // Archive entries and the like have paths that don't exist on disk
+            (Some(workdir), Some(relative)) if path.symlink_metadata().is_ok() => {
+                repositories
+                    .entry(workdir)
+                    .or_default()
+                    .push((index, relative));
+            }
+            _ => {
+                let info = file.git.get_or_insert_with(Box::default);
+                info.history_read = history;
+            }
+        }
+    }
+
+    for (workdir, members) in repositories {
+        if cancel.is_cancelled() {
+            return;
+        }
+        let repository = Repository::open(&workdir).ok();
+        let statuses = match &repository {
+            Some(repository) if members.iter().any(|&(i, _)| rows[i].git.is_none()) => {
+                read_statuses(repository).unwrap_or_default()
+            }
+            _ => HashMap::new(),
+        };
+        let commits = match &repository {
+            Some(repository) if history => {
+                let paths = members.iter().map(|(_, path)| path.as_str());
+                read_last_commits(repository, paths, cancel).unwrap_or_default()
+            }
+            _ => HashMap::new(),
+        };
+        for (index, path) in members {
+            let file = &mut rows[index];
+            let is_directory = file.file_type == "directory";
+            let info = file.git.get_or_insert_with(|| {
+                let status = repository
+                    .as_ref()
+                    .and_then(|_| status_of(&statuses, &path, is_directory));
+                Box::new(GitInfo {
+                    status,
+                    ..GitInfo::default()
+                })
+            });
+            if history && !info.history_read {
+                if let Some((date, author)) = commits.get(&path) {
+                    info.last_commit_date = Some(*date);
+                    info.last_author = Some(author.clone());
+                }
+                info.history_read = true;
+            }
+        }
+    }
+}
+
+// The nearest directory at or above `dir` with a `.git` in it, remembering
+// the answer for every directory on the way
+fn find_workdir(dir: &Path, cache: &mut HashMap<PathBuf, Option<PathBuf>>) -> Option<PathBuf> {
+    let mut visited = Vec::new();
+    let mut found = None;
+    for ancestor in dir.ancestors() {
+        if let Some(known) = cache.get(ancestor) {
+            found = known.clone();
+            break;
+        }
+        visited.push(ancestor.to_path_buf());
+        if ancestor.join(".git").exists() {
+            found = Some(ancestor.to_path_buf());
+            break;
+        }
+    }
+    for dir in visited {
+        cache.insert(dir, found.clone());
+    }
+    found
+}
+
+// `path` relative to the work tree with `/` separators, as git names it.
+// The work tree itself and anything inside `.git` have no status.
+fn worktree_path(path: &Path, workdir: &Path) -> Option<String> {
+    let relative = path.strip_prefix(workdir).ok()?;
+    let first = relative.components().next()?;
+    if first == Component::Normal(".git".as_ref()) {
+        return None;
+    }
+    let parts: Option<Vec<&str>> = relative.iter().map(|part| part.to_str()).collect();
+    Some(parts?.join("/"))
+}
+
+// Every path that isn't clean. Untracked and ignored directories are
+// listed once, as `dir/`, rather than file by file.
+fn read_statuses(repository: &Repository) -> Result<HashMap<String, Status>, git2::Error> {
+    let mut options = StatusOptions::new();
+    options
+        .include_untracked(true)
+        .recurse_untracked_dirs(false)
+        .include_ignored(true)
+        .recurse_ignored_dirs(false)
+        .exclude_submodules(true);
+    let statuses = repository.statuses(Some(&mut options))?;
+    Ok(statuses
+        .iter()
+        .filter_map(|entry| Some((entry.path()?.to_string(), entry.status())))
+        .collect())
+}
+
+fn status_of(
+    statuses: &HashMap<String, Status>,
+    path: &str,
+    is_directory: bool,
+) -> Option<&'static str> {
+    let own = if is_directory {
+        statuses.get(&format!("{}/", path))
+    } else {
+        statuses.get(path)
+    };
+    // Otherwise a directory above it may be untracked or ignored as a whole
+    let inherited = || {
+        path.match_indices('/')
+            .find_map(|(end, _)| statuses.get(&path[..=end]))
+    };
+    match own.or_else(inherited) {
+        Some(&status) => Some(status_name(status)),
+        // Git tracks files, not directories
+        None if is_directory => None,
+        None => Some("clean"),
+    }
+}
+
+// Changes not yet staged win over staged ones, as they are what `git add` would pick up
+fn status_name(status: Status) -> &'static str {
+    let modified = Status::WT_MODIFIED
+        | Status::WT_DELETED
+        | Status::WT_TYPECHANGE
+        | Status::WT_RENAMED
+        | Status::CONFLICTED;
+    let staged = Status::INDEX_NEW
+        | Status::INDEX_MODIFIED
+        | Status::INDEX_DELETED
+        | Status::INDEX_RENAMED
+        | Status::INDEX_TYPECHANGE;
+    if status.is_ignored() {
+        "ignored"
+    } else if status.is_wt_new() {
+        "untracked"
+    } else if status.intersects(modified) {
+        "modified"
+    } else if status.intersects(staged) {
+        "staged"
+    } else {
+        "clean"
+    }
+}
+
+/// The author date and name of the newest commit reachable from HEAD that
+/// changed each of `paths` (for a directory, anything below it), as
+/// `git log -1 -- path` finds it. Paths that aren't in HEAD are skipped,
+/// and the walk stops once every other one has been found.
+fn read_last_commits<'a>(
+    repository: &Repository,
+    paths: impl Iterator<Item = &'a str>,
+    cancel: &CancelToken,
+) -> Result<HashMap<String, (DateTime<Utc>, String)>, git2::Error> {
+    let head = repository.head()?.peel_to_commit()?;
+    let head_tree = head.tree()?;
+    let mut wanted: HashSet<String> = paths
+        .filter(|path| !path.is_empty() && head_tree.get_path(Path::new(path)).is_ok())
+        .map(str::to_string)
+        .collect();
+    let mut found = HashMap::new();
+
+    let mut walk = repository.revwalk()?;
+    walk.push(head.id())?;
+    walk.set_sorting(Sort::TIME)?;
+    for oid in walk {
+        if wanted.is_empty() || cancel.is_cancelled() {
+            break;
+        }
+        let commit = repository.find_commit(oid?)?;
+        let tree = commit.tree()?;
+        // A merge only changed what differs from every parent; the rest
+        // came from one of them and is found on that side
+        let mut changed: Option<HashSet<String>> = None;
+        let parents: Vec<_> = commit.parents().collect();
+        let parent_trees = parents
+            .iter()
+            .map(|parent| parent.tree().map(Some))
+            .collect::<Result<Vec<_>, _>>()?;
+        let parent_trees = if parent_trees.is_empty() {
+            vec![None]
+        } else {
+            parent_trees
+        };
+        for parent_tree in &parent_trees {
+            let diff = repository.diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), None)?;
+            let paths: HashSet<String> = diff
+                .deltas()
+                .flat_map(|delta| [delta.old_file().path(), delta.new_file().path()])
+                .flatten()
+                .filter_map(|path| path.to_str().map(str::to_string))
+                .collect();
+            changed = Some(match changed {
+                Some(changed) => changed.intersection(&paths).cloned().collect(),
+                None => paths,
+            });
+        }
+
+        let author = commit.author();
+        let date = DateTime::from_timestamp(author.when().seconds(), 0).unwrap_or_default();
+        let name = String::from_utf8_lossy(author.name_bytes()).into_owned();
+        for path in changed.unwrap_or_default() {
+            // The file itself, then each directory above it
+            let ancestors = path.match_indices('/').map(|(end, _)| &path[..end]);
+            for candidate in std::iter::once(path.as_str()).chain(ancestors) {
+                if wanted.remove(candidate) {
+                    found.insert(candidate.to_string(), (date, name.clone()));
+                }
+            }
+        }
+    }
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::{Signature, Time};
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn commit(repository: &Repository, paths: &[&str], author: &str, seconds: i64) {
+        let mut index = repository.index().unwrap();
+        for path in paths {
+            index.add_path(Path::new(path)).unwrap();
+        }
+        index.write().unwrap();
+        let tree = repository.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = Signature::new(author, "dev@example.com", &Time::new(seconds, 0)).unwrap();
+        let parent = repository.head().ok().map(|h| h.peel_to_commit().unwrap());
+        let parents: Vec<_> = parent.iter().collect();
+        repository
+            .commit(
+                Some("HEAD"),
+                &signature,
+                &signature,
+                "change",
+                &tree,
+                &parents,
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn test_fill_git_info() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let repository = Repository::init(root).unwrap();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::create_dir_all(root.join("target/debug")).unwrap();
+        fs::create_dir_all(root.join("notes")).unwrap();
+        fs::write(root.join(".gitignore"), "target/\n*.log\n").unwrap();
+        fs::write(root.join("README.md"), "readme\n").unwrap();
+        fs::write(root.join("src/main.rs"), "fn main() {}\n").unwrap();
+        commit(
+            &repository,
+            &[".gitignore", "README.md", "src/main.rs"],
+            "Ada",
+            1_700_000_000,
+        );
+        fs::write(root.join("src/main.rs"), "fn main() { run() }\n").unwrap();
+        commit(&repository, &["src/main.rs"], "Grace", 1_700_086_400);
+
+        fs::write(root.join("README.md"), "changed\n").unwrap();
+        fs::write(root.join("src/lib.rs"), "pub fn run() {}\n").unwrap();
+        let mut index = repository.index().unwrap();
+        index.add_path(Path::new("src/lib.rs")).unwrap();
+        index.write().unwrap();
+        fs::write(root.join("notes/todo.txt"), "x\n").unwrap();
+        fs::write(root.join("debug.log"), "x\n").unwrap();
+        fs::write(root.join("target/debug/app"), "x\n").unwrap();
+
+        let names = [
+            "README.md",
+            "src/main.rs",
+            "src/lib.rs",
+            "notes/todo.txt",
+            "debug.log",
+            "target/debug/app",
+            ".gitignore",
+            "src",
+            "target",
+            ".git/HEAD",
+        ];
+        let mut rows: Vec<FileInfo> = names
+            .iter()
+            .map(|name| FileInfo::new(&root.join(name), root).unwrap())
+            .collect();
+        let cancel = CancelToken::new();
+        fill_git_info(&mut rows, root, false, &cancel);
+        let statuses: Vec<_> = rows
+            .iter()
+            .map(|r| r.git.as_ref().unwrap().status)
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                Some("modified"),
+                Some("clean"),
+                Some("staged"),
+                Some("untracked"),
+                Some("ignored"),
+                Some("ignored"),
+                Some("clean"),
+                None,
+                Some("ignored"),
+                None,
+            ]
+        );
+        assert_eq!(rows[1].git.as_ref().unwrap().last_author, None);
+
+        fill_git_info(&mut rows, root, true, &cancel);
+        let author = |i: usize| rows[i].git.as_ref().unwrap().last_author.clone();
+        assert_eq!(author(0).as_deref(), Some("Ada"));
+        assert_eq!(author(1).as_deref(), Some("Grace"));
+        assert_eq!(author(2), None); // Staged, but not committed yet
+        assert_eq!(author(7).as_deref(), Some("Grace"));
+        assert_eq!(
+            rows[0]
+                .git
+                .as_ref()
+                .unwrap()
+                .last_commit_date
+                .unwrap()
+                .timestamp(),
+            1_700_000_000
+        );
+
+        let outside = TempDir::new().unwrap();
+        fs::write(outside.path().join("loose.txt"), "x\n").unwrap();
+        let mut rows =
+            vec![FileInfo::new(&outside.path().join("loose.txt"), outside.path()).unwrap()];
+        fill_git_info(&mut rows, outside.path(), true, &cancel);
+        assert_eq!(
+            rows[0].git.as_deref(),
+            Some(&GitInfo {
+                history_read: true,
+                ..GitInfo::default()
+            })
+        );
+    }
+}
@@ -1,5 +1,7 @@
+use crate::columns::display_value;
 use crate::{
-    execute_query, get_template_dir, load_template_content, parse_query, save_template, QueryResult,
+    execute_query_with_cancel, get_template_dir, load_template_content, parse_query, save_template,
+    CancelToken, QueryResult,
 };
 use iced::{
     widget::{
@@ -21,6 +23,7 @@ pub struct Gui {
     templates: Vec<String>,
     selected_template: Option<String>,
     is_loading: bool,
+    cancel_token: Option<CancelToken>, // Token for the running query, used by the Cancel button
     spinner_frame: usize,
     sort_column: Option<usize>,
     sort_direction: SortDirection,
@@ -40,6 +43,7 @@ pub enum SortDirection {
 pub enum Message {
     QueryChanged(iced::widget::text_editor::Action),
     ExecuteQuery,
+    CancelQuery, // Stop the running query and keep its partial results
     QueryExecuted(Result<QueryResultData, String>),
     TemplateSelected(String),
     LoadTemplate(String),
@@ -65,6 +69,7 @@ pub struct QueryResultData {
     pub all_rows: Option<Vec<GuiResultRow>>, // All results for pagination
     pub execution_time: u128,
     pub is_file_results: bool,
+    pub cancelled: bool, // Query was stopped early, rows are partial
 }
 
 impl Default for Gui {
@@ -78,6 +83,7 @@ impl Default for Gui {
             templates: Vec::new(),
             selected_template: None,
             is_loading: false,
+            cancel_token: None,
             spinner_frame: 0,
             sort_column: None,
             sort_direction: SortDirection::Default,
@@ -107,14 +113,17 @@ impl Gui {
         }
     }
 
-    fn execute_query_async(content: iced::widget::text_editor::Content) -> Command<Message> {
+    fn execute_query_async(
+        content: iced::widget::text_editor::Content,
+        cancel: CancelToken,
+    ) -> Command<Message> {
         Command::perform(
             async move {
                 let start_time = Instant::now();
                 let query_text = content.text();
 
                 match parse_query(&query_text) {
-                    Ok(query) => match execute_query(&query) {
+                    Ok(query) => match execute_query_with_cancel(&query, &cancel) {
                         Ok(results) => {
                             let (headers, result_rows, is_file_results) = match results {
                                 QueryResult::Files(files) => {
@@ -162,7 +171,9 @@ impl Gui {
                                                 "size" => file.size.clone(),
                                                 "path" => file.path.clone(),
                                                 "depth" => file.depth.to_string(),
-                                                _ => "".to_string(), // Unknown field
+                                                other => {
+                                                    display_value(&file, other).unwrap_or_default()
+                                                }
                                             };
                                             columns.push(value);
                                         }
@@ -309,6 +320,37 @@ impl Gui {
                                     }
                                     (headers, rows, false)
                                 }
+                                QueryResult::Table(table) => {
+                                    // `*` is every column of the file
+                                    let selected_fields = table.fields(&query.select_fields);
+
+                                    // Create headers from selected fields (capitalize first letter)
+                                    let headers: Vec<String> = selected_fields
+                                        .iter()
+                                        .map(|field| {
+                                            let mut chars = field.chars();
+                                            match chars.next() {
+                                                None => String::new(),
+                                                Some(first) => {
+                                                    first.to_uppercase().collect::<String>()
+                                                        + chars.as_str()
+                                                }
+                                            }
+                                        })
+                                        .collect();
+
+                                    let mut rows = Vec::new();
+                                    for row in &table.rows {
+                                        let columns = selected_fields
+                                            .iter()
+                                            .map(|field| {
+                                                table.display_value(row, field).unwrap_or_default()
+                                            })
+                                            .collect();
+                                        rows.push(GuiResultRow { columns });
+                                    }
+                                    (headers, rows, false)
+                                }
                             };
                             let execution_time = start_time.elapsed().as_millis();
 
@@ -325,6 +367,7 @@ impl Gui {
                                 all_rows: Some(result_rows), // Store all results for pagination
                                 execution_time,
                                 is_file_results,
+                                cancelled: cancel.is_cancelled(),
                             })
                         }
                         Err(e) => Err(format!("Error executing query: {}", e)),
@@ -336,6 +379,13 @@ impl Gui {
         )
     }
 
+    // Replace the token of any previous query with a fresh one for the next run
+    fn start_cancel_token(&mut self) -> CancelToken {
+        let token = CancelToken::new();
+        self.cancel_token = Some(token.clone());
+        token
+    }
+
     fn load_template(&mut self, template_name: String) {
         match load_template_content(&template_name) {
             Ok(content) => {
@@ -472,10 +522,18 @@ impl Application for Gui {
                 self.status = "Executing query...".to_string();
                 let content =
                     iced::widget::text_editor::Content::with_text(&self.query_content.text());
-                Self::execute_query_async(content)
+                Self::execute_query_async(content, self.start_cancel_token())
+            }
+            Message::CancelQuery => {
+                if let Some(token) = &self.cancel_token {
+                    token.cancel();
+                    self.status = "Cancelling query...".to_string();
+                }
+                Command::none()
             }
             Message::QueryExecuted(result) => {
                 self.is_loading = false;
+                self.cancel_token = None;
                 match result {
                     Ok(data) => {
                         self.column_headers = data.headers;
@@ -488,7 +546,12 @@ impl Application for Gui {
                         self.sort_direction = SortDirection::Default;
 
                         let total_count = self.all_results.len();
-                        if total_count > 200 {
+                        if data.cancelled {
+                            self.status = format!(
+                                "Query cancelled after {:.3}ms - Showing {} of {} partial results",
+                                data.execution_time, self.displayed_count, total_count
+                            );
+                        } else if total_count > 200 {
                             self.status = format!(
                                 "Query executed in {:.3}ms - Showing {} of {} results",
                                 data.execution_time, self.displayed_count, total_count
@@ -563,29 +626,27 @@ impl Application for Gui {
                 Command::none()
             }
             Message::KeyboardEvent(event) => {
-                match event {
-                    iced::Event::Keyboard(iced::keyboard::Event::KeyPressed {
-                        key: iced::keyboard::Key::Named(iced::keyboard::key::Named::Enter),
-                        modifiers,
-                        ..
-                    }) => {
-                        // Check for Cmd+Enter (Mac) or Ctrl+Enter (other platforms)
-                        #[cfg(target_os = "macos")]
-                        let is_modifier_pressed = modifiers.command();
-                        #[cfg(not(target_os = "macos"))]
-                        let is_modifier_pressed = modifiers.control();
-
-                        if is_modifier_pressed && !self.is_loading {
-                            let query_text = self.query_content.text().trim().to_uppercase();
-                            if query_text.starts_with("DELETE") {
-                                // Show warning for DELETE queries executed via shortcut
-                                self.status = "⚠️ DELETE query executed via keyboard shortcut. Please review before confirming.".to_string();
-                            }
-                            // Trigger query execution
-                            return self.update(Message::ExecuteQuery);
+                if let iced::Event::Keyboard(iced::keyboard::Event::KeyPressed {
+                    key: iced::keyboard::Key::Named(iced::keyboard::key::Named::Enter),
+                    modifiers,
+                    ..
+                }) = event
+                {
+                    // Check for Cmd+Enter (Mac) or Ctrl+Enter (other platforms)
+                    #[cfg(target_os = "macos")]
+                    let is_modifier_pressed = modifiers.command();
+                    #[cfg(not(target_os = "macos"))]
+                    let is_modifier_pressed = modifiers.control();
+
+                    if is_modifier_pressed && !self.is_loading {
+                        let query_text = self.query_content.text().trim().to_uppercase();
+                        if query_text.starts_with("DELETE") {
+                            // Show warning for DELETE queries executed via shortcut
+                            self.status = "⚠️ DELETE query executed via keyboard shortcut. Please review before confirming.".to_string();
                         }
+                        // Trigger query execution
+                        return self.update(Message::ExecuteQuery);
                     }
-                    _ => {}
                 }
                 Command::none()
             }
@@ -604,7 +665,7 @@ impl Application for Gui {
 
                         // Execute the query immediately
                         let content = iced::widget::text_editor::Content::with_text(&delete_query);
-                        Self::execute_query_async(content)
+                        Self::execute_query_async(content, self.start_cancel_token())
                     } else {
                         Command::none()
                     }
@@ -677,9 +738,13 @@ impl Application for Gui {
             .on_press(Message::SaveTemplate)
             .padding(10);
 
-        let controls_row = row![template_picklist, execute_button, save_button]
+        let mut controls_row = row![template_picklist, execute_button, save_button]
             .spacing(10)
             .align_items(Alignment::Start);
+        if self.is_loading {
+            controls_row =
+                controls_row.push(button("Cancel").on_press(Message::CancelQuery).padding(10));
+        }
 
         let query_section = container(column![query_input, controls_row].spacing(15)).padding(20);
 
@@ -727,7 +792,7 @@ impl Application for Gui {
 
             // Results table rows with modern styling
             let mut results_column = Column::new().spacing(0);
-            for (_row_idx, result) in self.results.iter().enumerate() {
+            for result in self.results.iter() {
                 let mut row: Row<'_, Message, Theme> = Row::new().spacing(0);
                 for (col_idx, column) in result.columns.iter().enumerate() {
                     let mut cell_container = container(text(column).size(13))
@@ -760,7 +825,7 @@ impl Application for Gui {
                 } else {
                     // For process results, make the row right-clickable for context menu
                     // PID is typically in the first column (index 0)
-                    let pid = result.columns.get(0).unwrap_or(&String::new()).clone();
+                    let pid = result.columns.first().unwrap_or(&String::new()).clone();
                     iced::widget::container(
                         iced::widget::mouse_area(row)
                             .on_right_press(Message::RightClickProcess(pid)),
@@ -1122,9 +1187,11 @@ mod tests {
             modified_date: chrono::Utc::now(),
             permissions: "644".to_string(),
             size: "1024 B".to_string(),
+            size_bytes: 1024,
             path: "./test.txt".to_string(),
             depth: 1,
             extension: Some("txt".to_string()),
+            ..Default::default()
         };
 
         let selected_fields = vec!["name".to_string()];
@@ -1172,7 +1239,7 @@ mod tests {
 
     #[test]
     fn test_column_header_capitalization() {
-        let selected_fields = vec![
+        let selected_fields = [
             "name".to_string(),
             "file_type".to_string(),
             "cpu_usage".to_string(),
@@ -1218,6 +1285,7 @@ mod tests {
             order_direction: crate::models::SortDirection::Ascending,
             limit: None,
             distinct: false,
+            from_options: Default::default(),
         };
 
         let selected_fields = if query_with_empty_fields.select_fields.is_empty() {
@@ -1253,6 +1321,7 @@ mod tests {
             order_direction: crate::models::SortDirection::Ascending,
             limit: None,
             distinct: false,
+            from_options: Default::default(),
         };
 
         // This would be handled by the parser expansion, but test the GUI logic
@@ -1281,4 +1350,18 @@ mod tests {
         assert_eq!(gui.spinner_frame, 0);
         assert!(!gui.is_loading);
     }
+
+    #[test]
+    fn test_cancel_query_flips_running_token() {
+        let mut gui = Gui::default();
+        let token = gui.start_cancel_token();
+        gui.is_loading = true;
+
+        let _ = gui.update(Message::CancelQuery);
+        assert!(token.is_cancelled());
+        assert_eq!(gui.status, "Cancelling query...");
+
+        // The next query gets a fresh token
+        assert!(!gui.start_cancel_token().is_cancelled());
+    }
 }
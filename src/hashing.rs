@@ -0,0 +1,188 @@
+use crate::cancel::CancelToken;
+use crate::models::{Condition, FileInfo};
+use crate::predicate::compile_condition;
+use crate::utils::open_regular_file;
+use rayon::prelude::*;
+use ring::digest::{Context, SHA256};
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::Path;
+use xxhash_rust::xxh3::Xxh3Default;
+
+/// Content hash columns, computed only for queries that reference them
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HashKind {
+    Fast,   // `hash`: 128-bit XXH3, for finding identical files
+    Sha256, // `sha256`: for checking against published checksums
+}
+
+impl HashKind {
+    pub fn for_field(field: &str) -> Option<Self> {
+        match field {
+            "hash" => Some(HashKind::Fast),
+            "sha256" => Some(HashKind::Sha256),
+            _ => None,
+        }
+    }
+
+    fn get(self, file: &FileInfo) -> Option<&String> {
+        match self {
+            HashKind::Fast => file.hash.as_ref(),
+            HashKind::Sha256 => file.sha256.as_ref(),
+        }
+    }
+
+    fn slot(self, file: &mut FileInfo) -> &mut Option<String> {
+        match self {
+            HashKind::Fast => &mut file.hash,
+            HashKind::Sha256 => &mut file.sha256,
+        }
+    }
+}
+
+/// Hex digest of a file's contents, or `None` if it isn't a readable regular file
+pub fn hash_file(path: &Path, kind: HashKind, cancel: &CancelToken) -> Option<String> {
+    let mut file = open_regular_file(path)?;
+    let mut buffer = vec![0u8; 64 * 1024];
+    let mut fast = Xxh3Default::new();
+    let mut sha256 = Context::new(&SHA256);
+
+    loop {
+        // Give up on large files promptly once the query is cancelled
+        if cancel.is_cancelled() {
+            return None;
+        }
+        let read = file.read(&mut buffer).ok()?;
+        if read == 0 {
+            break;
+        }
+        match kind {
+            HashKind::Fast => fast.update(&buffer[..read]),
+            HashKind::Sha256 => sha256.update(&buffer[..read]),
+        }
+    }
+
+    Some(match kind {
+        HashKind::Fast => format!("{:032x}", fast.digest128()),
+        HashKind::Sha256 => sha256
+            .finish()
+            .as_ref()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect(),
+    })
+}
+
+/// Fill in the hash for every regular file that doesn't have one yet, in parallel.
+/// Directories, special files and unreadable files are left as NULL.
+pub fn fill_hashes(rows: &mut [FileInfo], root: &Path, kind: HashKind, cancel: &CancelToken) {
+    rows.par_iter_mut()
+        .filter(|file| file.file_type == "file")
+        .for_each(|file| {
+            if kind.slot(file).is_none() {
+                let hash = hash_file(&file.full_path(root), kind, cancel);
+                *kind.slot(file) = hash;
+            }
+        });
+}
+
+/// Keep the non-empty files whose contents match at least one other row
+/// (or, when `negated`, every file that has a hash and no such twin).
+///
+/// A row without a hash, like a directory or an unreadable file, is NULL
+/// and matches neither, as in SQL. Files are grouped by size first, so
+/// `IN` only reads and hashes files sharing a size with another one.
+pub fn retain_duplicates(
+    rows: &mut Vec<FileInfo>,
+    root: &Path,
+    kind: HashKind,
+    negated: bool,
+    cancel: &CancelToken,
+) {
+    let mut sizes: HashMap<u64, usize> = HashMap::new();
+    for file in rows.iter().filter(|file| is_candidate(file)) {
+        *sizes.entry(file.size_bytes).or_default() += 1;
+    }
+    // Every file kept by NOT IN needs its hash, to tell it from NULL
+    let needs_hash = |file: &FileInfo| {
+        if negated {
+            file.file_type == "file"
+        } else {
+            is_candidate(file) && sizes[&file.size_bytes] > 1
+        }
+    };
+
+    let mut hashed: Vec<FileInfo> = rows.drain(..).filter(needs_hash).collect();
+    fill_hashes(&mut hashed, root, kind, cancel);
+
+    let mut copies: HashMap<(u64, String), usize> = HashMap::new();
+    for file in hashed.iter().filter(|file| is_candidate(file)) {
+        if let Some(hash) = kind.get(file) {
+            *copies.entry((file.size_bytes, hash.clone())).or_default() += 1;
+        }
+    }
+    let is_duplicate = |file: &FileInfo| {
+        is_candidate(file)
+            && kind
+                .get(file)
+                .is_some_and(|hash| copies[&(file.size_bytes, hash.clone())] > 1)
+    };
+
+    rows.extend(
+        hashed
+            .into_iter()
+            .filter(|file| kind.get(file).is_some() && is_duplicate(file) != negated),
+    );
+}
+
+/// Apply WHERE conditions on `hash` / `sha256`, hashing only the rows that
+/// survived every other condition
+pub fn apply_hash_conditions(
+    rows: &mut Vec<FileInfo>,
+    root: &Path,
+    conditions: &[Condition],
+    cancel: &CancelToken,
+) {
+    for condition in conditions {
+        let Some(kind) = HashKind::for_field(&condition.field) else {
+            continue;
+        };
+        if condition.operator == "IN" {
+            retain_duplicates(rows, root, kind, condition.negated, cancel);
+        } else {
+            fill_hashes(rows, root, kind, cancel);
+            let predicate = compile_condition::<FileInfo>(condition);
+            rows.retain(|file| predicate.matches(file));
+        }
+    }
+}
+
+// Empty files are all identical, which is rarely what a duplicate search is after
+fn is_candidate(file: &FileInfo) -> bool {
+    file.file_type == "file" && file.size_bytes > 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_hash_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("abc.txt");
+        fs::write(&path, "abc").unwrap();
+        let cancel = CancelToken::new();
+
+        assert_eq!(
+            hash_file(&path, HashKind::Sha256, &cancel).unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(hash_file(&path, HashKind::Fast, &cancel).unwrap().len(), 32);
+        assert_eq!(
+            hash_file(&temp_dir.path().join("missing"), HashKind::Fast, &cancel),
+            None
+        );
+    }
+}
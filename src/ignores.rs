@@ -0,0 +1,142 @@
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Per-directory ignore file names, in increasing order of precedence
+const IGNORE_FILES: [&str; 2] = [".gitignore", ".ignore"];
+
+/// The ignore rules in effect for one directory of a traversal.
+///
+/// Matchers are stacked from the outermost (global git excludes) to the
+/// innermost (the directory's own `.gitignore` / `.ignore`). As in git, the
+/// innermost file with a matching pattern decides, so a `!pattern` in a
+/// subdirectory can re-include something a parent ignores. Clones share the
+/// parsed matchers, so handing the rules down to a child directory is cheap.
+#[derive(Clone, Default)]
+pub struct IgnoreRules {
+    matchers: Vec<Arc<Gitignore>>,
+}
+
+impl IgnoreRules {
+    /// Rules that apply above `root`: global git excludes, the repository's
+    /// `.git/info/exclude` and ignore files in the directories between the
+    /// repository root and `root`. `root` must be absolute.
+    ///
+    /// Ignore files in `root` itself are picked up by `descend` like any
+    /// other directory's.
+    pub fn for_root(root: &Path) -> Self {
+        let repo_root = find_repo_root(root);
+        let mut rules = IgnoreRules::default();
+
+        let (global, _) = GitignoreBuilder::new(repo_root.unwrap_or(root)).build_global();
+        rules.push(global);
+
+        if let Some(repo_root) = repo_root {
+            let mut builder = GitignoreBuilder::new(repo_root);
+            builder.add(repo_root.join(".git").join("info").join("exclude"));
+            if let Ok(exclude) = builder.build() {
+                rules.push(exclude);
+            }
+
+            // Parents of the FROM path, outermost first
+            let mut parents: Vec<&Path> = root
+                .ancestors()
+                .skip(1)
+                .take_while(|dir| dir.starts_with(repo_root))
+                .collect();
+            parents.reverse();
+            for dir in parents {
+                if let Some(matcher) = load_dir_rules(dir) {
+                    rules.push(matcher);
+                }
+            }
+        }
+
+        rules
+    }
+
+    /// Rules for the children of `dir`, or `None` if `dir` has no ignore files
+    /// of its own and the current rules carry over unchanged.
+    pub fn descend(&self, dir: &Path) -> Option<Self> {
+        let matcher = load_dir_rules(dir)?;
+        let mut rules = self.clone();
+        rules.push(matcher);
+        Some(rules)
+    }
+
+    /// Whether the absolute `path` is ignored
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        for matcher in self.matchers.iter().rev() {
+            let matched = matcher.matched(path, is_dir);
+            if matched.is_ignore() {
+                return true;
+            }
+            if matched.is_whitelist() {
+                return false;
+            }
+        }
+        false
+    }
+
+    fn push(&mut self, matcher: Gitignore) {
+        if !matcher.is_empty() {
+            self.matchers.push(Arc::new(matcher));
+        }
+    }
+}
+
+/// The closest directory at or above `dir` containing `.git`
+fn find_repo_root(dir: &Path) -> Option<&Path> {
+    dir.ancestors().find(|dir| dir.join(".git").exists())
+}
+
+/// `.gitignore` and `.ignore` in `dir` combined into one matcher, with
+/// `.ignore` taking precedence. Unreadable or malformed lines are skipped.
+fn load_dir_rules(dir: &Path) -> Option<Gitignore> {
+    let files: Vec<PathBuf> = IGNORE_FILES
+        .iter()
+        .map(|name| dir.join(name))
+        .filter(|file| file.is_file())
+        .collect();
+    if files.is_empty() {
+        return None;
+    }
+
+    let mut builder = GitignoreBuilder::new(dir);
+    for file in files {
+        builder.add(file);
+    }
+    builder.build().ok().filter(|matcher| !matcher.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_innermost_ignore_file_wins() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().canonicalize().unwrap();
+        fs::create_dir(root.join(".git")).unwrap();
+        fs::create_dir(root.join("logs")).unwrap();
+        fs::write(root.join(".gitignore"), "*.log\nbuild/\n").unwrap();
+        fs::write(root.join("logs").join(".ignore"), "!keep.log\n").unwrap();
+
+        let rules = IgnoreRules::for_root(&root).descend(&root).unwrap();
+        assert!(rules.is_ignored(&root.join("debug.log"), false));
+        assert!(rules.is_ignored(&root.join("build"), true));
+        assert!(!rules.is_ignored(&root.join("build"), false));
+        assert!(!rules.is_ignored(&root.join("main.rs"), false));
+
+        let logs = root.join("logs");
+        let rules = rules.descend(&logs).unwrap();
+        assert!(rules.is_ignored(&logs.join("other.log"), false));
+        assert!(!rules.is_ignored(&logs.join("keep.log"), false));
+
+        // Rules above the FROM path apply when querying a subdirectory
+        let rules = IgnoreRules::for_root(&logs);
+        assert!(rules.is_ignored(&logs.join("other.log"), false));
+    }
+}
@@ -0,0 +1,272 @@
+use crate::cancel::CancelToken;
+use crate::models::FileInfo;
+use crate::utils::open_regular_file;
+use chrono::{DateTime, FixedOffset, NaiveDate, TimeZone, Utc};
+use exif::{Exif, In, Tag};
+use imagesize::ImageType;
+use rayon::prelude::*;
+use std::io::{BufRead, BufReader, Seek};
+use std::path::Path;
+
+/// Dimensions and EXIF metadata of an image, read from its headers without
+/// decoding any pixels. Every field is `None` for files that aren't images.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ImageInfo {
+    pub width: Option<u64>,
+    pub height: Option<u64>,
+    pub format: Option<&'static str>,
+    pub camera: Option<String>, // EXIF make and model, e.g. "Canon EOS R5"
+    pub taken_at: Option<DateTime<Utc>>, // DateTimeOriginal
+    pub orientation: Option<u64>, // EXIF orientation, 1 (upright) to 8
+    pub gps_latitude: Option<f64>, // Decimal degrees, negative in the south
+    pub gps_longitude: Option<f64>, // Decimal degrees, negative in the west
+}
+
+/// Fields that are computed by reading an image's headers
+pub fn is_image_field(field: &str) -> bool {
+    matches!(
+        field,
+        "image_width"
+            | "image_height"
+            | "image_format"
+            | "camera"
+            | "taken_at"
+            | "orientation"
+            | "gps_latitude"
+            | "gps_longitude"
+    )
+}
+
+/// Metadata of a JPEG, PNG, GIF or WebP image. Only the headers and the
+/// EXIF block are read; anything else gives an empty `ImageInfo`.
+pub fn read_image<R: BufRead + Seek>(reader: &mut R) -> ImageInfo {
+    let format = match imagesize::reader_type(&mut *reader) {
+        Ok(ImageType::Jpeg) => "jpeg",
+        Ok(ImageType::Png) => "png",
+        Ok(ImageType::Gif) => "gif",
+        Ok(ImageType::Webp) => "webp",
+        _ => return ImageInfo::default(),
+    };
+    let mut info = ImageInfo {
+        format: Some(format),
+        ..ImageInfo::default()
+    };
+    if reader.rewind().is_ok() {
+        if let Ok(size) = imagesize::reader_size(&mut *reader) {
+            info.width = Some(size.width as u64);
+            info.height = Some(size.height as u64);
+        }
+    }
+    // GIF has no EXIF
+    if format != "gif" && reader.rewind().is_ok() {
+        if let Ok(exif) = exif::Reader::new().read_from_container(reader) {
+            apply_exif(&mut info, &exif);
+        }
+    }
+    info
+}
+
+fn apply_exif(info: &mut ImageInfo, exif: &Exif) {
+    let make = ascii_field(exif, Tag::Make);
+    let model = ascii_field(exif, Tag::Model);
+    info.camera = match (make, model) {
+        // Most models already start with the make ("Canon" + "Canon EOS R5")
+        (Some(make), Some(model)) if model.to_lowercase().starts_with(&make.to_lowercase()) => {
+            Some(model)
+        }
+        (Some(make), Some(model)) => Some(format!("{} {}", make, model)),
+        (make, model) => make.or(model),
+    };
+    info.orientation = exif
+        .get_field(Tag::Orientation, In::PRIMARY)
+        .and_then(|field| field.value.get_uint(0))
+        .map(u64::from);
+    info.taken_at = taken_at(exif);
+    info.gps_latitude = gps_coordinate(exif, Tag::GPSLatitude, Tag::GPSLatitudeRef, b'S');
+    info.gps_longitude = gps_coordinate(exif, Tag::GPSLongitude, Tag::GPSLongitudeRef, b'W');
+}
+
+// The first string of an ASCII field, without the padding cameras add
+fn ascii_field(exif: &Exif, tag: Tag) -> Option<String> {
+    let exif::Value::Ascii(values) = &exif.get_field(tag, In::PRIMARY)?.value else {
+        return None;
+    };
+    let text = String::from_utf8_lossy(values.first()?);
+    let text = text.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+// DateTimeOriginal is the camera's local time. With OffsetTimeOriginal it
+// is converted to UTC; without one it is taken as UTC, so it reads as the
+// time the camera showed.
+fn taken_at(exif: &Exif) -> Option<DateTime<Utc>> {
+    let field = exif.get_field(Tag::DateTimeOriginal, In::PRIMARY)?;
+    let exif::Value::Ascii(values) = &field.value else {
+        return None;
+    };
+    let mut time = exif::DateTime::from_ascii(values.first()?).ok()?;
+    if let Some(field) = exif.get_field(Tag::OffsetTimeOriginal, In::PRIMARY) {
+        if let exif::Value::Ascii(values) = &field.value {
+            if let Some(offset) = values.first() {
+                let _ = time.parse_offset(offset);
+            }
+        }
+    }
+    let local = NaiveDate::from_ymd_opt(time.year.into(), time.month.into(), time.day.into())?
+        .and_hms_opt(time.hour.into(), time.minute.into(), time.second.into())?;
+    let offset = FixedOffset::east_opt(i32::from(time.offset.unwrap_or(0)) * 60)?;
+    Some(
+        offset
+            .from_local_datetime(&local)
+            .single()?
+            .with_timezone(&Utc),
+    )
+}
+
+// Degrees, minutes and seconds as decimal degrees, negated for the
+// reference that points south or west
+fn gps_coordinate(exif: &Exif, tag: Tag, reference: Tag, negative: u8) -> Option<f64> {
+    let exif::Value::Rational(parts) = &exif.get_field(tag, In::PRIMARY)?.value else {
+        return None;
+    };
+    let degrees = parts
+        .iter()
+        .zip([1.0, 60.0, 3600.0])
+        .map(|(part, unit)| part.to_f64() / unit)
+        .sum::<f64>();
+    if !degrees.is_finite() {
+        return None;
+    }
+    let negated = match &exif.get_field(reference, In::PRIMARY)?.value {
+        exif::Value::Ascii(values) => values.first()?.first() == Some(&negative),
+        _ => false,
+    };
+    Some(if negated { -degrees } else { degrees })
+}
+
+/// Metadata of the image at `path`, empty if it isn't a readable image
+pub fn read_image_file(path: &Path) -> ImageInfo {
+    match open_regular_file(path) {
+        Some(file) => read_image(&mut BufReader::new(file)),
+        None => ImageInfo::default(),
+    }
+}
+
+/// Fill in the image metadata of every row that doesn't have it yet, in parallel
+pub fn fill_image_info(rows: &mut [FileInfo], root: &Path, cancel: &CancelToken) {
+    rows.par_iter_mut()
+        .filter(|file| file.image.is_none())
+        .for_each(|file| {
+            if cancel.is_cancelled() {
+                return;
+            }
+            let info = match file.file_type.as_str() {
+                "file" => read_image_file(&file.full_path(root)),
+                _ => ImageInfo::default(),
+            };
+            file.image = Some(Box::new(info));
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use exif::experimental::Writer;
+    use exif::{Field, Rational};
+    use std::io::Cursor;
+
+    // A JPEG with an APP1 EXIF segment holding `fields`, then a frame header
+    fn jpeg_with_exif(fields: &[Field], width: u16, height: u16) -> Vec<u8> {
+        let mut writer = Writer::new();
+        for field in fields {
+            writer.push_field(field);
+        }
+        let mut tiff = Cursor::new(Vec::new());
+        writer.write(&mut tiff, false).unwrap();
+        let tiff = tiff.into_inner();
+
+        let mut jpeg = vec![0xff, 0xd8, 0xff, 0xe1];
+        jpeg.extend(((tiff.len() + 8) as u16).to_be_bytes());
+        jpeg.extend(b"Exif\0\0");
+        jpeg.extend(tiff);
+        jpeg.extend([0xff, 0xc0, 0x00, 0x11, 0x08]);
+        jpeg.extend(height.to_be_bytes());
+        jpeg.extend(width.to_be_bytes());
+        jpeg.extend([3, 1, 0x22, 0, 2, 0x11, 1, 3, 0x11, 1]);
+        jpeg.extend([0xff, 0xd9]);
+        jpeg
+    }
+
+    fn ascii(tag: Tag, text: &str) -> Field {
+        Field {
+            tag,
+            ifd_num: In::PRIMARY,
+            value: exif::Value::Ascii(vec![text.as_bytes().to_vec()]),
+        }
+    }
+
+    fn rationals(tag: Tag, parts: &[(u32, u32)]) -> Field {
+        Field {
+            tag,
+            ifd_num: In::PRIMARY,
+            value: exif::Value::Rational(parts.iter().map(|&p| Rational::from(p)).collect()),
+        }
+    }
+
+    #[test]
+    fn test_read_jpeg_exif() {
+        let fields = [
+            ascii(Tag::Make, "Canon"),
+            ascii(Tag::Model, "Canon EOS R5"),
+            Field {
+                tag: Tag::Orientation,
+                ifd_num: In::PRIMARY,
+                value: exif::Value::Short(vec![6]),
+            },
+            ascii(Tag::DateTimeOriginal, "2024:07:14 18:30:05"),
+            ascii(Tag::OffsetTimeOriginal, "+02:00"),
+            rationals(Tag::GPSLatitude, &[(48, 1), (51, 1), (2400, 100)]),
+            ascii(Tag::GPSLatitudeRef, "N"),
+            rationals(Tag::GPSLongitude, &[(2, 1), (21, 1), (0, 1)]),
+            ascii(Tag::GPSLongitudeRef, "W"),
+        ];
+        let info = read_image(&mut Cursor::new(jpeg_with_exif(&fields, 640, 480)));
+        assert_eq!(info.format, Some("jpeg"));
+        assert_eq!((info.width, info.height), (Some(640), Some(480)));
+        assert_eq!(info.camera.as_deref(), Some("Canon EOS R5"));
+        assert_eq!(info.orientation, Some(6));
+        assert_eq!(
+            info.taken_at.unwrap().to_rfc3339(),
+            "2024-07-14T16:30:05+00:00"
+        );
+        assert!((info.gps_latitude.unwrap() - 48.856_667).abs() < 1e-6);
+        assert!((info.gps_longitude.unwrap() + 2.35).abs() < 1e-9);
+
+        let fields = [ascii(Tag::Make, "Apple"), ascii(Tag::Model, "iPhone 15")];
+        let info = read_image(&mut Cursor::new(jpeg_with_exif(&fields, 1, 1)));
+        assert_eq!(info.camera.as_deref(), Some("Apple iPhone 15"));
+        assert_eq!(info.taken_at, None);
+    }
+
+    #[test]
+    fn test_read_image_headers() {
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+        png.extend(1920u32.to_be_bytes());
+        png.extend(1080u32.to_be_bytes());
+        png.extend([8, 6, 0, 0, 0, 0, 0, 0, 0]);
+        let info = read_image(&mut Cursor::new(png));
+        assert_eq!(info.format, Some("png"));
+        assert_eq!((info.width, info.height), (Some(1920), Some(1080)));
+        assert_eq!(info.camera, None);
+
+        let gif = b"GIF89a\x20\x00\x10\x00\x80\x00\x00".to_vec();
+        let info = read_image(&mut Cursor::new(gif));
+        assert_eq!(info.format, Some("gif"));
+        assert_eq!((info.width, info.height), (Some(32), Some(16)));
+
+        assert_eq!(
+            read_image(&mut Cursor::new(b"hello world".to_vec())),
+            ImageInfo::default()
+        );
+    }
+}
@@ -0,0 +1,366 @@
+use crate::cancel::CancelToken;
+use crate::tables::{normalize_name, open_table_file, KindGuess, TableColumn, TableRow};
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+use std::io::BufReader;
+use std::path::Path;
+
+/// Where the records are in a JSON document, e.g. `$.items[*]` or `$.data['rows']`
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonPath {
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Key(String),
+    Index(usize),
+    Wildcard,
+}
+
+impl JsonPath {
+    /// Parse the subset of JSONPath made of `.key`, `['key']`, `[index]` and `[*]`
+    pub fn parse(path: &str) -> Result<Self, String> {
+        let invalid = || format!("Invalid JSON path '{}'", path);
+        let mut rest = path.trim().strip_prefix('$').ok_or_else(invalid)?;
+        let mut segments = Vec::new();
+        while !rest.is_empty() {
+            if let Some(after) = rest.strip_prefix('.') {
+                let end = after.find(['.', '[']).unwrap_or(after.len());
+                segments.push(match &after[..end] {
+                    "" => return Err(invalid()),
+                    "*" => Segment::Wildcard,
+                    key => Segment::Key(key.to_string()),
+                });
+                rest = &after[end..];
+            } else if let Some(after) = rest.strip_prefix('[') {
+                let end = after.find(']').ok_or_else(invalid)?;
+                let inner = after[..end].trim();
+                let quoted = ['\'', '"']
+                    .iter()
+                    .find_map(|&q| inner.strip_prefix(q).and_then(|k| k.strip_suffix(q)));
+                segments.push(match (inner, quoted) {
+                    (_, Some(key)) => Segment::Key(key.to_string()),
+                    ("*", None) => Segment::Wildcard,
+                    (index, None) => Segment::Index(index.parse().map_err(|_| invalid())?),
+                });
+                rest = &after[end + 1..];
+            } else {
+                return Err(invalid());
+            }
+        }
+        Ok(JsonPath { segments })
+    }
+
+    /// The values the path leads to from `root`, with `*` expanding to every
+    /// element or member
+    fn select<'a>(&self, root: &'a JsonValue) -> Vec<&'a JsonValue> {
+        let mut current = vec![root];
+        for segment in &self.segments {
+            current = current
+                .into_iter()
+                .flat_map(|value| match (segment, value) {
+                    (Segment::Key(key), JsonValue::Object(map)) => {
+                        map.get(key).into_iter().collect()
+                    }
+                    (Segment::Index(index), JsonValue::Array(items)) => {
+                        items.get(*index).into_iter().collect()
+                    }
+                    (Segment::Wildcard, JsonValue::Array(items)) => items.iter().collect(),
+                    (Segment::Wildcard, JsonValue::Object(map)) => map.values().collect(),
+                    _ => Vec::new(),
+                })
+                .collect();
+        }
+        current
+    }
+}
+
+/// Call `f` with every record in a JSON file. The file may hold one document
+/// or many (JSON Lines); each is read in turn rather than the whole file at
+/// once. Arrays, at the top level or where `records` leads, are a record per element.
+fn for_each_record(
+    path: &Path,
+    records: Option<&JsonPath>,
+    cancel: &CancelToken,
+    mut f: impl FnMut(&JsonValue) -> Result<(), String>,
+) -> Result<(), String> {
+    let reader = BufReader::new(open_table_file(path)?);
+    for document in serde_json::Deserializer::from_reader(reader).into_iter::<JsonValue>() {
+        if cancel.is_cancelled() {
+            break;
+        }
+        let document = document.map_err(|e| format!("{}: {}", path.display(), e))?;
+        let selected = match records {
+            Some(records) => records.select(&document),
+            None => vec![&document],
+        };
+        for value in selected {
+            match value {
+                JsonValue::Array(items) => items.iter().try_for_each(&mut f)?,
+                value => f(value)?,
+            }
+        }
+    }
+    Ok(())
+}
+
+type FlatRow = Vec<(String, String)>;
+
+/// A record as rows of (column, text) pairs. Nested objects become dotted
+/// columns (`user.id`); a nested array gives a row per element, so a record
+/// with several arrays gives a row for every combination of their elements.
+fn flatten(value: &JsonValue, column: &str) -> Vec<FlatRow> {
+    let leaf = |text: String| {
+        let name = if column.is_empty() { "value" } else { column };
+        vec![vec![(name.to_string(), text)]]
+    };
+    match value {
+        JsonValue::Null => vec![Vec::new()],
+        JsonValue::Bool(flag) => leaf(flag.to_string()),
+        JsonValue::Number(number) => leaf(number.to_string()),
+        JsonValue::String(text) => leaf(text.clone()),
+        JsonValue::Array(items) if items.is_empty() => vec![Vec::new()],
+        JsonValue::Array(items) => items
+            .iter()
+            .flat_map(|item| flatten(item, column))
+            .collect(),
+        JsonValue::Object(map) => {
+            let mut rows = vec![Vec::new()];
+            for (key, member) in map {
+                let key = match normalize_name(key) {
+                    key if key.is_empty() => "value".to_string(),
+                    key => key,
+                };
+                let key = if column.is_empty() {
+                    key
+                } else {
+                    format!("{}.{}", column, key)
+                };
+                rows = rows_with(rows, &flatten(member, &key));
+            }
+            rows
+        }
+    }
+}
+
+/// `flatten` for a whole record, refusing one that would unnest into more
+/// than `max_rows` rows; a few arrays of modest size multiply into millions
+fn flatten_record(record: &JsonValue, max_rows: usize) -> Result<Vec<FlatRow>, String> {
+    let rows = unnested_rows(record);
+    if rows > max_rows {
+        return Err(format!(
+            "A record unnests into {} rows, more than {} (max rows)",
+            rows, max_rows
+        ));
+    }
+    Ok(flatten(record, ""))
+}
+
+// How many rows `flatten` gives for a value, counted without building them
+fn unnested_rows(value: &JsonValue) -> usize {
+    match value {
+        JsonValue::Array(items) if !items.is_empty() => {
+            items.iter().fold(0, |rows: usize, item| {
+                rows.saturating_add(unnested_rows(item))
+            })
+        }
+        JsonValue::Object(map) => map.values().fold(1, |rows: usize, member| {
+            rows.saturating_mul(unnested_rows(member))
+        }),
+        _ => 1,
+    }
+}
+
+// Every row of `rows` extended with every row of `more`
+fn rows_with(rows: Vec<FlatRow>, more: &[FlatRow]) -> Vec<FlatRow> {
+    rows.into_iter()
+        .flat_map(|row| {
+            more.iter().map(move |extra| {
+                let mut row = row.clone();
+                row.extend(extra.iter().cloned());
+                row
+            })
+        })
+        .collect()
+}
+
+/// First pass over a JSON file: every column that appears in any record, in
+/// the order first seen, with its type inferred from all of its values
+pub(crate) fn infer_columns(
+    path: &Path,
+    records: Option<&JsonPath>,
+    max_rows: usize,
+    cancel: &CancelToken,
+) -> Result<Vec<TableColumn>, String> {
+    let mut names: Vec<String> = Vec::new();
+    let mut guesses: Vec<KindGuess> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+    for_each_record(path, records, cancel, |record| {
+        for row in flatten_record(record, max_rows)? {
+            for (name, text) in row {
+                let column = *index.entry(name).or_insert_with_key(|name| {
+                    names.push(name.clone());
+                    guesses.push(KindGuess::new());
+                    names.len() - 1
+                });
+                guesses[column].observe(&text);
+            }
+        }
+        Ok(())
+    })?;
+    Ok(names
+        .into_iter()
+        .zip(guesses)
+        .map(|(name, guess)| TableColumn {
+            name,
+            kind: guess.kind(),
+        })
+        .collect())
+}
+
+/// Second pass: hand each row to `keep`, with the cells of columns a record
+/// doesn't have left NULL
+pub(crate) fn for_each_row(
+    path: &Path,
+    records: Option<&JsonPath>,
+    columns: &[TableColumn],
+    max_rows: usize,
+    cancel: &CancelToken,
+    keep: &mut impl FnMut(TableRow) -> Result<(), String>,
+) -> Result<(), String> {
+    let index: HashMap<&str, usize> = columns
+        .iter()
+        .enumerate()
+        .map(|(i, column)| (column.name.as_str(), i))
+        .collect();
+    for_each_record(path, records, cancel, |record| {
+        for row in flatten_record(record, max_rows)? {
+            let mut texts: Vec<Option<&str>> = vec![None; columns.len()];
+            for (name, text) in &row {
+                if let Some(&i) = index.get(name.as_str()) {
+                    texts[i] = Some(text);
+                }
+            }
+            keep(TableRow::from_texts(texts, columns))?;
+        }
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::columns::ColumnKind;
+    use serde_json::json;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_json_path() {
+        let document = json!({"data": {"items": [{"id": 1}, {"id": 2}]}, "count": 2});
+        let select = |path: &str| JsonPath::parse(path).unwrap().select(&document);
+        assert_eq!(select("$"), vec![&document]);
+        assert_eq!(select("$.count"), vec![&json!(2)]);
+        assert_eq!(
+            select("$.data.items[*]"),
+            vec![&json!({"id": 1}), &json!({"id": 2})]
+        );
+        assert_eq!(select("$['data'].items[1].id"), vec![&json!(2)]);
+        assert!(select("$.missing[*]").is_empty());
+        assert!(JsonPath::parse("data.items").is_err());
+        assert!(JsonPath::parse("$.items[x]").is_err());
+        assert!(JsonPath::parse("$.items[").is_err());
+    }
+
+    #[test]
+    fn test_flatten_unnests_arrays() {
+        let record = json!({
+            "id": 7,
+            "User": {"name": "ada", "admin": true},
+            "tags": ["a", "b"],
+            "note": null,
+            "empty": []
+        });
+        let rows = flatten(&record, "");
+        assert_eq!(rows.len(), 2);
+        let columns: Vec<&str> = rows[0].iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(columns, vec!["id", "user.name", "user.admin", "tags"]);
+        assert_eq!(rows[1][3], ("tags".to_string(), "b".to_string()));
+        assert_eq!(
+            flatten(&json!(3), ""),
+            vec![vec![("value".to_string(), "3".to_string())]]
+        );
+    }
+
+    #[test]
+    fn test_record_with_several_arrays() {
+        let record = json!({"a": [1, 2, 3], "b": {"c": ["x", "y"], "d": [true, false]}});
+        assert_eq!(unnested_rows(&record), 12);
+        assert_eq!(flatten_record(&record, 12).unwrap().len(), 12);
+        assert!(flatten_record(&record, 11).is_err());
+
+        // Five arrays of 30 would be 24 million rows; it is refused before any is built
+        let arrays: serde_json::Map<String, JsonValue> = (0..5)
+            .map(|i| {
+                (
+                    format!("a{}", i),
+                    JsonValue::from((0..30).collect::<Vec<i32>>()),
+                )
+            })
+            .collect();
+        let record = JsonValue::Object(arrays);
+        assert_eq!(unnested_rows(&record), 24_300_000);
+        let error = flatten_record(&record, 10_000).unwrap_err();
+        assert!(error.contains("24300000 rows"));
+    }
+
+    #[test]
+    fn test_read_json_lines_and_documents() {
+        let temp_dir = TempDir::new().unwrap();
+        let lines = temp_dir.path().join("events.ndjson");
+        fs::write(
+            &lines,
+            "{\"level\": \"info\", \"user\": {\"id\": 1}, \"at\": \"2025-01-02T03:04:05Z\"}\n\
+             {\"level\": \"error\", \"user\": {\"id\": 2}, \"code\": 500}\n",
+        )
+        .unwrap();
+        let cancel = CancelToken::new();
+        let columns = infer_columns(&lines, None, 100, &cancel).unwrap();
+        let kinds: Vec<_> = columns.iter().map(|c| (c.name.as_str(), c.kind)).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ("level", ColumnKind::Text),
+                ("user.id", ColumnKind::Number),
+                ("at", ColumnKind::Timestamp),
+                ("code", ColumnKind::Number),
+            ]
+        );
+        let mut rows = Vec::new();
+        for_each_row(&lines, None, &columns, 100, &cancel, &mut |row| {
+            rows.push(row);
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(rows.len(), 2);
+
+        let document = temp_dir.path().join("config.json");
+        fs::write(
+            &document,
+            r#"{"items": [{"name": "a"}, {"name": "b"}], "version": 3}"#,
+        )
+        .unwrap();
+        let records = JsonPath::parse("$.items[*]").unwrap();
+        let columns = infer_columns(&document, Some(&records), 100, &cancel).unwrap();
+        assert_eq!(columns.len(), 1);
+        assert_eq!(columns[0].name, "name");
+        // Without a path the whole document is one record, its array unnested
+        let columns = infer_columns(&document, None, 100, &cancel).unwrap();
+        let names: Vec<_> = columns.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["items.name", "version"]);
+
+        fs::write(&document, "{\"a\": 1}\n{\"a\": ").unwrap();
+        assert!(infer_columns(&document, None, 100, &cancel).is_err());
+    }
+}
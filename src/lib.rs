@@ -1,17 +1,41 @@
+pub mod aggregate;
+pub mod archives;
 pub mod applications;
+pub mod cancel;
+pub mod columns;
+pub mod configs;
+pub mod content;
+pub mod disks;
+pub mod elf;
+pub mod extensions;
 pub mod filesystem;
+pub mod git;
 pub mod gui;
+pub mod hashing;
+pub mod ignores;
+pub mod images;
+pub mod json;
+pub mod limits;
+pub mod logs;
+pub mod mime;
 pub mod models;
 pub mod network;
 pub mod parser;
+pub mod permissions;
+pub mod predicate;
 pub mod processes;
+pub mod tables;
 pub mod templates;
+pub mod users;
 pub mod utils;
 pub mod web;
+pub mod xattrs;
 
 // Re-export commonly used types and functions for convenience
 pub use applications::execute_application_query;
-pub use filesystem::execute_query;
+pub use cancel::CancelToken;
+pub use filesystem::{execute_query, execute_query_with_cancel, execute_query_with_limits};
+pub use limits::QueryLimits;
 pub use models::{
     ApplicationInfo, Args, Condition, FileInfo, NetInfo, ProcessInfo, QueryResult, SqlQuery, Subquery, SubqueryType,
 };
@@ -20,6 +44,6 @@ pub use templates::{
     get_template_dir, load_template, load_template_content, load_template_with_args, save_template,
 };
 pub use utils::{
-    display_application_results, display_network_results, display_process_results, display_results, evaluate_conditions,
-    evaluate_single_condition, expand_path, sort_process_results,
+    display_application_results, display_network_results, display_process_results, display_results,
+    display_table_results, expand_path, sort_process_results,
 };
@@ -0,0 +1,107 @@
+/// Resource limits enforced while a query runs.
+///
+/// Exceeding any of them fails the query with an error naming the limit,
+/// rather than silently truncating the results. The exception is
+/// `max_read_bytes`: content searches only look at the start of larger files.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryLimits {
+    /// How deeply subqueries may nest inside one another
+    pub max_subquery_depth: usize,
+    /// Rows any single result set (query or subquery) may hold before sorting and LIMIT
+    pub max_rows: usize,
+    /// How many directory levels below the FROM path a traversal may descend
+    pub max_traversal_depth: usize,
+    /// How many directories one traversal may open. Each handle is closed
+    /// before descending, so this bounds total work rather than open descriptors.
+    pub max_open_dirs: usize,
+    /// How many bytes of each file content conditions and line columns read
+    pub max_read_bytes: u64,
+}
+
+pub const DEFAULT_MAX_SUBQUERY_DEPTH: usize = 3;
+pub const DEFAULT_MAX_ROWS: usize = 1_000_000;
+pub const DEFAULT_MAX_TRAVERSAL_DEPTH: usize = 256;
+pub const DEFAULT_MAX_OPEN_DIRS: usize = 1_000_000;
+pub const DEFAULT_MAX_READ_BYTES: u64 = 16 * 1024 * 1024;
+
+impl Default for QueryLimits {
+    fn default() -> Self {
+        QueryLimits {
+            max_subquery_depth: DEFAULT_MAX_SUBQUERY_DEPTH,
+            max_rows: DEFAULT_MAX_ROWS,
+            max_traversal_depth: DEFAULT_MAX_TRAVERSAL_DEPTH,
+            max_open_dirs: DEFAULT_MAX_OPEN_DIRS,
+            max_read_bytes: DEFAULT_MAX_READ_BYTES,
+        }
+    }
+}
+
+impl QueryLimits {
+    pub fn check_subquery_depth(&self, depth: usize) -> Result<(), String> {
+        if depth > self.max_subquery_depth {
+            return Err(format!(
+                "Subqueries nested more than {} levels deep (max subquery depth)",
+                self.max_subquery_depth
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn check_rows(&self, rows: usize) -> Result<(), String> {
+        if rows > self.max_rows {
+            return Err(format!(
+                "Query produced more than {} rows (max rows); add a WHERE clause to narrow it",
+                self.max_rows
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn check_traversal_depth(&self, depth: usize, path: &str) -> Result<(), String> {
+        if depth > self.max_traversal_depth {
+            return Err(format!(
+                "'{}' is more than {} levels below the FROM path (max traversal depth)",
+                path, self.max_traversal_depth
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn check_open_dirs(&self, opened: usize) -> Result<(), String> {
+        if opened > self.max_open_dirs {
+            return Err(format!(
+                "Traversal opened more than {} directories (max open directories)",
+                self.max_open_dirs
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limits_allow_values_up_to_the_maximum() {
+        let limits = QueryLimits {
+            max_subquery_depth: 1,
+            max_rows: 2,
+            max_traversal_depth: 3,
+            max_open_dirs: 4,
+            max_read_bytes: 5,
+        };
+
+        assert!(limits.check_subquery_depth(1).is_ok());
+        assert!(limits.check_subquery_depth(2).is_err());
+        assert!(limits.check_rows(2).is_ok());
+        assert!(limits.check_rows(3).is_err());
+        assert!(limits.check_traversal_depth(3, "a/b/c").is_ok());
+        assert_eq!(
+            limits.check_traversal_depth(4, "a/b/c/d").unwrap_err(),
+            "'a/b/c/d' is more than 3 levels below the FROM path (max traversal depth)"
+        );
+        assert!(limits.check_open_dirs(4).is_ok());
+        assert!(limits.check_open_dirs(5).is_err());
+    }
+}
@@ -0,0 +1,252 @@
+use crate::cancel::CancelToken;
+use crate::columns::ColumnKind;
+use crate::tables::{
+    matching_files, normalize_name, open_table_file, KindGuess, TableColumn, TableRow,
+};
+use crate::utils::compile_regexp;
+use flate2::read::MultiGzDecoder;
+use regex::Regex;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+/// The columns of every `lines()` row, before those captured by its PATTERN
+const LINE_COLUMNS: [(&str, ColumnKind); 3] = [
+    ("file", ColumnKind::Text),
+    ("line_number", ColumnKind::Number),
+    ("line", ColumnKind::Text),
+];
+
+/// A PATTERN whose named groups become columns, e.g. `(?P<level>\w+) (?P<msg>.*)`
+#[derive(Debug, Clone)]
+pub struct LinePattern {
+    regex: Regex,
+    // (group name, column name) for each named group
+    groups: Vec<(String, String)>,
+}
+
+impl PartialEq for LinePattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.regex.as_str() == other.regex.as_str()
+    }
+}
+
+impl LinePattern {
+    pub fn new(pattern: &str) -> Result<Self, String> {
+        let regex = compile_regexp(pattern)?;
+        let mut groups: Vec<(String, String)> = Vec::new();
+        for group in regex.capture_names().flatten() {
+            let column = normalize_name(group);
+            let taken = LINE_COLUMNS.iter().any(|(name, _)| *name == column)
+                || groups.iter().any(|(_, name)| *name == column);
+            if column.is_empty() || taken {
+                return Err(format!("PATTERN group '{}' needs another name", group));
+            }
+            groups.push((group.to_string(), column));
+        }
+        if groups.is_empty() {
+            return Err(format!(
+                "PATTERN '{}' has no named groups such as (?P<level>\\w+)",
+                pattern
+            ));
+        }
+        Ok(LinePattern { regex, groups })
+    }
+
+    // The text each named group captured, `None` if the line doesn't match
+    // or the group took no part in the match
+    fn captures<'a>(&self, line: &'a str) -> Vec<Option<&'a str>> {
+        let captures = self.regex.captures(line);
+        self.groups
+            .iter()
+            .map(|(group, _)| {
+                let capture = captures.as_ref()?.name(group)?;
+                Some(capture.as_str())
+            })
+            .collect()
+    }
+}
+
+/// Call `f` with each line of each file `path` names, numbered from 1 in
+/// every file. `.gz` files are decompressed on the fly, and bytes that
+/// aren't UTF-8 are replaced rather than failing the query.
+fn for_each_line(
+    path: &Path,
+    cancel: &CancelToken,
+    mut f: impl FnMut(&Path, usize, &str) -> Result<(), String>,
+) -> Result<(), String> {
+    for file in matching_files(path)? {
+        let handle = open_table_file(&file)?;
+        let mut reader: Box<dyn BufRead> = match file.extension() {
+            Some(extension) if extension == "gz" => {
+                Box::new(BufReader::new(MultiGzDecoder::new(handle)))
+            }
+            _ => Box::new(BufReader::new(handle)),
+        };
+        let mut buffer = Vec::new();
+        let mut line_number = 0;
+        loop {
+            if cancel.is_cancelled() {
+                return Ok(());
+            }
+            buffer.clear();
+            let read = reader
+                .read_until(b'\n', &mut buffer)
+                .map_err(|e| format!("{}: {}", file.display(), e))?;
+            if read == 0 {
+                break;
+            }
+            line_number += 1;
+            let line = String::from_utf8_lossy(&buffer);
+            f(&file, line_number, line.trim_end_matches(['\n', '\r']))?;
+        }
+    }
+    Ok(())
+}
+
+/// The file, line number and line columns, then one per named group of the
+/// pattern with its type inferred from everything it captured. Only a
+/// pattern needs this first pass over the files.
+pub(crate) fn infer_columns(
+    path: &Path,
+    pattern: Option<&LinePattern>,
+    cancel: &CancelToken,
+) -> Result<Vec<TableColumn>, String> {
+    let mut columns: Vec<TableColumn> = LINE_COLUMNS
+        .iter()
+        .map(|(name, kind)| TableColumn {
+            name: name.to_string(),
+            kind: *kind,
+        })
+        .collect();
+    let pattern = match pattern {
+        Some(pattern) => pattern,
+        None => return Ok(columns),
+    };
+
+    let mut guesses = vec![KindGuess::new(); pattern.groups.len()];
+    for_each_line(path, cancel, |_, _, line| {
+        for (guess, capture) in guesses.iter_mut().zip(pattern.captures(line)) {
+            if let Some(text) = capture {
+                guess.observe(text);
+            }
+        }
+        Ok(())
+    })?;
+    columns.extend(
+        pattern
+            .groups
+            .iter()
+            .zip(guesses)
+            .map(|((_, name), guess)| TableColumn {
+                name: name.clone(),
+                kind: guess.kind(),
+            }),
+    );
+    Ok(columns)
+}
+
+/// Hand a row for every line to `keep`. Lines the pattern doesn't match are
+/// kept, with their captured columns NULL.
+pub(crate) fn for_each_row(
+    path: &Path,
+    pattern: Option<&LinePattern>,
+    columns: &[TableColumn],
+    cancel: &CancelToken,
+    keep: &mut impl FnMut(TableRow) -> Result<(), String>,
+) -> Result<(), String> {
+    for_each_line(path, cancel, |file, line_number, line| {
+        let file = file.display().to_string();
+        let line_number = line_number.to_string();
+        let mut texts = vec![Some(file.as_str()), Some(line_number.as_str()), Some(line)];
+        if let Some(pattern) = pattern {
+            texts.extend(pattern.captures(line));
+        }
+        keep(TableRow::from_texts(texts, columns))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::fs;
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_line_pattern() {
+        let pattern = LinePattern::new(r"(?P<ts>\S+) (?P<Level>\w+) (?P<msg>.*)").unwrap();
+        let columns: Vec<&str> = pattern.groups.iter().map(|(_, c)| c.as_str()).collect();
+        assert_eq!(columns, vec!["ts", "level", "msg"]);
+        assert_eq!(
+            pattern.captures("12:00 WARN disk almost full"),
+            vec![Some("12:00"), Some("WARN"), Some("disk almost full")]
+        );
+        assert_eq!(pattern.captures("oops"), vec![None, None, None]);
+
+        assert!(LinePattern::new(r"(\w+) (\w+)").is_err());
+        assert!(LinePattern::new(r"(?P<line>.*)").is_err());
+        assert!(LinePattern::new(r"(?P<a>\w+) (?P<A>\w+)").is_err());
+        assert!(LinePattern::new(r"(?P<a>\w+").is_err());
+    }
+
+    #[test]
+    fn test_rotated_logs() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::write(
+            root.join("app.log"),
+            "2025-01-02T10:00:00Z INFO 12 started\n2025-01-02T10:00:05Z ERROR 503 failed\r\n",
+        )
+        .unwrap();
+        let mut gz = GzEncoder::new(
+            fs::File::create(root.join("app.log.1.gz")).unwrap(),
+            Compression::default(),
+        );
+        gz.write_all(b"2025-01-01T09:00:00Z ERROR 500 crashed\n\xff stray bytes\n")
+            .unwrap();
+        gz.finish().unwrap();
+
+        let path = root.join("app.log*");
+        let pattern =
+            LinePattern::new(r"^(?P<ts>\S+) (?P<level>[A-Z]+) (?P<code>\d+) (?P<msg>.*)$").unwrap();
+        let cancel = CancelToken::new();
+        let columns = infer_columns(&path, Some(&pattern), &cancel).unwrap();
+        let kinds: Vec<_> = columns.iter().map(|c| (c.name.as_str(), c.kind)).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ("file", ColumnKind::Text),
+                ("line_number", ColumnKind::Number),
+                ("line", ColumnKind::Text),
+                ("ts", ColumnKind::Timestamp),
+                ("level", ColumnKind::Text),
+                ("code", ColumnKind::Number),
+                ("msg", ColumnKind::Text),
+            ]
+        );
+
+        let mut rows = Vec::new();
+        for_each_row(&path, Some(&pattern), &columns, &cancel, &mut |row| {
+            rows.push(row);
+            Ok(())
+        })
+        .unwrap();
+        let table = crate::tables::Table { columns, rows };
+        let column = |name: &str| -> Vec<String> {
+            table
+                .rows
+                .iter()
+                .map(|row| table.display_value(row, name).unwrap())
+                .collect()
+        };
+        assert_eq!(column("line_number"), vec!["1", "2", "1", "2"]);
+        assert_eq!(column("code"), vec!["12", "503", "500", "NULL"]);
+        assert_eq!(column("msg")[1], "failed");
+        assert_eq!(column("line")[3], "\u{fffd} stray bytes");
+        assert!(column("file")[2].ends_with("app.log.1.gz"));
+
+        assert!(infer_columns(&root.join("*.txt"), Some(&pattern), &cancel).is_err());
+    }
+}
@@ -2,10 +2,10 @@ use clap::Parser;
 use query_os::models::QueryType;
 use query_os::{
     display_application_results, display_network_results, display_process_results, display_results,
-    execute_query, gui, load_template_with_args, parse_query, save_template, web, Args,
-    QueryResult,
+    display_table_results, execute_query_with_limits, extensions, gui, load_template_with_args,
+    parse_query, save_template, web, Args, CancelToken, QueryLimits, QueryResult, SqlQuery,
 };
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 fn main() {
     let args = Args::parse();
@@ -19,25 +19,32 @@ fn main() {
         return;
     }
 
+    let cancel = match args.timeout {
+        Some(seconds) => CancelToken::with_timeout(Duration::from_secs(seconds)),
+        None => CancelToken::new(),
+    };
+
+    // The first Ctrl-C stops the query and keeps the partial results, a second one quits
+    let handler_token = cancel.clone();
+    if let Err(e) = ctrlc::set_handler(move || {
+        if handler_token.is_cancelled() {
+            std::process::exit(130);
+        }
+        eprintln!("\nCancelling query... (press Ctrl-C again to quit)");
+        handler_token.cancel();
+    }) {
+        eprintln!("Warning: could not install Ctrl-C handler: {}", e);
+    }
+
+    let limits = args.limits();
+    extensions::add_compound_extensions(&args.compound_extensions);
+
     // Handle template mode
     if let Some(template_name) = &args.template {
         match load_template_with_args(template_name, &args.template_args) {
-            Ok(query) => {
-                let start_time = Instant::now();
-                match execute_query(&query) {
-                    Ok(results) => {
-                        // For DELETE queries, results are already printed by the execution functions
-                        if query.query_type == QueryType::Select {
-                            display_query_results(&results, &query.select_fields, &query.from_path);
-                        }
-                        let duration = start_time.elapsed();
-                        println!(
-                            "\x1b[32mQuery executed in {:.3}ms\x1b[0m",
-                            duration.as_millis()
-                        );
-                    }
-                    Err(e) => eprintln!("Error executing query: {}", e),
-                }
+            Ok(mut query) => {
+                args.apply_from_options(&mut query.from_options);
+                run_query(&query, Instant::now(), &cancel, &limits)
             }
             Err(e) => eprintln!("Error loading template '{}': {}", template_name, e),
         }
@@ -49,7 +56,7 @@ fn main() {
         let start_time = Instant::now();
 
         match parse_query(query_str) {
-            Ok(query) => {
+            Ok(mut query) => {
                 // If save flag is present, save the query before executing
                 if let Some(template_name) = &args.save {
                     if let Err(e) = save_template(template_name, query_str) {
@@ -59,20 +66,8 @@ fn main() {
                     println!("Template '{}' saved successfully.", template_name);
                 }
 
-                match execute_query(&query) {
-                    Ok(results) => {
-                        // For DELETE queries, results are already printed by the execution functions
-                        if query.query_type == QueryType::Select {
-                            display_query_results(&results, &query.select_fields, &query.from_path);
-                        }
-                        let duration = start_time.elapsed();
-                        println!(
-                            "\x1b[32mQuery executed in {:.3}ms\x1b[0m",
-                            duration.as_millis()
-                        );
-                    }
-                    Err(e) => eprintln!("Error executing query: {}", e),
-                }
+                args.apply_from_options(&mut query.from_options);
+                run_query(&query, start_time, &cancel, &limits);
             }
             Err(e) => eprintln!("Error parsing query: {}", e),
         }
@@ -82,6 +77,36 @@ fn main() {
     }
 }
 
+fn run_query(query: &SqlQuery, start_time: Instant, cancel: &CancelToken, limits: &QueryLimits) {
+    match execute_query_with_limits(query, cancel, limits) {
+        Ok(results) => {
+            // Only a query stopped before it finished is partial; the deadline
+            // passing while its results are printed doesn't count. A DELETE
+            // that returns has either finished or said it was cancelled.
+            let cancelled = cancel.is_cancelled();
+            let is_select = query.query_type == QueryType::Select;
+            // For DELETE queries, results are already printed by the execution functions
+            if is_select {
+                display_query_results(&results, &query.select_fields, &query.from_path);
+            }
+            let duration = start_time.elapsed();
+            if cancelled && is_select {
+                println!(
+                    "\x1b[33m{} after {:.3}ms - showing partial results\x1b[0m",
+                    cancel.error(),
+                    duration.as_millis()
+                );
+            } else {
+                println!(
+                    "\x1b[32mQuery executed in {:.3}ms\x1b[0m",
+                    duration.as_millis()
+                );
+            }
+        }
+        Err(e) => eprintln!("Error executing query: {}", e),
+    }
+}
+
 fn display_query_results(results: &QueryResult, select_fields: &[String], from_path: &str) {
     match results {
         QueryResult::Files(files) => {
@@ -102,5 +127,6 @@ fn display_query_results(results: &QueryResult, select_fields: &[String], from_p
         QueryResult::Processes(processes) => display_process_results(processes, select_fields),
         QueryResult::Network(network_info) => display_network_results(network_info, select_fields),
         QueryResult::Applications(apps) => display_application_results(apps, select_fields),
+        QueryResult::Table(table) => display_table_results(table, select_fields),
     }
 }
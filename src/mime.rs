@@ -0,0 +1,224 @@
+use crate::cancel::CancelToken;
+use crate::content::looks_binary;
+use crate::models::FileInfo;
+use crate::utils::open_regular_file;
+use rayon::prelude::*;
+use std::fs;
+use std::io::Read;
+use std::os::unix::fs::FileTypeExt;
+use std::path::Path;
+
+/// How much of the start of a file is read to tell its type
+const SNIFF_BYTES: u64 = 8192;
+
+/// Bytes expected at an offset from the start of the file
+type Magic = (usize, &'static [u8]);
+
+/// Magic numbers, each a list of parts that must all match.
+/// More specific signatures come before the ones they overlap with.
+const SIGNATURES: &[(&[Magic], &str)] = &[
+    // Images
+    (&[(0, b"\x89PNG\r\n\x1a\n")], "image/png"),
+    (&[(0, b"\xff\xd8\xff")], "image/jpeg"),
+    (&[(0, b"GIF87a")], "image/gif"),
+    (&[(0, b"GIF89a")], "image/gif"),
+    (&[(0, b"RIFF"), (8, b"WEBP")], "image/webp"),
+    (&[(0, b"II*\0")], "image/tiff"),
+    (&[(0, b"MM\0*")], "image/tiff"),
+    (&[(0, b"\0\0\x01\0")], "image/vnd.microsoft.icon"),
+    (&[(4, b"ftypavif")], "image/avif"),
+    (&[(4, b"ftypheic")], "image/heic"),
+    // Audio and video
+    (&[(0, b"RIFF"), (8, b"WAVE")], "audio/wav"),
+    (&[(0, b"RIFF"), (8, b"AVI ")], "video/x-msvideo"),
+    (&[(4, b"ftypM4A ")], "audio/mp4"),
+    (&[(4, b"ftypqt  ")], "video/quicktime"),
+    (&[(4, b"ftyp")], "video/mp4"),
+    (&[(0, b"ID3")], "audio/mpeg"),
+    (&[(0, b"\xff\xfb")], "audio/mpeg"),
+    (&[(0, b"fLaC")], "audio/flac"),
+    (&[(0, b"OggS")], "audio/ogg"),
+    (&[(0, b"\x1a\x45\xdf\xa3")], "video/x-matroska"),
+    // Documents and data
+    (&[(0, b"%PDF-")], "application/pdf"),
+    (&[(0, b"SQLite format 3\0")], "application/vnd.sqlite3"),
+    (&[(0, b"\0asm")], "application/wasm"),
+    // Archives and compression
+    (&[(0, b"PK\x03\x04")], "application/zip"),
+    (&[(0, b"PK\x05\x06")], "application/zip"),
+    (&[(0, b"\x1f\x8b")], "application/gzip"),
+    (&[(0, b"BZh")], "application/x-bzip2"),
+    (&[(0, b"\xfd7zXZ\0")], "application/x-xz"),
+    (&[(0, b"\x28\xb5\x2f\xfd")], "application/zstd"),
+    (&[(0, b"7z\xbc\xaf\x27\x1c")], "application/x-7z-compressed"),
+    (&[(0, b"Rar!\x1a\x07")], "application/vnd.rar"),
+    (&[(257, b"ustar")], "application/x-tar"),
+    // Executables
+    (&[(0, b"\x7fELF")], "application/x-executable"),
+    (&[(0, b"\xcf\xfa\xed\xfe")], "application/x-mach-binary"),
+    (&[(0, b"\xce\xfa\xed\xfe")], "application/x-mach-binary"),
+    // Fonts
+    (&[(0, b"wOFF")], "font/woff"),
+    (&[(0, b"wOF2")], "font/woff2"),
+    (&[(0, b"OTTO")], "font/otf"),
+    (&[(0, b"\0\x01\0\0\0")], "font/ttf"),
+];
+
+/// Fields that are computed by sniffing the start of a file
+pub fn is_mime_field(field: &str) -> bool {
+    matches!(field, "mime_type" | "is_binary")
+}
+
+/// MIME type of a file from its first bytes, falling back to
+/// `text/plain` or `application/octet-stream` when no signature matches
+pub fn sniff(bytes: &[u8]) -> &'static str {
+    if bytes.is_empty() {
+        return "inode/x-empty";
+    }
+    let signature = SIGNATURES.iter().find(|(parts, _)| {
+        parts
+            .iter()
+            .all(|(offset, magic)| bytes.get(*offset..offset + magic.len()) == Some(*magic))
+    });
+    if let Some((_, mime)) = signature {
+        return mime;
+    }
+
+    // UTF-16 text is full of NUL bytes without being binary
+    if bytes.starts_with(b"\xff\xfe") || bytes.starts_with(b"\xfe\xff") {
+        return "text/plain";
+    }
+    if looks_binary(bytes) {
+        return "application/octet-stream";
+    }
+    sniff_text(bytes)
+}
+
+// Text formats recognised by how they start
+fn sniff_text(bytes: &[u8]) -> &'static str {
+    let bytes = bytes.strip_prefix(b"\xef\xbb\xbf").unwrap_or(bytes);
+    let head = String::from_utf8_lossy(&bytes[..bytes.len().min(512)]);
+    let head = head.trim_start().to_ascii_lowercase();
+
+    if let Some(shebang) = head.strip_prefix("#!") {
+        return script_type(shebang.lines().next().unwrap_or(""));
+    }
+    if head.starts_with("<?xml") || head.starts_with("<svg") {
+        return if head.contains("<svg") {
+            "image/svg+xml"
+        } else {
+            "application/xml"
+        };
+    }
+    if head.starts_with("<!doctype html") || head.starts_with("<html") {
+        return "text/html";
+    }
+    "text/plain"
+}
+
+// `/usr/bin/env python3 -u` or `/bin/sh` -> the interpreter's type
+fn script_type(shebang: &str) -> &'static str {
+    let mut words = shebang.split_whitespace();
+    let mut program = words.next().unwrap_or("");
+    if program.ends_with("/env") {
+        program = words.find(|word| !word.starts_with('-')).unwrap_or("");
+    }
+    let name = program.rsplit('/').next().unwrap_or("");
+    match name.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.') {
+        "sh" | "bash" | "zsh" | "dash" | "ksh" => "text/x-shellscript",
+        "python" => "text/x-script.python",
+        "perl" => "text/x-perl",
+        "ruby" => "text/x-ruby",
+        "node" => "application/javascript",
+        _ => "text/plain",
+    }
+}
+
+/// Whether files of this type hold binary data. `None` for types that
+/// aren't file contents, such as `inode/directory`.
+pub fn is_binary(mime: &str) -> Option<bool> {
+    match mime {
+        "inode/x-empty" => Some(false),
+        _ if mime.starts_with("inode/") => None,
+        "application/xml" | "application/javascript" | "image/svg+xml" => Some(false),
+        _ => Some(!mime.starts_with("text/")),
+    }
+}
+
+/// MIME type of the file at `path`, or `None` if it can't be read.
+/// Special files get `inode/...` types, as `file --mime-type` reports them.
+pub fn sniff_file(path: &Path) -> Option<&'static str> {
+    let Some(file) = open_regular_file(path) else {
+        let file_type = fs::metadata(path).ok()?.file_type();
+        return if file_type.is_fifo() {
+            Some("inode/fifo")
+        } else if file_type.is_socket() {
+            Some("inode/socket")
+        } else if file_type.is_char_device() {
+            Some("inode/chardevice")
+        } else if file_type.is_block_device() {
+            Some("inode/blockdevice")
+        } else {
+            None
+        };
+    };
+    let mut bytes = Vec::new();
+    file.take(SNIFF_BYTES).read_to_end(&mut bytes).ok()?;
+    Some(sniff(&bytes))
+}
+
+/// Fill in the MIME type of every row that doesn't have one yet, in parallel
+pub fn fill_mime_types(rows: &mut [FileInfo], root: &Path, cancel: &CancelToken) {
+    rows.par_iter_mut()
+        .filter(|file| file.mime_type.is_none())
+        .for_each(|file| {
+            if cancel.is_cancelled() {
+                return;
+            }
+            file.mime_type = match file.file_type.as_str() {
+                "directory" => Some("inode/directory"),
+                "symlink" => Some("inode/symlink"),
+                _ => sniff_file(&file.full_path(root)),
+            };
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sniff() {
+        assert_eq!(sniff(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"), "image/png");
+        assert_eq!(sniff(b"RIFF\x24\0\0\0WEBPVP8 "), "image/webp");
+        assert_eq!(sniff(b"RIFF\x24\0\0\0WAVEfmt "), "audio/wav");
+        assert_eq!(sniff(b"\0\0\0\x20ftypisom"), "video/mp4");
+        assert_eq!(sniff(b"\x7fELF\x02\x01\x01"), "application/x-executable");
+        let mut tar = vec![0u8; 512];
+        tar[..8].copy_from_slice(b"file.txt");
+        tar[257..262].copy_from_slice(b"ustar");
+        assert_eq!(sniff(&tar), "application/x-tar");
+
+        assert_eq!(sniff(b""), "inode/x-empty");
+        assert_eq!(sniff(b"hello\nworld\n"), "text/plain");
+        assert_eq!(sniff(b"\x01\x02\0\x03"), "application/octet-stream");
+        assert_eq!(sniff(b"\xff\xfeh\0i\0"), "text/plain");
+        assert_eq!(
+            sniff(b"#!/usr/bin/env python3\nprint()"),
+            "text/x-script.python"
+        );
+        assert_eq!(sniff(b"#!/bin/bash\necho"), "text/x-shellscript");
+        assert_eq!(sniff(b"<?xml version=\"1.0\"?>\n<svg>"), "image/svg+xml");
+        assert_eq!(sniff(b"\n<!DOCTYPE html>\n<html>"), "text/html");
+    }
+
+    #[test]
+    fn test_is_binary() {
+        assert_eq!(is_binary("image/png"), Some(true));
+        assert_eq!(is_binary("application/octet-stream"), Some(true));
+        assert_eq!(is_binary("text/x-shellscript"), Some(false));
+        assert_eq!(is_binary("image/svg+xml"), Some(false));
+        assert_eq!(is_binary("inode/x-empty"), Some(false));
+        assert_eq!(is_binary("inode/directory"), None);
+    }
+}
@@ -1,18 +1,54 @@
+use crate::disks::Mount;
+use crate::elf::ElfInfo;
+use crate::git::GitInfo;
+use crate::images::ImageInfo;
+use crate::limits;
 use chrono::{DateTime, Utc};
 use clap::Parser;
-use std::os::unix::fs::PermissionsExt;
-use std::path::Path;
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct FileInfo {
     pub name: String,
     pub file_type: String,
     pub modified_date: DateTime<Utc>,
     pub permissions: String,
     pub size: String,
+    pub size_bytes: u64, // Raw size behind `size`, used for filtering and sorting
     pub path: String,
     pub depth: usize,
     pub extension: Option<String>,
+    pub link_target: Option<String>, // Where a symlink points, as stored in the link
+    pub is_hidden: bool,             // Name starts with a dot
+    pub mode: Option<u32>,           // Raw st_mode, including the file type bits
+    pub inode: Option<u64>,
+    pub nlink: Option<u64>,  // Number of hard links
+    pub device: Option<u64>, // st_dev of the filesystem holding the file
+    pub blocks: Option<u64>, // Allocated 512-byte blocks, which can be fewer than size for sparse files
+    pub uid: Option<u32>,    // Owner ids; names are resolved only when selected
+    pub gid: Option<u32>,
+    pub accessed_date: Option<DateTime<Utc>>, // atime
+    pub changed_date: Option<DateTime<Utc>>,  // ctime: last change to contents or metadata
+    pub created_date: Option<DateTime<Utc>>,  // Birth time, if the filesystem records it
+    pub hash: Option<String>, // Content hashes, only filled in when a query uses them
+    pub sha256: Option<String>,
+    pub line_count: Option<u64>, // Filled in when the file is read for content columns
+    pub matching_lines: Option<u64>,
+    pub line_number: Option<u64>, // Set on per-line rows of a content search
+    pub line: Option<String>,
+    pub mime_type: Option<&'static str>, // Sniffed from the first bytes when a query uses it
+    pub total_size: Option<u64>, // Directories only: disk usage of everything below, like du -s
+    pub file_count: Option<u64>, // Directories only: files below, at any depth
+    pub compressed_size: Option<u64>, // Entries of zip archives, which compress each entry on its own
+    pub image: Option<Box<ImageInfo>>, // Read from the headers when a query uses an image column
+    pub elf: Option<Box<ElfInfo>>,    // Likewise for ELF columns
+    pub xattrs: Option<Vec<(String, Vec<u8>)>>, // Extended attributes, read when a query uses them
+    pub git: Option<Box<GitInfo>>,    // From the repository holding the file, when a query uses it
+    pub mount: Option<Arc<Mount>>,    // The filesystem it is on, shared by every row on it
 }
 
 impl FileInfo {
@@ -45,64 +81,39 @@ impl FileInfo {
             Err(_) => return None, // Treat permission errors like file doesn't exist
         };
 
-        let name = path.file_name()?.to_string_lossy().to_string();
-        let file_type = if metadata.is_dir() {
-            "directory"
-        } else {
-            "file"
-        };
-
-        let modified_date = match metadata.modified() {
-            Ok(t) => DateTime::<Utc>::from(t),
-            Err(_) => DateTime::<Utc>::from(std::time::SystemTime::UNIX_EPOCH),
-        };
-
-        let permissions = format!("{:o}", metadata.permissions().mode());
-
-        let size_bytes = metadata.len();
-        let size = Self::format_size(size_bytes);
-
-        let relative_path = path.strip_prefix(root_path).unwrap_or(path);
-        let path_str = relative_path.to_string_lossy().to_string();
-
-        // Calculate depth: count path components from root
-        let depth = if relative_path == Path::new("") {
-            0 // Root path itself
-        } else {
-            relative_path.components().count()
-        };
-
-        let extension = Self::extract_extension(&name, file_type == "directory");
-
-        Some(FileInfo {
-            name,
-            file_type: file_type.to_string(),
-            modified_date,
-            permissions,
-            size,
-            path: path_str,
-            depth,
-            extension,
-        })
+        let mut file_info = Self::new_lightweight_with_type(path, root_path, metadata.is_dir())?;
+        file_info.apply_metadata(&metadata);
+        Some(file_info)
     }
 
     // Lightweight version that only gets name and path for filtering
     pub fn new_lightweight(path: &Path, root_path: &Path) -> Option<Self> {
-        let name = path.file_name()?.to_string_lossy().to_string();
-        let relative_path = path.strip_prefix(root_path).unwrap_or(path);
-        let path_str = relative_path.to_string_lossy().to_string();
-
         // Get minimal metadata just for file type
         let metadata = match std::fs::metadata(path) {
             Ok(m) => m,
             Err(_) => return None,
         };
 
-        let file_type = if metadata.is_dir() {
-            "directory"
-        } else {
-            "file"
+        Self::new_lightweight_with_type(path, root_path, metadata.is_dir())
+    }
+
+    // Lightweight version for callers that already know the entry type
+    // (e.g. from `DirEntry::file_type`), so no stat is needed at all
+    pub fn new_lightweight_with_type(
+        path: &Path,
+        root_path: &Path,
+        is_directory: bool,
+    ) -> Option<Self> {
+        let relative_path = path.strip_prefix(root_path).unwrap_or(path);
+        // The FROM path itself may have no file name (`.`, `/`), so it is named as written
+        let name = match path.file_name() {
+            Some(name) => name.to_string_lossy().to_string(),
+            None if relative_path == Path::new("") => path.to_string_lossy().to_string(),
+            None => return None,
         };
+        let path_str = relative_path.to_string_lossy().to_string();
+
+        let file_type = if is_directory { "directory" } else { "file" };
 
         // Calculate depth: count path components from root
         let depth = if relative_path == Path::new("") {
@@ -111,33 +122,78 @@ impl FileInfo {
             relative_path.components().count()
         };
 
-        let extension = Self::extract_extension(&name, file_type == "directory");
+        let extension = Self::extract_extension(&name, is_directory);
+        let is_hidden = name.starts_with('.') && name != "." && name != "..";
 
-        // For lightweight version, use defaults for other fields
+        // For lightweight version, use defaults for other fields; the
+        // default modified_date is the Unix epoch
         Some(FileInfo {
             name,
             file_type: file_type.to_string(),
-            modified_date: DateTime::<Utc>::from(std::time::SystemTime::UNIX_EPOCH),
             permissions: "0".to_string(),
             size: "0 B".to_string(),
             path: path_str,
             depth,
+            is_hidden,
             extension,
+            ..Default::default()
         })
     }
 
+    // Fields that can be evaluated on a lightweight FileInfo, before any stat
+    pub fn is_lightweight_field(field: &str) -> bool {
+        matches!(
+            field,
+            "name"
+                | "type"
+                | "path"
+                | "depth"
+                | "extension"
+                | "full_extension"
+                | "stem"
+                | "language"
+                | "is_hidden"
+        )
+    }
+
+    // Rows hold paths relative to the FROM path, which is itself the row with an empty path
+    pub fn full_path(&self, root: &Path) -> PathBuf {
+        if self.path.is_empty() {
+            root.to_path_buf()
+        } else {
+            root.join(&self.path)
+        }
+    }
+
     // Upgrade lightweight FileInfo to full version with all metadata
     pub fn upgrade_to_full(&mut self, path: &Path) {
         if let Ok(metadata) = std::fs::metadata(path) {
-            self.modified_date = match metadata.modified() {
-                Ok(t) => DateTime::<Utc>::from(t),
-                Err(_) => DateTime::<Utc>::from(std::time::SystemTime::UNIX_EPOCH),
-            };
-            self.permissions = format!("{:o}", metadata.permissions().mode());
-            self.size = Self::format_size(metadata.len());
+            self.apply_metadata(&metadata);
         }
     }
 
+    // Fill in the metadata-backed fields from an already fetched stat result
+    pub fn apply_metadata(&mut self, metadata: &std::fs::Metadata) {
+        self.modified_date = match metadata.modified() {
+            Ok(t) => DateTime::<Utc>::from(t),
+            Err(_) => DateTime::<Utc>::from(std::time::SystemTime::UNIX_EPOCH),
+        };
+        self.permissions = format!("{:o}", metadata.permissions().mode());
+        self.size_bytes = metadata.len();
+        self.size = Self::format_size(self.size_bytes);
+        self.mode = Some(metadata.mode());
+        self.inode = Some(metadata.ino());
+        self.nlink = Some(metadata.nlink());
+        self.device = Some(metadata.dev());
+        self.blocks = Some(metadata.blocks());
+        self.uid = Some(metadata.uid());
+        self.gid = Some(metadata.gid());
+        self.accessed_date = metadata.accessed().ok().map(DateTime::<Utc>::from);
+        self.changed_date =
+            DateTime::from_timestamp(metadata.ctime(), metadata.ctime_nsec() as u32);
+        self.created_date = metadata.created().ok().map(DateTime::<Utc>::from);
+    }
+
     pub fn format_size(bytes: u64) -> String {
         const UNITS: &[&str] = &["B", "KB", "MB", "GB", "TB"];
 
@@ -233,6 +289,15 @@ mod tests {
         // Test root directory itself (edge case)
         let root_dir = FileInfo::new_lightweight(temp_path, temp_path).unwrap();
         assert_eq!(root_dir.depth, 0); // Root directory itself has depth 0
+
+        // A root without a file name is named as written
+        let dot =
+            FileInfo::new_lightweight_with_type(Path::new("."), Path::new("."), true).unwrap();
+        assert_eq!(dot.name, ".");
+        assert!(!dot.is_hidden);
+        let child = FileInfo::new_lightweight_with_type(Path::new("./.env"), Path::new("."), false)
+            .unwrap();
+        assert!(child.is_hidden);
     }
 
     #[test]
@@ -321,6 +386,23 @@ pub enum QueryResult {
     Processes(Vec<ProcessInfo>),
     Network(Vec<NetInfo>),
     Applications(Vec<ApplicationInfo>),
+    Table(crate::tables::Table),
+}
+
+impl QueryResult {
+    pub fn len(&self) -> usize {
+        match self {
+            QueryResult::Files(files) => files.len(),
+            QueryResult::Processes(processes) => processes.len(),
+            QueryResult::Network(network_info) => network_info.len(),
+            QueryResult::Applications(apps) => apps.len(),
+            QueryResult::Table(table) => table.rows.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -330,6 +412,8 @@ pub struct ProcessInfo {
     pub cpu_usage: String,
     pub memory_usage: String,
     pub status: String,
+    pub cpu_percent: f32,  // Raw value behind `cpu_usage`
+    pub memory_bytes: u64, // Raw value behind `memory_usage`
 }
 
 impl ProcessInfo {
@@ -340,6 +424,8 @@ impl ProcessInfo {
             cpu_usage: format!("{:.1}%", cpu_usage),
             memory_usage: Self::format_memory(memory_bytes),
             status: status.to_string(),
+            cpu_percent: cpu_usage,
+            memory_bytes,
         }
     }
 
@@ -409,7 +495,7 @@ impl ApplicationInfo {
             name: name.to_string(),
             version,
             path: path.to_string(),
-            size: size.map(|s| Self::format_size(s)),
+            size: size.map(Self::format_size),
             category,
         }
     }
@@ -489,6 +575,65 @@ pub struct Args {
     /// Launch GUI interface
     #[arg(long)]
     pub gui: bool,
+
+    /// Stop the query after this many seconds and show the rows found so far
+    #[arg(long, value_name = "SECONDS")]
+    pub timeout: Option<u64>,
+
+    /// Maximum nesting depth of subqueries
+    #[arg(long, value_name = "N", default_value_t = limits::DEFAULT_MAX_SUBQUERY_DEPTH)]
+    pub max_subquery_depth: usize,
+
+    /// Maximum rows a result set may hold before ORDER BY and LIMIT are applied
+    #[arg(long, value_name = "N", default_value_t = limits::DEFAULT_MAX_ROWS)]
+    pub max_rows: usize,
+
+    /// Maximum number of directory levels to descend below the FROM path
+    #[arg(long, value_name = "N", default_value_t = limits::DEFAULT_MAX_TRAVERSAL_DEPTH)]
+    pub max_depth: usize,
+
+    /// Maximum number of directories a traversal may open
+    #[arg(long, value_name = "N", default_value_t = limits::DEFAULT_MAX_OPEN_DIRS)]
+    pub max_open_dirs: usize,
+
+    /// Maximum bytes read from each file for content conditions and line columns
+    #[arg(long, value_name = "BYTES", default_value_t = limits::DEFAULT_MAX_READ_BYTES)]
+    pub max_read_bytes: u64,
+
+    /// List the entries of zip and tar archives found during the walk (INCLUDE ARCHIVES)
+    #[arg(long)]
+    pub include_archives: bool,
+
+    /// Extra multi-part extension for full_extension and stem, e.g. bak.gz (repeatable)
+    #[arg(long = "compound-extension", value_name = "EXT", value_delimiter = ',')]
+    pub compound_extensions: Vec<String>,
+
+    /// Skip files matched by .gitignore, .ignore and global git excludes (RESPECT IGNORES)
+    #[arg(long)]
+    pub respect_ignores: bool,
+
+    /// Skip hidden files and directories (SKIP HIDDEN)
+    #[arg(long)]
+    pub skip_hidden: bool,
+}
+
+impl Args {
+    pub fn limits(&self) -> limits::QueryLimits {
+        limits::QueryLimits {
+            max_subquery_depth: self.max_subquery_depth,
+            max_rows: self.max_rows,
+            max_traversal_depth: self.max_depth,
+            max_open_dirs: self.max_open_dirs,
+            max_read_bytes: self.max_read_bytes,
+        }
+    }
+
+    /// Turn on the FROM options given as flags, on top of any written in the query
+    pub fn apply_from_options(&self, options: &mut FromOptions) {
+        options.respect_ignores |= self.respect_ignores;
+        options.skip_hidden |= self.skip_hidden;
+        options.include_archives |= self.include_archives;
+    }
 }
 
 #[derive(Debug, PartialEq)]
@@ -529,6 +674,59 @@ pub struct SqlQuery {
     pub order_by: Option<String>,
     pub order_direction: SortDirection,
     pub limit: Option<usize>,
+    pub from_options: FromOptions, // Options written after the FROM path
+}
+
+/// How the FROM path is walked, set by options following it in the query
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FromOptions {
+    // FOLLOW SYMLINKS: report links as their targets and descend into linked directories
+    pub follow_symlinks: bool,
+    // RESPECT IGNORES: leave out entries matched by .gitignore, .ignore and global git excludes
+    pub respect_ignores: bool,
+    // SKIP HIDDEN: leave out dot-files and dot-directories, along with everything below them
+    pub skip_hidden: bool,
+    // INCLUDE ARCHIVES: list the entries of zip and tar archives below the archive's own row
+    pub include_archives: bool,
+}
+
+/// A typed column value, as produced by the column accessors in `columns.rs`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value<'a> {
+    Null,
+    Text(Cow<'a, str>),
+    Number(f64),
+    Timestamp(DateTime<Utc>),
+    Bool(bool),
+}
+
+impl Value<'_> {
+    // Text form used by LIKE and string comparisons; NULL compares as 'NULL'
+    pub fn as_text(&self) -> Cow<'_, str> {
+        match self {
+            Value::Null => Cow::Borrowed("NULL"),
+            Value::Text(text) => Cow::Borrowed(text.as_ref()),
+            Value::Number(number) => Cow::Owned(number.to_string()),
+            // Shown to the second; comparisons still see the nanoseconds
+            Value::Timestamp(timestamp) => {
+                Cow::Owned(timestamp.format("%Y-%m-%d %H:%M:%S").to_string())
+            }
+            Value::Bool(flag) => Cow::Borrowed(if *flag { "true" } else { "false" }),
+        }
+    }
+
+    // Ordering used by ORDER BY; NULL sorts after every other value
+    pub fn sort_cmp(&self, other: &Value<'_>) -> Ordering {
+        match (self, other) {
+            (Value::Null, Value::Null) => Ordering::Equal,
+            (Value::Null, _) => Ordering::Greater,
+            (_, Value::Null) => Ordering::Less,
+            (Value::Number(a), Value::Number(b)) => a.partial_cmp(b).unwrap_or(Ordering::Equal),
+            (Value::Timestamp(a), Value::Timestamp(b)) => a.cmp(b),
+            (Value::Bool(a), Value::Bool(b)) => a.cmp(b),
+            (a, b) => a.as_text().cmp(&b.as_text()),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
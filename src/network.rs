@@ -1,6 +1,6 @@
-use crate::models::{Condition, NetInfo};
+use crate::models::NetInfo;
 use crate::parser::parse_compound_conditions;
-use crate::utils::{compare_strings, like_match};
+use crate::predicate::compile_conditions;
 use std::process::Command;
 
 pub fn execute_network_query(query: &crate::models::SqlQuery) -> Result<Vec<NetInfo>, String> {
@@ -10,15 +10,14 @@ pub fn execute_network_query(query: &crate::models::SqlQuery) -> Result<Vec<NetI
         Vec::new()
     };
 
+    let predicate = compile_conditions::<NetInfo>(&conditions);
     let network_info = collect_network_info()?;
-    let mut results: Vec<NetInfo> = Vec::new();
 
     // Apply WHERE conditions
-    for net_info in network_info {
-        if evaluate_network_conditions(&net_info, &conditions) {
-            results.push(net_info);
-        }
-    }
+    let mut results: Vec<NetInfo> = network_info
+        .into_iter()
+        .filter(|net_info| predicate.matches(net_info))
+        .collect();
 
     // Apply DISTINCT
     if query.distinct {
@@ -67,7 +66,7 @@ fn collect_network_info() -> Result<Vec<NetInfo>, String> {
     let mut network_info = Vec::new();
 
     // Try multiple commands in order of preference
-    let output = if let Ok(output) = Command::new("ss").args(&["-tlnp"]).output() {
+    let output = if let Ok(output) = Command::new("ss").args(["-tlnp"]).output() {
         if output.status.success() {
             String::from_utf8_lossy(&output.stdout).to_string()
         } else {
@@ -91,7 +90,7 @@ fn collect_network_info() -> Result<Vec<NetInfo>, String> {
 
 fn try_netstat_or_lsof() -> Result<String, String> {
     // Try lsof first (works on macOS and Linux)
-    if let Ok(output) = Command::new("lsof").args(&["-i", "-P", "-n"]).output() {
+    if let Ok(output) = Command::new("lsof").args(["-i", "-P", "-n"]).output() {
         if output.status.success() {
             return Ok(String::from_utf8_lossy(&output.stdout).to_string());
         }
@@ -99,7 +98,7 @@ fn try_netstat_or_lsof() -> Result<String, String> {
 
     // Fallback to netstat
     let output = Command::new("netstat")
-        .args(&["-tlnp"])
+        .args(["-tlnp"])
         .output()
         .map_err(|_| "Failed to run network commands".to_string())?;
     if output.status.success() {
@@ -118,7 +117,7 @@ fn parse_network_line(line: &str) -> Option<NetInfo> {
     // Check if this is lsof output format first (COMMAND, PID, USER, FD, TYPE, DEVICE, SIZE/OFF, NODE, NAME)
     if parts.len() >= 9 && parts[0].chars().all(|c| c.is_alphabetic() || c == '-') {
         // lsof format: COMMAND PID USER FD TYPE DEVICE SIZE/OFF NODE NAME
-        let command = parts.get(0)?;
+        let command = parts.first()?;
         let pid_str = parts.get(1)?;
         let name_field = parts.get(8)?; // NAME field contains IP:Port or hostname:port
 
@@ -154,7 +153,7 @@ fn parse_network_line(line: &str) -> Option<NetInfo> {
 
         // Local address might be IP:Port or just Port
         let port_str = if local_addr.contains(':') {
-            local_addr.split(':').last()?
+            local_addr.split(':').next_back()?
         } else {
             local_addr
         };
@@ -230,82 +229,7 @@ fn get_process_name(pid: u32) -> Option<String> {
         .map(|s| s.trim().to_string())
 }
 
-fn evaluate_network_conditions(net_info: &NetInfo, conditions: &[Condition]) -> bool {
-    for condition in conditions {
-        let result = evaluate_single_network_condition(net_info, condition);
-        let final_result = if condition.negated { !result } else { result };
-
-        if !final_result {
-            return false;
-        }
-    }
-    true
-}
-
-fn evaluate_single_network_condition(net_info: &NetInfo, condition: &Condition) -> bool {
-    // Handle NULL checks first
-    if condition.operator == "IS" && condition.value == "NULL" {
-        let is_null = match condition.field.as_str() {
-            "name" => net_info.name.is_empty(),
-            "port" => net_info.port.is_empty(),
-            "pid" => net_info.pid.is_empty(),
-            _ => false,
-        };
-        // Return the base NULL check result; negation is handled by evaluate_network_conditions
-        return is_null;
-    }
-
-    match condition.field.as_str() {
-        "name" => {
-            if condition.operator == "LIKE" {
-                like_match(&net_info.name, &condition.value)
-            } else {
-                compare_strings(&net_info.name, &condition.operator, &condition.value)
-            }
-        }
-        "port" => {
-            if condition.operator == "LIKE" {
-                like_match(&net_info.port, &condition.value)
-            } else {
-                // Parse ports as numbers for numeric comparison
-                match (net_info.port.parse::<u16>(), condition.value.parse::<u16>()) {
-                    (Ok(net_port), Ok(cond_port)) => match condition.operator.as_str() {
-                        "=" => net_port == cond_port,
-                        "!=" => net_port != cond_port,
-                        ">" => net_port > cond_port,
-                        "<" => net_port < cond_port,
-                        ">=" => net_port >= cond_port,
-                        "<=" => net_port <= cond_port,
-                        _ => false,
-                    },
-                    _ => false, // If parsing fails, condition is false
-                }
-            }
-        }
-        "pid" => {
-            if condition.operator == "LIKE" {
-                like_match(&net_info.pid, &condition.value)
-            } else {
-                // Parse PIDs as numbers for numeric comparison
-                match (net_info.pid.parse::<u32>(), condition.value.parse::<u32>()) {
-                    (Ok(net_pid), Ok(cond_pid)) => match condition.operator.as_str() {
-                        "=" => net_pid == cond_pid,
-                        "!=" => net_pid != cond_pid,
-                        ">" => net_pid > cond_pid,
-                        "<" => net_pid < cond_pid,
-                        ">=" => net_pid >= cond_pid,
-                        "<=" => net_pid <= cond_pid,
-                        _ => false,
-                    },
-                    _ => false, // If parsing fails, condition is false
-                }
-            }
-        }
-        _ => false,
-    }
-}
-
-fn sort_network_results(results: &mut Vec<NetInfo>, order_by: &str, direction: &crate::models::SortDirection) -> Result<(), String> {
+fn sort_network_results(results: &mut [NetInfo], order_by: &str, direction: &crate::models::SortDirection) -> Result<(), String> {
     // Validate order_by field first
     match order_by {
         "name" | "port" | "pid" => {},
@@ -351,7 +275,7 @@ mod tests {
     }
 
     #[test]
-    fn test_evaluate_network_conditions() {
+    fn test_network_conditions() {
         let net_info = NetInfo::new("node", 3000, 1234);
 
         let conditions = vec![Condition {
@@ -361,7 +285,7 @@ mod tests {
             negated: false,
         }];
 
-        assert!(evaluate_network_conditions(&net_info, &conditions));
+        assert!(compile_conditions::<NetInfo>(&conditions).matches(&net_info));
 
         // Test non-matching condition
         let bad_conditions = vec![Condition {
@@ -371,7 +295,17 @@ mod tests {
             negated: false,
         }];
 
-        assert!(!evaluate_network_conditions(&net_info, &bad_conditions));
+        assert!(!compile_conditions::<NetInfo>(&bad_conditions).matches(&net_info));
+
+        // Ports compare as numbers, not text
+        let below = vec![Condition {
+            field: "port".to_string(),
+            operator: "<".to_string(),
+            value: "500".to_string(),
+            negated: false,
+        }];
+
+        assert!(!compile_conditions::<NetInfo>(&below).matches(&net_info));
     }
 
     #[test]
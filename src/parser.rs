@@ -1,4 +1,6 @@
-use crate::models::{Condition, SqlQuery, Subquery};
+use crate::aggregate::{is_aggregate_query, parse_aggregate};
+use crate::models::{Condition, FromOptions, SqlQuery, Subquery};
+use crate::tables::TableSource;
 use crate::utils::expand_path;
 use crate::web::is_url;
 use pest::Parser;
@@ -37,13 +39,20 @@ fn uppercase_keywords(query: &str) -> String {
         "EXISTS", "IN", "DISTINCT", "IS", "NULL",
     ];
 
-    let mut result = query.to_string();
-    for keyword in &keywords {
-        // Use word boundaries to avoid partial matches, case insensitive
-        let pattern = regex::Regex::new(&format!(r"(?i)\b{}\b", regex::escape(keyword))).unwrap();
-        result = pattern.replace_all(&result, *keyword).to_string();
-    }
-    result
+    // Whole words only, and never inside quoted strings, so patterns such as
+    // content LIKE '%is not null%' are searched for as written
+    let token = regex::Regex::new(r#"'[^']*'|"[^"]*"|\w+"#).unwrap();
+    token
+        .replace_all(query, |caps: &regex::Captures| {
+            let word = &caps[0];
+            let upper = word.to_uppercase();
+            if keywords.contains(&upper.as_str()) {
+                upper
+            } else {
+                word.to_string()
+            }
+        })
+        .into_owned()
 }
 
 fn parse_select_query(
@@ -62,6 +71,7 @@ fn parse_select_query(
     let mut order_by = None;
     let mut order_direction = crate::models::SortDirection::Ascending; // Default to ascending
     let mut limit = None;
+    let mut from_options = FromOptions::default();
 
     for inner_pair in pair.into_inner() {
         match inner_pair.as_rule() {
@@ -77,6 +87,9 @@ fn parse_select_query(
             Rule::path => {
                 from_path = parse_path(inner_pair)?;
             }
+            Rule::from_option => {
+                parse_from_option(inner_pair, &mut from_options);
+            }
             Rule::condition => {
                 let (clause, subqueries) = parse_condition(inner_pair)?;
                 where_clause = Some(clause);
@@ -99,8 +112,12 @@ fn parse_select_query(
         }
     }
 
-    // Handle * expansion like the original parser
-    if select_fields == vec!["*"] {
+    // Handle * expansion like the original parser; a table's columns are only
+    // known once its file has been read, and `FROM disks` is a table too
+    if select_fields == vec!["*"]
+        && from_path != "disks"
+        && parse_table_source(&from_path)?.is_none()
+    {
         if from_path == "ps" {
             select_fields = vec![
                 "pid".to_string(),
@@ -144,6 +161,7 @@ fn parse_select_query(
         order_by,
         order_direction,
         limit,
+        from_options,
     })
 }
 
@@ -153,12 +171,16 @@ fn parse_delete_query(pair: pest::iterators::Pair<Rule>) -> Result<SqlQuery, Str
     let mut from_path = String::new();
     let mut where_clause = None;
     let mut where_subqueries = Vec::new();
+    let mut from_options = FromOptions::default();
 
     for inner_pair in pair.into_inner() {
         match inner_pair.as_rule() {
             Rule::path => {
                 from_path = parse_path(inner_pair)?;
             }
+            Rule::from_option => {
+                parse_from_option(inner_pair, &mut from_options);
+            }
             Rule::condition => {
                 let (clause, subqueries) = parse_condition(inner_pair)?;
                 where_clause = Some(clause);
@@ -180,10 +202,19 @@ fn parse_delete_query(pair: pest::iterators::Pair<Rule>) -> Result<SqlQuery, Str
         order_by: None,
         order_direction: crate::models::SortDirection::Ascending,
         limit: None,
+        from_options,
     })
 }
 
 fn parse_path(pair: pest::iterators::Pair<Rule>) -> Result<String, String> {
+    // A table function is kept as written and read with `parse_table_source`,
+    // but its options are checked now so mistakes are reported as parse errors
+    if let Some(table_function) = pair.clone().into_inner().next() {
+        if table_function.as_rule() == Rule::table_function {
+            parse_table_function(table_function)?;
+            return Ok(pair.as_str().to_string());
+        }
+    }
     let path_str = pair.as_str().trim_matches('\'');
     if is_url(path_str) {
         Ok(path_str.to_string())
@@ -192,9 +223,58 @@ fn parse_path(pair: pest::iterators::Pair<Rule>) -> Result<String, String> {
     }
 }
 
-fn parse_fields(
-    pair: pest::iterators::Pair<Rule>,
-) -> Result<(Vec<String>, Vec<Option<String>>, Vec<Subquery>), String> {
+/// The table file a FROM path such as `csv('data.csv', delimiter = ';')`,
+/// `json('config.json', '$.items[*]')` or `lines('app.log') PATTERN '...'` reads,
+/// or `None` for any other kind of path
+pub fn parse_table_source(from_path: &str) -> Result<Option<TableSource>, String> {
+    match FqParser::parse(Rule::table_source, from_path) {
+        Ok(mut pairs) => {
+            let table_function = pairs.next().unwrap().into_inner().next().unwrap();
+            parse_table_function(table_function).map(Some)
+        }
+        Err(_) => Ok(None),
+    }
+}
+
+fn parse_table_function(pair: pest::iterators::Pair<Rule>) -> Result<TableSource, String> {
+    let mut format = String::new();
+    let mut path = String::new();
+    let mut records = None;
+    let mut pattern = None;
+    let mut options = Vec::new();
+    for inner_pair in pair.into_inner() {
+        match inner_pair.as_rule() {
+            Rule::table_format => format = inner_pair.as_str().to_lowercase(),
+            Rule::string => path = unquote(inner_pair.as_str()).to_string(),
+            Rule::json_path => records = Some(unquote(inner_pair.as_str()).to_string()),
+            Rule::line_pattern => pattern = Some(unquote(inner_pair.as_str()).to_string()),
+            Rule::table_option => {
+                let mut parts = inner_pair.into_inner();
+                let name = parts.next().unwrap().as_str().to_string();
+                let value = parts.last().unwrap().as_str();
+                options.push((name, unquote(value).to_string()));
+            }
+            _ => {}
+        }
+    }
+    TableSource::new(&format, &path, records.as_deref(), pattern.as_deref(), &options)
+}
+
+fn parse_from_option(pair: pest::iterators::Pair<Rule>, options: &mut FromOptions) {
+    for inner_pair in pair.into_inner() {
+        match inner_pair.as_rule() {
+            Rule::follow_symlinks => options.follow_symlinks = true,
+            Rule::respect_ignores => options.respect_ignores = true,
+            Rule::skip_hidden => options.skip_hidden = true,
+            Rule::include_archives => options.include_archives = true,
+            _ => {}
+        }
+    }
+}
+
+type ParsedFields = (Vec<String>, Vec<Option<String>>, Vec<Subquery>);
+
+fn parse_fields(pair: pest::iterators::Pair<Rule>) -> Result<ParsedFields, String> {
     let mut fields = Vec::new();
     let mut aliases = Vec::new();
     let mut subqueries = Vec::new();
@@ -226,6 +306,16 @@ fn parse_fields(
         }
     }
 
+    // Without GROUP BY there is only one summary row for plain columns to go in
+    if is_aggregate_query(&fields) {
+        if let Some(field) = fields.iter().find(|field| parse_aggregate(field).is_none()) {
+            return Err(format!(
+                "Cannot select '{}' alongside aggregates such as COUNT(*) or SUM(size)",
+                field
+            ));
+        }
+    }
+
     Ok((fields, aliases, subqueries))
 }
 
@@ -237,6 +327,7 @@ fn parse_field(
 
     for inner_pair in pair.into_inner() {
         match inner_pair.as_rule() {
+            Rule::aggregate => field_name = parse_aggregate_field(inner_pair),
             Rule::identifier => {
                 if field_name.is_empty() {
                     field_name = inner_pair.as_str().to_string();
@@ -251,9 +342,66 @@ fn parse_field(
     Ok((field_name, alias, None))
 }
 
+// Normalised to e.g. `SUM(disk_usage)`, the form the aggregate module reads back
+fn parse_aggregate_field(pair: pest::iterators::Pair<Rule>) -> String {
+    let mut function = String::new();
+    let mut column = String::new();
+
+    for inner_pair in pair.into_inner() {
+        match inner_pair.as_rule() {
+            Rule::aggregate_function => function = inner_pair.as_str().to_uppercase(),
+            Rule::identifier => column = inner_pair.as_str().to_string(),
+            _ => {}
+        }
+    }
+
+    format!("{}({})", function, column)
+}
+
 fn parse_condition(pair: pest::iterators::Pair<Rule>) -> Result<(String, Vec<Subquery>), String> {
+    let mut terms = Vec::new();
+    let mut subqueries = Vec::new();
+
+    for term_pair in pair.into_inner() {
+        if term_pair.as_rule() == Rule::condition_term {
+            let (term, mut term_subqueries) = parse_condition_term(term_pair)?;
+            terms.push(term);
+            subqueries.append(&mut term_subqueries);
+        }
+    }
+
+    if terms.is_empty() {
+        return Err("Invalid condition".to_string());
+    }
+
+    // Terms are re-joined so parse_compound_conditions sees the whole clause
+    Ok((terms.join(" AND "), subqueries))
+}
+
+fn parse_condition_term(
+    pair: pest::iterators::Pair<Rule>,
+) -> Result<(String, Vec<Subquery>), String> {
     for inner_pair in pair.into_inner() {
         match inner_pair.as_rule() {
+            Rule::has_perm_condition => {
+                return parse_has_perm_condition(inner_pair);
+            }
+            Rule::not_has_perm_condition => {
+                let inner = inner_pair.into_inner().next().ok_or("Invalid condition")?;
+                let (term, subqueries) = parse_has_perm_condition(inner)?;
+                return Ok((format!("NOT {}", term), subqueries));
+            }
+            Rule::in_duplicates_condition | Rule::not_in_duplicates_condition => {
+                let negated = inner_pair.as_rule() == Rule::not_in_duplicates_condition;
+                let field = inner_pair
+                    .into_inner()
+                    .next()
+                    .ok_or("Invalid condition")?
+                    .as_str()
+                    .to_string();
+                let operator = if negated { "NOT IN" } else { "IN" };
+                return Ok((format!("{} {} (DUPLICATES)", field, operator), Vec::new()));
+            }
             Rule::comparison => {
                 return parse_comparison_condition(inner_pair);
             }
@@ -263,6 +411,9 @@ fn parse_condition(pair: pest::iterators::Pair<Rule>) -> Result<(String, Vec<Sub
             Rule::not_like_condition => {
                 return parse_not_like_condition(inner_pair);
             }
+            Rule::regexp_condition | Rule::not_regexp_condition => {
+                return parse_regexp_condition(inner_pair);
+            }
             Rule::null_condition | Rule::is_null_condition | Rule::simple_null_condition => {
                 return parse_null_condition(inner_pair);
             }
@@ -302,6 +453,23 @@ fn parse_comparison_condition(
     Ok((format!("{} {} {}", field, operator, value), Vec::new()))
 }
 
+fn parse_has_perm_condition(
+    pair: pest::iterators::Pair<Rule>,
+) -> Result<(String, Vec<Subquery>), String> {
+    let mut field = String::new();
+    let mut spec = String::new();
+
+    for inner_pair in pair.into_inner() {
+        match inner_pair.as_rule() {
+            Rule::identifier => field = inner_pair.as_str().to_string(),
+            Rule::string => spec = inner_pair.as_str().to_string(),
+            _ => {}
+        }
+    }
+
+    Ok((format!("HAS_PERM({}, {})", field, spec), Vec::new()))
+}
+
 fn parse_like_condition(
     pair: pest::iterators::Pair<Rule>,
 ) -> Result<(String, Vec<Subquery>), String> {
@@ -336,18 +504,39 @@ fn parse_not_like_condition(
     Ok((format!("{} NOT LIKE {}", field, value), Vec::new()))
 }
 
-fn parse_null_condition(
+fn parse_regexp_condition(
     pair: pest::iterators::Pair<Rule>,
 ) -> Result<(String, Vec<Subquery>), String> {
+    let operator = if pair.as_rule() == Rule::not_regexp_condition {
+        "NOT REGEXP"
+    } else {
+        "REGEXP"
+    };
     let mut field = String::new();
+    let mut value = String::new();
 
     for inner_pair in pair.into_inner() {
         match inner_pair.as_rule() {
             Rule::identifier => field = inner_pair.as_str().to_string(),
+            Rule::value => value = inner_pair.as_str().to_string(),
             _ => {}
         }
     }
 
+    Ok((format!("{} {} {}", field, operator, value), Vec::new()))
+}
+
+fn parse_null_condition(
+    pair: pest::iterators::Pair<Rule>,
+) -> Result<(String, Vec<Subquery>), String> {
+    let mut field = String::new();
+
+    for inner_pair in pair.into_inner() {
+        if inner_pair.as_rule() == Rule::identifier {
+            field = inner_pair.as_str().to_string();
+        }
+    }
+
     Ok((format!("{} IS NULL", field), Vec::new()))
 }
 
@@ -357,9 +546,8 @@ fn parse_not_null_condition(
     let mut field = String::new();
 
     for inner_pair in pair.into_inner() {
-        match inner_pair.as_rule() {
-            Rule::identifier => field = inner_pair.as_str().to_string(),
-            _ => {}
+        if inner_pair.as_rule() == Rule::identifier {
+            field = inner_pair.as_str().to_string();
         }
     }
 
@@ -399,22 +587,65 @@ fn parse_order_by_clause(
 pub fn parse_compound_conditions(where_clause: &str) -> Result<Vec<Condition>, String> {
     let mut conditions = Vec::new();
 
-    // Pre-compile regexes to avoid compiling in loop
-    let not_like_re = regex::Regex::new(r"(?i)(\w+)\s+NOT\s+LIKE\s+(.+)").unwrap();
-    let is_null_re = regex::Regex::new(r"(?i)(\w+)\s+IS\s+NULL").unwrap();
-    let is_not_null_re = regex::Regex::new(r"(?i)(\w+)\s+IS\s+NOT\s+NULL").unwrap();
-    let condition_re = regex::Regex::new(r"(?i)(\w+)\s*([=<>!]+|LIKE)\s*(.+)").unwrap();
+    // Pre-compile regexes to avoid compiling in loop. A field is a name or an
+    // XATTR(path, 'name') call.
+    let field = r#"(XATTR\s*\(\s*\w+\s*,\s*(?:'[^']*'|"[^"]*")\s*\)|[\w.]+)"#;
+    let field_re = |rest: &str| regex::Regex::new(&format!("(?i)^{}{}", field, rest)).unwrap();
+    let not_like_re = field_re(r"\s+NOT\s+LIKE\s+(.+)");
+    let is_null_re = field_re(r"\s+IS\s+NULL");
+    let is_not_null_re = field_re(r"\s+IS\s+NOT\s+NULL");
+    let condition_re = field_re(r"\s*([=<>!]+|LIKE)\s*(.+)");
+    let duplicates_re =
+        regex::Regex::new(r"(?i)^(\w+)\s+(NOT\s+)?IN\s*\(\s*DUPLICATES\s*\)$").unwrap();
+    let has_perm_re =
+        regex::Regex::new(r#"(?i)^(NOT\s+)?HAS_PERM\s*\(\s*(\w+)\s*,\s*['"]([^'"]*)['"]\s*\)$"#)
+            .unwrap();
+    let regexp_re = field_re(r"\s+(NOT\s+)?REGEXP\s+(.+)$");
 
     // Split by AND (case-insensitive) first, then handle each part
-    let and_re = regex::Regex::new(r"(?i)\s+and\s+").unwrap();
-    let and_parts: Vec<&str> = and_re.split(where_clause).collect();
-
-    for part in and_parts {
+    for part in split_and(where_clause) {
         let part = part.trim();
         if part.is_empty() {
             continue;
         }
 
+        if let Some(caps) = duplicates_re.captures(part) {
+            conditions.push(Condition {
+                field: caps[1].to_lowercase(),
+                operator: "IN".to_string(),
+                value: "DUPLICATES".to_string(),
+                negated: caps.get(2).is_some(),
+            });
+            continue;
+        }
+
+        // HAS_PERM(field, 'spec') is checked first, since a spec like 'o=w' would
+        // otherwise read as a comparison
+        if let Some(caps) = has_perm_re.captures(part) {
+            let spec = caps[3].to_string();
+            crate::permissions::parse_perm_spec(&spec)?;
+            conditions.push(Condition {
+                field: caps[2].to_lowercase(),
+                operator: "HAS_PERM".to_string(),
+                value: spec,
+                negated: caps.get(1).is_some(),
+            });
+            continue;
+        }
+
+        // Checked before the other forms, which would find an operator inside the pattern
+        if let Some(caps) = regexp_re.captures(part) {
+            let pattern = unquote(caps[3].trim());
+            crate::utils::compile_regexp(pattern)?;
+            conditions.push(Condition {
+                field: field_name(&caps[1]),
+                operator: "REGEXP".to_string(),
+                value: pattern.to_string(),
+                negated: caps.get(2).is_some(),
+            });
+            continue;
+        }
+
         // Parse the individual condition - handle special cases first
         let (is_negated, condition_part) = if let Some(caps) = not_like_re.captures(part) {
             (true, format!("{} LIKE {}", &caps[1], &caps[2]))
@@ -424,7 +655,7 @@ pub fn parse_compound_conditions(where_clause: &str) -> Result<Vec<Condition>, S
 
         // Handle IS NULL and IS NOT NULL conditions
         if let Some(caps) = is_null_re.captures(&condition_part) {
-            let field = caps[1].to_lowercase();
+            let field = field_name(&caps[1]);
             conditions.push(Condition {
                 field,
                 operator: "IS".to_string(),
@@ -432,7 +663,7 @@ pub fn parse_compound_conditions(where_clause: &str) -> Result<Vec<Condition>, S
                 negated: false,
             });
         } else if let Some(caps) = is_not_null_re.captures(&condition_part) {
-            let field = caps[1].to_lowercase();
+            let field = field_name(&caps[1]);
             conditions.push(Condition {
                 field,
                 operator: "IS".to_string(),
@@ -440,7 +671,7 @@ pub fn parse_compound_conditions(where_clause: &str) -> Result<Vec<Condition>, S
                 negated: true, // IS NOT NULL is negated IS NULL
             });
         } else if let Some(caps) = condition_re.captures(&condition_part) {
-            let field = caps[1].to_lowercase();
+            let field = field_name(&caps[1]);
             let operator = caps[2].to_uppercase();
             let value = caps[3].trim_matches('\'').trim().to_string();
 
@@ -458,6 +689,38 @@ pub fn parse_compound_conditions(where_clause: &str) -> Result<Vec<Condition>, S
     Ok(conditions)
 }
 
+// Field names are case-insensitive, but the attribute an XATTR call names isn't
+fn field_name(field: &str) -> String {
+    match crate::xattrs::xattr_name(field) {
+        Some(_) => field.to_string(),
+        None => field.to_lowercase(),
+    }
+}
+
+// Split a WHERE clause on AND, leaving any inside quoted values alone
+fn split_and(where_clause: &str) -> Vec<&str> {
+    let token = regex::Regex::new(r#"(?i)'[^']*'|"[^"]*"|\s+and\s+"#).unwrap();
+    let mut parts = Vec::new();
+    let mut start = 0;
+    for separator in token.find_iter(where_clause) {
+        if !separator.as_str().starts_with(['\'', '"']) {
+            parts.push(&where_clause[start..separator.start()]);
+            start = separator.end();
+        }
+    }
+    parts.push(&where_clause[start..]);
+    parts
+}
+
+fn unquote(value: &str) -> &str {
+    for quote in ['\'', '"'] {
+        if let Some(inner) = value.strip_prefix(quote).and_then(|v| v.strip_suffix(quote)) {
+            return inner;
+        }
+    }
+    value
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -470,6 +733,10 @@ mod tests {
         assert_eq!(result.select_fields.len(), 6);
         assert_eq!(result.from_path, "/tmp");
         assert!(result.where_clause.is_none());
+
+        // The disks table lists its own columns
+        let result = parse_query("SELECT * FROM disks").unwrap();
+        assert_eq!(result.select_fields, vec!["*"]);
     }
 
     #[test]
@@ -531,6 +798,264 @@ mod tests {
         assert_eq!(result.where_clause, Some("name = 'node'".to_string()));
     }
 
+    #[test]
+    fn test_parse_query_with_and_conditions() {
+        let query = "SELECT name FROM /tmp WHERE type = 'file' and name LIKE '%.rs' ORDER BY name";
+        let result = parse_query(query).unwrap();
+        assert_eq!(
+            result.where_clause,
+            Some("type = 'file' AND name LIKE '%.rs'".to_string())
+        );
+        assert_eq!(result.order_by, Some("name".to_string()));
+
+        let conditions = parse_compound_conditions(result.where_clause.as_deref().unwrap()).unwrap();
+        assert_eq!(conditions.len(), 2);
+    }
+
+    #[test]
+    fn test_parse_query_with_underscored_fields() {
+        let query = "SELECT name, link_target FROM /tmp WHERE link_target IS NOT NULL ORDER BY modified_date";
+        let result = parse_query(query).unwrap();
+        assert_eq!(result.select_fields, vec!["name", "link_target"]);
+        assert_eq!(result.where_clause, Some("link_target IS NOT NULL".to_string()));
+        assert_eq!(result.order_by, Some("modified_date".to_string()));
+    }
+
+    #[test]
+    fn test_parse_query_follow_symlinks() {
+        let result = parse_query("SELECT name FROM /tmp WHERE type = 'symlink'").unwrap();
+        assert!(!result.from_options.follow_symlinks);
+
+        let result =
+            parse_query("SELECT name FROM /tmp follow symlinks WHERE type = 'file'").unwrap();
+        assert_eq!(result.from_path, "/tmp");
+        assert!(result.from_options.follow_symlinks);
+        assert_eq!(result.where_clause, Some("type = 'file'".to_string()));
+
+        let result = parse_query("DELETE FROM /tmp FOLLOW SYMLINKS WHERE name = 'x'").unwrap();
+        assert!(result.from_options.follow_symlinks);
+    }
+
+    #[test]
+    fn test_parse_aggregate_fields() {
+        let result =
+            parse_query("SELECT count(*), sum(disk_usage) AS used FROM /var WHERE type = 'file'")
+                .unwrap();
+        assert_eq!(result.select_fields, vec!["COUNT(*)", "SUM(disk_usage)"]);
+        assert_eq!(result.select_field_aliases, vec![None, Some("used".to_string())]);
+
+        // A column named like a function is still a column
+        let result = parse_query("SELECT max FROM /var").unwrap();
+        assert_eq!(result.select_fields, vec!["max"]);
+
+        assert!(parse_query("SELECT name, COUNT(*) FROM /var").is_err());
+    }
+
+    #[test]
+    fn test_parse_has_perm_condition() {
+        let result = parse_query(
+            "SELECT path FROM /srv WHERE type = 'file' AND has_perm(permissions, 'o+w')",
+        )
+        .unwrap();
+        let where_clause = result.where_clause.unwrap();
+        assert_eq!(where_clause, "type = 'file' AND HAS_PERM(permissions, 'o+w')");
+
+        let conditions = parse_compound_conditions(&where_clause).unwrap();
+        assert_eq!(conditions.len(), 2);
+        assert_eq!(conditions[1].field, "permissions");
+        assert_eq!(conditions[1].operator, "HAS_PERM");
+        assert_eq!(conditions[1].value, "o+w");
+        assert!(!conditions[1].negated);
+
+        let result = parse_query("SELECT path FROM /srv WHERE NOT HAS_PERM(mode, 'u=w')").unwrap();
+        assert_eq!(result.where_clause, Some("NOT HAS_PERM(mode, 'u=w')".to_string()));
+        // The spec is checked when the conditions are parsed
+        assert!(parse_compound_conditions("NOT HAS_PERM(mode, 'u=w')").is_err());
+        let conditions = parse_compound_conditions("NOT HAS_PERM(mode, 'u+s')").unwrap();
+        assert!(conditions[0].negated);
+    }
+
+    #[test]
+    fn test_parse_duplicates_condition() {
+        let result =
+            parse_query("SELECT path, hash FROM ~/media WHERE hash in (duplicates) AND size > 0")
+                .unwrap();
+        let where_clause = result.where_clause.unwrap();
+        assert_eq!(where_clause, "hash IN (DUPLICATES) AND size > 0");
+
+        let conditions = parse_compound_conditions(&where_clause).unwrap();
+        assert_eq!(conditions.len(), 2);
+        assert_eq!(conditions[0].field, "hash");
+        assert_eq!(conditions[0].operator, "IN");
+        assert_eq!(conditions[0].value, "DUPLICATES");
+        assert!(!conditions[0].negated);
+
+        let result = parse_query("SELECT path FROM . WHERE sha256 NOT IN (DUPLICATES)").unwrap();
+        let conditions = parse_compound_conditions(&result.where_clause.unwrap()).unwrap();
+        assert_eq!(conditions[0].field, "sha256");
+        assert!(conditions[0].negated);
+    }
+
+    #[test]
+    fn test_parse_regexp_condition() {
+        let result =
+            parse_query(r"SELECT path FROM . WHERE content regexp 'fn\s+main' AND name NOT REGEXP '^test'")
+                .unwrap();
+        let where_clause = result.where_clause.unwrap();
+        assert_eq!(where_clause, r"content REGEXP 'fn\s+main' AND name NOT REGEXP '^test'");
+
+        let conditions = parse_compound_conditions(&where_clause).unwrap();
+        assert_eq!(conditions.len(), 2);
+        assert_eq!(conditions[0].field, "content");
+        assert_eq!(conditions[0].operator, "REGEXP");
+        assert_eq!(conditions[0].value, r"fn\s+main");
+        assert!(!conditions[0].negated);
+        assert_eq!(conditions[1].value, "^test");
+        assert!(conditions[1].negated);
+
+        assert!(parse_compound_conditions("name REGEXP '('").is_err());
+        // An operator inside the pattern is part of the pattern
+        let conditions = parse_compound_conditions("content REGEXP 'a=b'").unwrap();
+        assert_eq!(conditions[0].field, "content");
+        assert_eq!(conditions[0].value, "a=b");
+    }
+
+    #[test]
+    fn test_quoted_values_keep_keywords() {
+        let result = parse_query(
+            "SELECT path FROM . WHERE content LIKE '%is not null and in%' AND type = 'file'",
+        )
+        .unwrap();
+        let where_clause = result.where_clause.unwrap();
+        assert_eq!(
+            where_clause,
+            "content LIKE '%is not null and in%' AND type = 'file'"
+        );
+
+        let conditions = parse_compound_conditions(&where_clause).unwrap();
+        assert_eq!(conditions.len(), 2);
+        assert_eq!(conditions[0].value, "%is not null and in%");
+        assert_eq!(conditions[1].field, "type");
+    }
+
+    #[test]
+    fn test_parse_query_ignore_options() {
+        let result = parse_query("SELECT name FROM . respect ignores skip hidden").unwrap();
+        assert_eq!(result.from_path, ".");
+        assert!(result.from_options.respect_ignores);
+        assert!(result.from_options.skip_hidden);
+        assert!(!result.from_options.follow_symlinks);
+
+        let result = parse_query("SELECT name FROM src SKIP HIDDEN WHERE is_hidden = false").unwrap();
+        assert!(!result.from_options.respect_ignores);
+        assert!(!result.from_options.include_archives);
+        assert!(result.from_options.skip_hidden);
+        assert_eq!(result.where_clause, Some("is_hidden = false".to_string()));
+
+        let result = parse_query("SELECT path FROM dist include archives WHERE name = 'a'").unwrap();
+        assert!(result.from_options.include_archives);
+        assert_eq!(result.where_clause, Some("name = 'a'".to_string()));
+    }
+
+    #[test]
+    fn test_parse_xattr_fields() {
+        let result = parse_query(
+            "SELECT name, XATTR(path, 'user.Tag') FROM /srv \
+             WHERE xattr(path, 'user.Tag') = 'blue' AND acl LIKE '%user:alice%' \
+             ORDER BY XATTR(path, 'user.Tag')",
+        )
+        .unwrap();
+        assert_eq!(result.select_fields, vec!["name", "XATTR(path, 'user.Tag')"]);
+        assert_eq!(result.order_by, Some("XATTR(path, 'user.Tag')".to_string()));
+
+        let conditions = parse_compound_conditions(result.where_clause.as_deref().unwrap()).unwrap();
+        assert_eq!(conditions[0].field, "xattr(path, 'user.Tag')");
+        assert_eq!(conditions[0].value, "blue");
+        assert_eq!(conditions[1].field, "acl");
+
+        let conditions =
+            parse_compound_conditions("XATTR(path, \"user.a=b\") IS NOT NULL").unwrap();
+        assert_eq!(conditions[0].field, "XATTR(path, \"user.a=b\")");
+        assert!(conditions[0].negated);
+    }
+
+    #[test]
+    fn test_parse_query_table_source() {
+        use crate::tables::TableFormat;
+
+        let result =
+            parse_query("SELECT * FROM csv('/tmp/sales.csv') WHERE amount > 100 LIMIT 5").unwrap();
+        assert_eq!(result.select_fields, vec!["*"]);
+        assert_eq!(result.where_clause, Some("amount > 100".to_string()));
+        let source = parse_table_source(&result.from_path).unwrap().unwrap();
+        assert_eq!(source.path, std::path::PathBuf::from("/tmp/sales.csv"));
+        assert_eq!(
+            source.format,
+            TableFormat::Delimited {
+                delimiter: b',',
+                has_header: true
+            }
+        );
+
+        let result =
+            parse_query("select name from TSV('/tmp/hosts.tsv', header = false)").unwrap();
+        assert_eq!(
+            parse_table_source(&result.from_path).unwrap().unwrap().format,
+            TableFormat::Delimited {
+                delimiter: b'\t',
+                has_header: false
+            }
+        );
+
+        let result = parse_query("SELECT a FROM csv('/tmp/x.txt', delimiter = '|')").unwrap();
+        assert!(matches!(
+            parse_table_source(&result.from_path).unwrap().unwrap().format,
+            TableFormat::Delimited { delimiter: b'|', .. }
+        ));
+
+        let result = parse_query(
+            "SELECT user.id FROM json('/tmp/config.json', '$.items[*]') WHERE user.name = 'ada'",
+        )
+        .unwrap();
+        assert_eq!(result.select_fields, vec!["user.id"]);
+        assert_eq!(result.where_clause, Some("user.name = 'ada'".to_string()));
+        let conditions = parse_compound_conditions("user.name = 'ada'").unwrap();
+        assert_eq!(conditions[0].field, "user.name");
+        let source = parse_table_source(&result.from_path).unwrap().unwrap();
+        assert!(matches!(source.format, TableFormat::Json { records: Some(_) }));
+        assert!(parse_query("SELECT a FROM json('/tmp/x.json', 'items')").is_err());
+        assert!(parse_query("SELECT a FROM csv('/tmp/x.csv', '$.items')").is_err());
+
+        let result = parse_query(
+            "SELECT msg FROM lines('/var/log/app.log*') pattern '(?P<level>\\w+) (?P<msg>.*)' \
+             WHERE level = 'ERROR'",
+        )
+        .unwrap();
+        assert_eq!(result.where_clause, Some("level = 'ERROR'".to_string()));
+        let source = parse_table_source(&result.from_path).unwrap().unwrap();
+        assert_eq!(source.path, std::path::PathBuf::from("/var/log/app.log*"));
+        assert!(matches!(source.format, TableFormat::Lines { pattern: Some(_) }));
+        assert!(parse_query("SELECT line FROM lines('/tmp/a.log') PATTERN '(\\w+)'").is_err());
+        assert!(parse_query("SELECT a FROM csv('/tmp/x.csv') PATTERN '(?P<a>.*)'").is_err());
+
+        let result = parse_query(
+            "SELECT file, value FROM toml('**/Cargo.toml') WHERE key = 'package.version'",
+        )
+        .unwrap();
+        let source = parse_table_source(&result.from_path).unwrap().unwrap();
+        assert_eq!(source.path, std::path::PathBuf::from("**/Cargo.toml"));
+        assert_eq!(
+            source.format,
+            TableFormat::Config {
+                syntax: crate::configs::ConfigSyntax::Toml
+            }
+        );
+        assert!(parse_query("SELECT key FROM ini('/etc/php.ini', header = false)").is_err());
+
+        assert!(parse_query("SELECT a FROM csv('/tmp/x.csv', delimiter = 'ab')").is_err());
+        assert_eq!(parse_table_source("/tmp/csv").unwrap(), None);
+    }
+
     #[test]
     fn test_parse_compound_conditions() {
         let conditions =
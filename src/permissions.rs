@@ -0,0 +1,177 @@
+// File type bits of st_mode, as in <sys/stat.h>
+const S_IFMT: u32 = 0o170000;
+const S_IFSOCK: u32 = 0o140000;
+const S_IFLNK: u32 = 0o120000;
+const S_IFREG: u32 = 0o100000;
+const S_IFBLK: u32 = 0o060000;
+const S_IFDIR: u32 = 0o040000;
+const S_IFCHR: u32 = 0o020000;
+const S_IFIFO: u32 = 0o010000;
+
+const SETUID: u32 = 0o4000;
+const SETGID: u32 = 0o2000;
+const STICKY: u32 = 0o1000;
+
+/// Permission bits without the file type, e.g. `0644`
+pub fn mode_octal(mode: u32) -> String {
+    format!("{:04o}", mode & 0o7777)
+}
+
+/// `ls -l` style mode, e.g. `-rwsr-xr-x` or `drwxrwxrwt`
+pub fn mode_string(mode: u32) -> String {
+    let file_type = match mode & S_IFMT {
+        S_IFDIR => 'd',
+        S_IFLNK => 'l',
+        S_IFCHR => 'c',
+        S_IFBLK => 'b',
+        S_IFIFO => 'p',
+        S_IFSOCK => 's',
+        _ => '-',
+    };
+
+    let mut text = String::with_capacity(10);
+    text.push(file_type);
+    // (shift to the rwx triplet, special bit shown in its execute slot, letter for it)
+    for (shift, special, letter) in [(6, SETUID, 's'), (3, SETGID, 's'), (0, STICKY, 't')] {
+        let bits = (mode >> shift) & 0o7;
+        text.push(if bits & 0o4 != 0 { 'r' } else { '-' });
+        text.push(if bits & 0o2 != 0 { 'w' } else { '-' });
+        text.push(match (bits & 0o1 != 0, mode & special != 0) {
+            (true, true) => letter,
+            (false, true) => letter.to_ascii_uppercase(),
+            (true, false) => 'x',
+            (false, false) => '-',
+        });
+    }
+    text
+}
+
+// A symlink's own mode is always 0777 and never checked by the kernel,
+// so the permission flags below are false for links
+fn is_link(mode: u32) -> bool {
+    mode & S_IFMT == S_IFLNK
+}
+
+/// A regular file with any execute bit set
+pub fn is_executable(mode: u32) -> bool {
+    mode & S_IFMT == S_IFREG && mode & 0o111 != 0
+}
+
+pub fn is_world_writable(mode: u32) -> bool {
+    !is_link(mode) && mode & 0o002 != 0
+}
+
+pub fn is_setuid(mode: u32) -> bool {
+    !is_link(mode) && mode & SETUID != 0
+}
+
+pub fn is_setgid(mode: u32) -> bool {
+    !is_link(mode) && mode & SETGID != 0
+}
+
+pub fn is_sticky(mode: u32) -> bool {
+    !is_link(mode) && mode & STICKY != 0
+}
+
+/// Parse the permission argument of `HAS_PERM` into the bits that must all be set.
+///
+/// Accepts chmod-style symbolic clauses joined by commas (`o+w`, `u+s`, `ug+rx,o+r`,
+/// where no user class means all three) or an octal mask (`0755`).
+pub fn parse_perm_spec(spec: &str) -> Result<u32, String> {
+    let spec = spec.trim();
+    let invalid = || {
+        format!(
+            "Invalid permission '{}': expected e.g. 'o+w', 'u+s' or '0755'",
+            spec
+        )
+    };
+
+    if !spec.is_empty() && spec.chars().all(|c| c.is_digit(8)) {
+        return u32::from_str_radix(spec, 8)
+            .ok()
+            .filter(|mask| *mask != 0 && *mask <= 0o7777)
+            .ok_or_else(invalid);
+    }
+
+    let mut mask = 0;
+    for clause in spec.split(',') {
+        let (who, perms) = clause.trim().split_once('+').ok_or_else(invalid)?;
+
+        let mut classes = 0;
+        for c in who.chars() {
+            classes |= match c.to_ascii_lowercase() {
+                'u' => 0o700,
+                'g' => 0o070,
+                'o' => 0o007,
+                'a' => 0o777,
+                _ => return Err(invalid()),
+            };
+        }
+        if classes == 0 {
+            classes = 0o777;
+        }
+
+        if perms.is_empty() {
+            return Err(invalid());
+        }
+        for c in perms.chars() {
+            mask |= match c.to_ascii_lowercase() {
+                'r' => classes & 0o444,
+                'w' => classes & 0o222,
+                'x' => classes & 0o111,
+                's' => {
+                    (if classes & 0o700 != 0 { SETUID } else { 0 })
+                        | (if classes & 0o070 != 0 { SETGID } else { 0 })
+                }
+                't' => STICKY,
+                _ => return Err(invalid()),
+            };
+        }
+    }
+
+    // e.g. 'o+s', which chmod accepts but which sets nothing
+    if mask == 0 {
+        return Err(invalid());
+    }
+    Ok(mask)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mode_string() {
+        assert_eq!(mode_string(0o100644), "-rw-r--r--");
+        assert_eq!(mode_string(0o104755), "-rwsr-xr-x");
+        assert_eq!(mode_string(0o102644), "-rw-r-Sr--");
+        assert_eq!(mode_string(0o041777), "drwxrwxrwt");
+        assert_eq!(mode_string(0o120777), "lrwxrwxrwx");
+        assert_eq!(mode_octal(0o104755), "4755");
+        assert_eq!(mode_octal(0o100644), "0644");
+    }
+
+    #[test]
+    fn test_permission_flags() {
+        assert!(is_executable(0o100755));
+        assert!(!is_executable(0o040755)); // Directories are searchable, not executable
+        assert!(is_world_writable(0o041777));
+        assert!(!is_world_writable(0o120777)); // Symlink modes mean nothing
+        assert!(is_setuid(0o104755));
+        assert!(!is_setgid(0o104755));
+        assert!(is_sticky(0o041777));
+    }
+
+    #[test]
+    fn test_parse_perm_spec() {
+        assert_eq!(parse_perm_spec("o+w"), Ok(0o002));
+        assert_eq!(parse_perm_spec("u+s"), Ok(0o4000));
+        assert_eq!(parse_perm_spec("+x"), Ok(0o111));
+        assert_eq!(parse_perm_spec("ug+rx, o+r"), Ok(0o554));
+        assert_eq!(parse_perm_spec("0755"), Ok(0o755));
+        assert!(parse_perm_spec("o-w").is_err());
+        assert!(parse_perm_spec("z+w").is_err());
+        assert!(parse_perm_spec("o+s").is_err());
+        assert!(parse_perm_spec("").is_err());
+    }
+}
@@ -0,0 +1,345 @@
+use crate::columns::{Column, ColumnKind, Columns};
+use crate::models::{Condition, Value};
+use crate::permissions::parse_perm_spec;
+use crate::utils::{compile_regexp, like_to_regex, parse_size};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Timelike, Utc};
+use std::cmp::Ordering;
+
+type Test<T> = Box<dyn Fn(&T) -> bool + Send + Sync>;
+
+/// A WHERE clause compiled once into a tree of closures.
+///
+/// Column names are resolved to accessors, literals are parsed into the
+/// column's type and LIKE and REGEXP patterns are turned into regexes at
+/// compile time, so evaluating a row does no string dispatch or parsing.
+pub struct Predicate<T> {
+    test: Test<T>,
+}
+
+impl<T> Predicate<T> {
+    pub fn matches(&self, row: &T) -> bool {
+        (self.test)(row)
+    }
+}
+
+/// Compile AND-ed conditions into a single predicate
+pub fn compile_conditions<T: Columns + 'static>(conditions: &[Condition]) -> Predicate<T> {
+    compile_conditions_with(conditions, T::column)
+}
+
+/// Compile AND-ed conditions against the columns `resolve` finds, for rows
+/// whose columns are only known at run time, like those of a CSV file
+pub fn compile_conditions_with<T: 'static>(
+    conditions: &[Condition],
+    resolve: impl Fn(&str) -> Option<Column<T>>,
+) -> Predicate<T> {
+    let tests: Vec<Test<T>> = conditions
+        .iter()
+        .map(|condition| compile_test(condition, resolve(&condition.field)))
+        .collect();
+    Predicate {
+        test: Box::new(move |row| tests.iter().all(|test| test(row))),
+    }
+}
+
+/// Compile one condition, including its negation
+pub fn compile_condition<T: Columns + 'static>(condition: &Condition) -> Predicate<T> {
+    Predicate {
+        test: compile_test(condition, T::column(&condition.field)),
+    }
+}
+
+fn compile_test<T: 'static>(condition: &Condition, column: Option<Column<T>>) -> Test<T> {
+    let test = compile_leaf(condition, column);
+    if condition.negated {
+        Box::new(move |row| !test(row))
+    } else {
+        test
+    }
+}
+
+fn compile_leaf<T: 'static>(condition: &Condition, column: Option<Column<T>>) -> Test<T> {
+    // Unknown columns never match, as before
+    let column = match column {
+        Some(column) => column,
+        None => return Box::new(|_| false),
+    };
+    match condition.operator.as_str() {
+        "IS" => Box::new(move |row| column.get(row) == Value::Null),
+        // The column is read as an octal mode, e.g. `permissions` or `mode`
+        "HAS_PERM" => match parse_perm_spec(&condition.value) {
+            Ok(mask) => Box::new(move |row| {
+                u32::from_str_radix(&column.get(row).as_text(), 8)
+                    .is_ok_and(|mode| mode & mask == mask)
+            }),
+            Err(_) => Box::new(|_| false),
+        },
+        "LIKE" => match like_to_regex(&condition.value) {
+            Some(regex) => Box::new(move |row| regex.is_match(&column.get(row).as_text())),
+            None => Box::new(|_| false),
+        },
+        "REGEXP" => match compile_regexp(&condition.value) {
+            Ok(regex) => Box::new(move |row| regex.is_match(&column.get(row).as_text())),
+            Err(_) => Box::new(|_| false),
+        },
+        operator => {
+            let operator = match CompareOp::parse(operator) {
+                Some(operator) => operator,
+                None => return Box::new(|_| false),
+            };
+            compile_comparison(column, operator, &condition.value)
+        }
+    }
+}
+
+fn compile_comparison<T: 'static>(
+    column: Column<T>,
+    operator: CompareOp,
+    literal: &str,
+) -> Test<T> {
+    let kind = column.kind;
+    match kind {
+        ColumnKind::Text => {
+            let literal = literal.to_string();
+            Box::new(move |row| {
+                operator.holds(column.get(row).as_text().as_ref().cmp(literal.as_str()))
+            })
+        }
+        ColumnKind::Number | ColumnKind::Size | ColumnKind::Percent => {
+            let parsed = match kind {
+                ColumnKind::Size => parse_size(literal).ok(),
+                ColumnKind::Percent => literal.trim().trim_end_matches('%').parse::<f64>().ok(),
+                _ => literal.trim().parse::<f64>().ok(),
+            };
+            // A literal that doesn't parse can't match any row
+            let literal = match parsed {
+                Some(literal) => literal,
+                None => return Box::new(|_| false),
+            };
+            // CPU readings are noisy, so percentages are equal within 0.1
+            let tolerance = if kind == ColumnKind::Percent {
+                0.1
+            } else {
+                0.0
+            };
+            Box::new(move |row| match column.get(row) {
+                Value::Number(number) => {
+                    let ordering = if (number - literal).abs() < tolerance {
+                        Ordering::Equal
+                    } else {
+                        number.partial_cmp(&literal).unwrap_or(Ordering::Equal)
+                    };
+                    operator.holds(ordering)
+                }
+                _ => false,
+            })
+        }
+        ColumnKind::Timestamp => {
+            let literal = match parse_timestamp(literal) {
+                Some(literal) => literal,
+                None => return Box::new(|_| false),
+            };
+            // A literal given to the second compares at that granularity, so
+            // '2025-01-02 10:00:00' equals a file modified at 10:00:00.7
+            let whole_seconds = literal.timestamp_subsec_nanos() == 0;
+            Box::new(move |row| match column.get(row) {
+                Value::Timestamp(timestamp) => {
+                    let timestamp = if whole_seconds {
+                        timestamp.with_nanosecond(0).unwrap_or(timestamp)
+                    } else {
+                        timestamp
+                    };
+                    operator.holds(timestamp.cmp(&literal))
+                }
+                _ => false,
+            })
+        }
+        ColumnKind::Boolean => {
+            let literal = match literal.trim().to_lowercase().as_str() {
+                "true" | "1" => true,
+                "false" | "0" => false,
+                _ => return Box::new(|_| false),
+            };
+            Box::new(move |row| match column.get(row) {
+                Value::Bool(flag) => operator.holds(flag.cmp(&literal)),
+                _ => false,
+            })
+        }
+    }
+}
+
+/// Parse a date literal: 'YYYY-MM-DD HH:MM:SS[.fraction]', 'YYYY-MM-DD' or RFC 3339
+pub fn parse_timestamp(literal: &str) -> Option<DateTime<Utc>> {
+    let literal = literal.trim();
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(literal) {
+        return Some(timestamp.with_timezone(&Utc));
+    }
+    if let Ok(timestamp) = NaiveDateTime::parse_from_str(literal, "%Y-%m-%d %H:%M:%S%.f") {
+        return Some(timestamp.and_utc());
+    }
+    NaiveDate::parse_from_str(literal, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|timestamp| timestamp.and_utc())
+}
+
+#[derive(Debug, Clone, Copy)]
+enum CompareOp {
+    Equal,
+    NotEqual,
+    Greater,
+    Less,
+    GreaterEqual,
+    LessEqual,
+}
+
+impl CompareOp {
+    fn parse(operator: &str) -> Option<Self> {
+        match operator {
+            "=" => Some(CompareOp::Equal),
+            "!=" | "<>" => Some(CompareOp::NotEqual),
+            ">" => Some(CompareOp::Greater),
+            "<" => Some(CompareOp::Less),
+            ">=" => Some(CompareOp::GreaterEqual),
+            "<=" => Some(CompareOp::LessEqual),
+            _ => None,
+        }
+    }
+
+    fn holds(self, ordering: Ordering) -> bool {
+        match self {
+            CompareOp::Equal => ordering == Ordering::Equal,
+            CompareOp::NotEqual => ordering != Ordering::Equal,
+            CompareOp::Greater => ordering == Ordering::Greater,
+            CompareOp::Less => ordering == Ordering::Less,
+            CompareOp::GreaterEqual => ordering != Ordering::Less,
+            CompareOp::LessEqual => ordering != Ordering::Greater,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{FileInfo, ProcessInfo};
+
+    fn condition(field: &str, operator: &str, value: &str, negated: bool) -> Condition {
+        Condition {
+            field: field.to_string(),
+            operator: operator.to_string(),
+            value: value.to_string(),
+            negated,
+        }
+    }
+
+    #[test]
+    fn test_compiled_file_predicate() {
+        let file = FileInfo {
+            name: "main.rs".to_string(),
+            file_type: "file".to_string(),
+            size: "2 KB".to_string(),
+            size_bytes: 2048,
+            path: "src/main.rs".to_string(),
+            depth: 2,
+            extension: Some("rs".to_string()),
+            ..Default::default()
+        };
+
+        let predicate = compile_conditions::<FileInfo>(&[
+            condition("name", "LIKE", "%.rs", false),
+            condition("path", "LIKE", "%target/%", true),
+            condition("size", ">", "1 KB", false),
+            condition("depth", "<=", "2", false),
+        ]);
+        assert!(predicate.matches(&file));
+
+        let predicate = compile_conditions::<FileInfo>(&[condition("size", ">", "1 MB", false)]);
+        assert!(!predicate.matches(&file));
+
+        // Unparseable literals and unknown columns never match
+        let predicate = compile_conditions::<FileInfo>(&[condition("depth", "=", "deep", false)]);
+        assert!(!predicate.matches(&file));
+        let predicate = compile_conditions::<FileInfo>(&[condition("owner", "=", "me", false)]);
+        assert!(!predicate.matches(&file));
+    }
+
+    #[test]
+    fn test_compiled_null_and_timestamp_predicates() {
+        let file = FileInfo {
+            name: "README".to_string(),
+            modified_date: parse_timestamp("2025-06-01 12:00:00").unwrap(),
+            ..Default::default()
+        };
+
+        assert!(
+            compile_condition::<FileInfo>(&condition("extension", "IS", "NULL", false))
+                .matches(&file)
+        );
+        assert!(
+            !compile_condition::<FileInfo>(&condition("extension", "IS", "NULL", true))
+                .matches(&file)
+        );
+        assert!(
+            compile_condition::<FileInfo>(&condition("extension", "=", "NULL", false))
+                .matches(&file)
+        );
+
+        let after = condition("modified_date", ">", "2025-01-01", false);
+        assert!(compile_condition::<FileInfo>(&after).matches(&file));
+        let before = condition("modified_date", "<", "2025-06-01 11:59:59", false);
+        assert!(!compile_condition::<FileInfo>(&before).matches(&file));
+    }
+
+    #[test]
+    fn test_timestamps_keep_nanoseconds() {
+        let changed = parse_timestamp("2025-06-01 12:00:00.000000500").unwrap();
+        let file = FileInfo {
+            modified_date: parse_timestamp("2025-06-01 12:00:00").unwrap(),
+            changed_date: Some(changed),
+            ..Default::default()
+        };
+        assert_eq!(changed.timestamp_subsec_nanos(), 500);
+        assert_eq!(Value::Timestamp(changed).as_text(), "2025-06-01 12:00:00");
+        assert_eq!(
+            Value::Timestamp(file.modified_date).as_text(),
+            "2025-06-01 12:00:00"
+        );
+
+        // Half a microsecond apart is still a difference to a fractional literal
+        let later = condition("changed_date", ">", "2025-06-01 12:00:00.0000004", false);
+        assert!(compile_condition::<FileInfo>(&later).matches(&file));
+        let exact = condition("changed_date", "=", "2025-06-01 12:00:00.0000005", false);
+        assert!(compile_condition::<FileInfo>(&exact).matches(&file));
+
+        // but not to one given to the second, which matches the whole second
+        let second = condition("changed_date", "=", "2025-06-01 12:00:00", false);
+        assert!(compile_condition::<FileInfo>(&second).matches(&file));
+        let after = condition("changed_date", ">", "2025-06-01 12:00:00", false);
+        assert!(!compile_condition::<FileInfo>(&after).matches(&file));
+        let before = condition("changed_date", "<", "2025-06-01 12:00:01", false);
+        assert!(compile_condition::<FileInfo>(&before).matches(&file));
+
+        // Missing timestamps are NULL rather than the epoch
+        let created = condition("created_date", "IS", "NULL", false);
+        assert!(compile_condition::<FileInfo>(&created).matches(&file));
+    }
+
+    #[test]
+    fn test_compiled_process_predicate() {
+        let process = ProcessInfo::new(1234, "node", 5.55, 2 * 1024 * 1024, "running");
+
+        assert!(
+            compile_condition::<ProcessInfo>(&condition("cpu_usage", "=", "5.5", false))
+                .matches(&process)
+        );
+        assert!(
+            compile_condition::<ProcessInfo>(&condition("memory_usage", ">", "1 MB", false))
+                .matches(&process)
+        );
+        // pid is numeric, so 1234 > 999 even though "1234" < "999" as strings
+        assert!(
+            compile_condition::<ProcessInfo>(&condition("pid", ">", "999", false))
+                .matches(&process)
+        );
+    }
+}
@@ -1,39 +1,51 @@
-use crate::models::{Condition, ProcessInfo};
+use crate::aggregate::is_aggregate_query;
+use crate::cancel::CancelToken;
+use crate::models::ProcessInfo;
 use crate::parser::parse_compound_conditions;
-use crate::utils::{compare_strings, like_match, sort_process_results};
+use crate::predicate::compile_conditions;
+use crate::utils::sort_process_results;
 use sysinfo::{ProcessRefreshKind, System};
 
-pub fn execute_process_query(query: &crate::models::SqlQuery) -> Result<Vec<ProcessInfo>, String> {
+pub fn execute_process_query(
+    query: &crate::models::SqlQuery,
+    cancel: &CancelToken,
+) -> Result<Vec<ProcessInfo>, String> {
     let conditions = if let Some(where_clause) = &query.where_clause {
         parse_compound_conditions(where_clause)?
     } else {
         Vec::new()
     };
 
-    let processes = collect_processes()?;
-    let mut results: Vec<ProcessInfo> = Vec::new();
+    let predicate = compile_conditions::<ProcessInfo>(&conditions);
+    let processes = collect_processes(cancel)?;
 
     // Apply WHERE conditions
-    for process in processes {
-        if evaluate_process_conditions(&process, &conditions) {
-            results.push(process);
-        }
-    }
+    let mut results: Vec<ProcessInfo> = processes
+        .into_iter()
+        .filter(|process| predicate.matches(process))
+        .collect();
 
     // Apply ORDER BY
     if let Some(order_by) = &query.order_by {
         sort_process_results(&mut results, order_by, &query.order_direction)?;
     }
 
-    // Apply LIMIT
+    // Apply LIMIT; an aggregate query summarises all rows instead
     if let Some(limit) = query.limit {
-        results.truncate(limit);
+        if !is_aggregate_query(&query.select_fields) {
+            results.truncate(limit);
+        }
     }
 
     Ok(results)
 }
 
-fn collect_processes() -> Result<Vec<ProcessInfo>, String> {
+fn collect_processes(cancel: &CancelToken) -> Result<Vec<ProcessInfo>, String> {
+    // The sysinfo refresh itself can't be interrupted, so check around it
+    if cancel.is_cancelled() {
+        return Ok(Vec::new());
+    }
+
     let mut system = System::new_all();
     system.refresh_processes_specifics(
         ProcessRefreshKind::everything()
@@ -44,6 +56,10 @@ fn collect_processes() -> Result<Vec<ProcessInfo>, String> {
     let mut processes = Vec::new();
 
     for (pid, process) in system.processes() {
+        if cancel.is_cancelled() {
+            break;
+        }
+
         let status = match process.status() {
             sysinfo::ProcessStatus::Run => "running",
             sysinfo::ProcessStatus::Sleep => "sleeping",
@@ -67,83 +83,6 @@ fn collect_processes() -> Result<Vec<ProcessInfo>, String> {
     Ok(processes)
 }
 
-fn evaluate_process_conditions(process: &ProcessInfo, conditions: &[Condition]) -> bool {
-    for condition in conditions {
-        let result = evaluate_single_process_condition(process, condition);
-        let final_result = if condition.negated { !result } else { result };
-
-        if !final_result {
-            return false;
-        }
-    }
-    true
-}
-
-fn evaluate_single_process_condition(process: &ProcessInfo, condition: &Condition) -> bool {
-    match condition.field.as_str() {
-        "pid" => {
-            if condition.operator == "LIKE" {
-                like_match(&process.pid, &condition.value)
-            } else {
-                compare_strings(&process.pid, &condition.operator, &condition.value)
-            }
-        }
-        "name" => {
-            if condition.operator == "LIKE" {
-                like_match(&process.name, &condition.value)
-            } else {
-                compare_strings(&process.name, &condition.operator, &condition.value)
-            }
-        }
-        "cpu_usage" => {
-            // Extract numeric value from "X.X%" format
-            if let Some(cpu_str) = process.cpu_usage.strip_suffix('%') {
-                if let Ok(cpu_val) = cpu_str.parse::<f32>() {
-                    if let Ok(compare_val) = condition.value.parse::<f32>() {
-                        match condition.operator.as_str() {
-                            "=" => (cpu_val - compare_val).abs() < 0.1, // Allow small floating point differences
-                            "!=" => (cpu_val - compare_val).abs() >= 0.1,
-                            ">" => cpu_val > compare_val,
-                            "<" => cpu_val < compare_val,
-                            ">=" => cpu_val >= compare_val,
-                            "<=" => cpu_val <= compare_val,
-                            _ => false,
-                        }
-                    } else {
-                        false
-                    }
-                } else {
-                    false
-                }
-            } else {
-                false
-            }
-        }
-        "memory_usage" => {
-            // For memory comparison, extract numeric value
-            if let Ok(process_memory) = parse_memory(&process.memory_usage) {
-                if let Ok(compare_memory) = parse_memory(&condition.value) {
-                    match condition.operator.as_str() {
-                        "=" => process_memory == compare_memory,
-                        "!=" => process_memory != compare_memory,
-                        ">" => process_memory > compare_memory,
-                        "<" => process_memory < compare_memory,
-                        ">=" => process_memory >= compare_memory,
-                        "<=" => process_memory <= compare_memory,
-                        _ => false,
-                    }
-                } else {
-                    false
-                }
-            } else {
-                false
-            }
-        }
-        "status" => compare_strings(&process.status, &condition.operator, &condition.value),
-        _ => false,
-    }
-}
-
 pub fn parse_memory(memory_str: &str) -> Result<f64, String> {
     let re = regex::Regex::new(r"([\d.]+)\s*(B|KB|MB|GB|TB)?").unwrap();
     if let Some(caps) = re.captures(memory_str) {
@@ -198,7 +137,7 @@ mod tests {
             },
         ];
 
-        assert!(evaluate_process_conditions(&process, &conditions));
+        assert!(compile_conditions::<ProcessInfo>(&conditions).matches(&process));
 
         // Test with a condition that should NOT match
         let bad_conditions = vec![Condition {
@@ -208,7 +147,7 @@ mod tests {
             negated: false,
         }];
 
-        assert!(!evaluate_process_conditions(&process, &bad_conditions));
+        assert!(!compile_conditions::<ProcessInfo>(&bad_conditions).matches(&process));
     }
 
     #[test]
@@ -227,9 +166,10 @@ mod tests {
             order_direction: crate::models::SortDirection::Ascending,
             limit: Some(2),
             distinct: false,
+            from_options: Default::default(),
         };
 
-        let result = execute_process_query(&query);
+        let result = execute_process_query(&query, &CancelToken::new());
         assert!(result.is_ok());
 
         let processes = result.unwrap();
@@ -258,9 +198,10 @@ mod tests {
             order_direction: crate::models::SortDirection::Ascending,
             limit: Some(3),
             distinct: false,
+            from_options: Default::default(),
         };
 
-        let result = execute_process_query(&query);
+        let result = execute_process_query(&query, &CancelToken::new());
         assert!(result.is_ok());
 
         let processes = result.unwrap();
@@ -269,4 +210,14 @@ mod tests {
             assert_eq!(process.status, "running");
         }
     }
+
+    #[test]
+    fn test_execute_process_query_cancelled() {
+        let query = crate::parser::parse_query("SELECT pid, name FROM ps").unwrap();
+        let cancel = CancelToken::new();
+        cancel.cancel();
+
+        let processes = execute_process_query(&query, &cancel).unwrap();
+        assert!(processes.is_empty());
+    }
 }
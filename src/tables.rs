@@ -0,0 +1,647 @@
+use crate::aggregate::{is_aggregate_query, parse_aggregate};
+use crate::cancel::CancelToken;
+use crate::columns::{format_value, Column, ColumnKind, Columns};
+use crate::configs::{self, ConfigSyntax};
+use crate::json::{self, JsonPath};
+use crate::limits::QueryLimits;
+use crate::logs::{self, LinePattern};
+use crate::models::{Condition, SqlQuery, Value};
+use crate::predicate::{compile_conditions_with, parse_timestamp};
+use crate::utils::{expand_path, open_regular_file, sort_by_column_with};
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+
+/// A file read as a table, as in `FROM csv('data.csv')` or `FROM json('events.ndjson')`
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableSource {
+    pub path: PathBuf,
+    pub format: TableFormat,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TableFormat {
+    /// CSV or TSV. Without a header row, columns are named column1, column2, ...
+    Delimited { delimiter: u8, has_header: bool },
+    /// A JSON document or JSON Lines, with the records found at `records`
+    Json { records: Option<JsonPath> },
+    /// Lines of text files matching a glob, split into columns by `pattern`
+    Lines { pattern: Option<LinePattern> },
+    /// A row per setting in TOML, YAML or INI files matching a glob
+    Config { syntax: ConfigSyntax },
+}
+
+impl TableSource {
+    /// A source for `csv(path, ...)`, `tsv(path, ...)`, `json(path, '$.items[*]')`,
+    /// `lines(path) PATTERN '...'`, `toml(path)`, `yaml(path)` or `ini(path)`,
+    /// with its `delimiter = ';'` and `header = false` options applied
+    pub fn new(
+        format: &str,
+        path: &str,
+        records: Option<&str>,
+        pattern: Option<&str>,
+        options: &[(String, String)],
+    ) -> Result<Self, String> {
+        let path = PathBuf::from(expand_path(path));
+        if records.is_some() && format != "json" {
+            return Err(format!("{}() takes no JSON path", format));
+        }
+        if pattern.is_some() && format != "lines" {
+            return Err(format!("PATTERN only applies to lines(), not {}()", format));
+        }
+        let delimiter = match format {
+            "csv" => b',',
+            "tsv" => b'\t',
+            "json" | "lines" | "toml" | "yaml" | "ini" => {
+                if let Some((name, _)) = options.first() {
+                    return Err(format!("Unknown {}() option '{}'", format, name));
+                }
+                let format = match format {
+                    "json" => TableFormat::Json {
+                        records: records.map(JsonPath::parse).transpose()?,
+                    },
+                    "lines" => TableFormat::Lines {
+                        pattern: pattern.map(LinePattern::new).transpose()?,
+                    },
+                    "toml" => TableFormat::Config {
+                        syntax: ConfigSyntax::Toml,
+                    },
+                    "yaml" => TableFormat::Config {
+                        syntax: ConfigSyntax::Yaml,
+                    },
+                    _ => TableFormat::Config {
+                        syntax: ConfigSyntax::Ini,
+                    },
+                };
+                return Ok(TableSource { path, format });
+            }
+            _ => return Err(format!("Unknown table format '{}'", format)),
+        };
+
+        let mut has_header = true;
+        let mut delimiter = delimiter;
+        for (name, value) in options {
+            match name.to_lowercase().as_str() {
+                "delimiter" => {
+                    delimiter = match value.as_str() {
+                        "\\t" | "tab" => b'\t',
+                        value if value.len() == 1 => value.as_bytes()[0],
+                        _ => {
+                            return Err(format!(
+                                "The delimiter must be a single character, not '{}'",
+                                value
+                            ))
+                        }
+                    }
+                }
+                "header" => {
+                    has_header = match value.to_lowercase().as_str() {
+                        "true" | "1" => true,
+                        "false" | "0" => false,
+                        _ => return Err(format!("header must be true or false, not '{}'", value)),
+                    }
+                }
+                _ => {
+                    return Err(format!(
+                        "Unknown {}() option '{}'; use delimiter or header",
+                        format, name
+                    ))
+                }
+            }
+        }
+        Ok(TableSource {
+            path,
+            format: TableFormat::Delimited {
+                delimiter,
+                has_header,
+            },
+        })
+    }
+}
+
+// Tables are read twice, to infer the column types and then to filter the
+// rows, so they have to be regular files rather than pipes
+pub(crate) fn open_table_file(path: &Path) -> Result<std::fs::File, String> {
+    open_regular_file(path).ok_or_else(|| format!("Cannot read table file: {}", path.display()))
+}
+
+/// The files a table path names: the path itself, or every file matching it
+/// when it is a glob such as `/var/log/app.log*` or `**/Cargo.toml`, in path order
+pub(crate) fn matching_files(path: &Path) -> Result<Vec<PathBuf>, String> {
+    let pattern = path.to_string_lossy();
+    if !pattern.contains(['*', '?', '[']) {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    let files: Vec<PathBuf> = glob::glob(&pattern)
+        .map_err(|e| format!("Invalid glob '{}': {}", pattern, e))?
+        .filter_map(Result::ok)
+        .filter(|path| path.is_file())
+        .collect();
+    if files.is_empty() {
+        return Err(format!("No files match {}", pattern));
+    }
+    Ok(files)
+}
+
+// Tab-separated files don't quote fields, so a `"` is just a character
+// there rather than the start of a field that runs on past tabs and newlines
+fn delimited_reader(
+    path: &Path,
+    delimiter: u8,
+    has_header: bool,
+) -> Result<csv::Reader<std::fs::File>, String> {
+    Ok(csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(has_header)
+        .flexible(true)
+        .quoting(delimiter != b'\t')
+        .from_reader(open_table_file(path)?))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableColumn {
+    pub name: String,
+    pub kind: ColumnKind,
+}
+
+/// One record of a table, its cells typed according to their columns
+#[derive(Debug, Clone)]
+pub struct TableRow {
+    cells: Vec<Value<'static>>,
+}
+
+impl TableRow {
+    /// A row from the text of each column's cell, `None` where it has none
+    pub(crate) fn from_texts<'a>(
+        texts: impl IntoIterator<Item = Option<&'a str>>,
+        columns: &[TableColumn],
+    ) -> Self {
+        let mut texts = texts.into_iter();
+        let cells = columns
+            .iter()
+            .map(|column| match texts.next().flatten() {
+                Some(text) => parse_cell(text, column.kind),
+                None => Value::Null, // Short rows are padded with NULLs
+            })
+            .collect();
+        TableRow { cells }
+    }
+
+    /// A row from cells that are already typed, one per column
+    pub(crate) fn from_values(cells: Vec<Value<'static>>) -> Self {
+        TableRow { cells }
+    }
+}
+
+fn cell(row: &TableRow, index: usize) -> Value<'_> {
+    match row.cells.get(index) {
+        Some(Value::Text(text)) => Value::Text(Cow::Borrowed(text)),
+        Some(value) => value.clone(),
+        None => Value::Null,
+    }
+}
+
+impl Columns for TableRow {
+    // A table's columns come from its file, so they are looked up with `Table::column`
+    fn column(_name: &str) -> Option<Column<Self>> {
+        None
+    }
+}
+
+/// The matching rows of a table and the columns they have
+#[derive(Debug, Clone)]
+pub struct Table {
+    pub columns: Vec<TableColumn>,
+    pub rows: Vec<TableRow>,
+}
+
+impl Table {
+    pub fn column(&self, name: &str) -> Option<Column<TableRow>> {
+        find_column(&self.columns, name)
+    }
+
+    /// The SELECT list with `*` expanded to every column
+    pub fn fields(&self, select_fields: &[String]) -> Vec<String> {
+        if select_fields == ["*"] {
+            self.columns.iter().map(|c| c.name.clone()).collect()
+        } else {
+            select_fields.to_vec()
+        }
+    }
+
+    pub fn display_value(&self, row: &TableRow, field: &str) -> Option<String> {
+        let column = self.column(field)?;
+        Some(format_value(column.kind, column.get(row)))
+    }
+}
+
+fn find_column(columns: &[TableColumn], name: &str) -> Option<Column<TableRow>> {
+    let name = name.to_lowercase();
+    let index = columns.iter().position(|column| column.name == name)?;
+    Some(Column::cell(columns[index].kind, cell, index))
+}
+
+/// A header or key as a name that can be written in a query: `Unit Price` is `unit_price`
+pub(crate) fn normalize_name(title: &str) -> String {
+    let name: String = title
+        .trim()
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect();
+    name.trim_matches('_').to_string()
+}
+
+// Blank and repeated header names are numbered
+fn column_names(header: Option<&csv::StringRecord>, count: usize) -> Vec<String> {
+    let mut names: Vec<String> = Vec::with_capacity(count);
+    for index in 0..count {
+        let title = header.and_then(|header| header.get(index)).unwrap_or("");
+        let mut name = normalize_name(title);
+        if name.is_empty() {
+            name = format!("column{}", index + 1);
+        }
+        if names.contains(&name) {
+            name = format!("{}_{}", name, index + 1);
+        }
+        names.push(name);
+    }
+    names
+}
+
+// Numbers with leading zeros (zip codes, part numbers) and integers too long
+// for an f64 to hold exactly (ids) are kept as text
+fn is_number(text: &str) -> bool {
+    let digits = text.trim_start_matches(['-', '+']);
+    let starts_like_number = digits.starts_with(|c: char| c.is_ascii_digit() || c == '.');
+    let leading_zero =
+        digits.len() > 1 && digits.starts_with('0') && digits.as_bytes()[1].is_ascii_digit();
+    let too_long = digits.chars().filter(|c| c.is_ascii_digit()).count() > 15;
+    starts_like_number && !leading_zero && !too_long && text.parse::<f64>().is_ok()
+}
+
+fn is_boolean(text: &str) -> bool {
+    text.eq_ignore_ascii_case("true") || text.eq_ignore_ascii_case("false")
+}
+
+/// What the non-empty cells of a column have all looked like so far
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct KindGuess {
+    seen: bool,
+    number: bool,
+    boolean: bool,
+    timestamp: bool,
+}
+
+impl KindGuess {
+    pub(crate) fn new() -> Self {
+        KindGuess {
+            seen: false,
+            number: true,
+            boolean: true,
+            timestamp: true,
+        }
+    }
+
+    pub(crate) fn observe(&mut self, text: &str) {
+        let text = text.trim();
+        if text.is_empty() {
+            return;
+        }
+        self.seen = true;
+        self.number = self.number && is_number(text);
+        self.boolean = self.boolean && is_boolean(text);
+        self.timestamp = self.timestamp && parse_timestamp(text).is_some();
+    }
+
+    pub(crate) fn kind(self) -> ColumnKind {
+        if !self.seen {
+            ColumnKind::Text
+        } else if self.number {
+            ColumnKind::Number
+        } else if self.boolean {
+            ColumnKind::Boolean
+        } else if self.timestamp {
+            ColumnKind::Timestamp
+        } else {
+            ColumnKind::Text
+        }
+    }
+}
+
+// Empty cells are NULL; the rest parse, as every cell of the column was checked
+fn parse_cell(text: &str, kind: ColumnKind) -> Value<'static> {
+    let trimmed = text.trim();
+    if trimmed.is_empty() {
+        return Value::Null;
+    }
+    match kind {
+        ColumnKind::Number => trimmed.parse().map_or(Value::Null, Value::Number),
+        ColumnKind::Boolean => Value::Bool(trimmed.eq_ignore_ascii_case("true")),
+        ColumnKind::Timestamp => parse_timestamp(trimmed).map_or(Value::Null, Value::Timestamp),
+        _ => Value::Text(Cow::Owned(text.to_string())),
+    }
+}
+
+/// First pass over a CSV or TSV file: name the columns and infer each one's
+/// type from every value in it
+fn infer_delimited_columns(
+    path: &Path,
+    delimiter: u8,
+    has_header: bool,
+    cancel: &CancelToken,
+) -> Result<Vec<TableColumn>, String> {
+    let mut reader = delimited_reader(path, delimiter, has_header)?;
+    let header = if has_header {
+        Some(reader.headers().map_err(|e| e.to_string())?.clone())
+    } else {
+        None
+    };
+    let mut guesses = vec![KindGuess::new(); header.as_ref().map_or(0, |h| h.len())];
+    for record in reader.records() {
+        if cancel.is_cancelled() {
+            break;
+        }
+        let record = record.map_err(|e| format!("{}: {}", path.display(), e))?;
+        if record.len() > guesses.len() {
+            guesses.resize(record.len(), KindGuess::new());
+        }
+        for (guess, text) in guesses.iter_mut().zip(record.iter()) {
+            guess.observe(text);
+        }
+    }
+
+    let names = column_names(header.as_ref(), guesses.len());
+    Ok(names
+        .into_iter()
+        .zip(guesses)
+        .map(|(name, guess)| TableColumn {
+            name,
+            kind: guess.kind(),
+        })
+        .collect())
+}
+
+fn for_each_delimited_row(
+    path: &Path,
+    delimiter: u8,
+    has_header: bool,
+    columns: &[TableColumn],
+    cancel: &CancelToken,
+    keep: &mut impl FnMut(TableRow) -> Result<(), String>,
+) -> Result<(), String> {
+    let mut reader = delimited_reader(path, delimiter, has_header)?;
+    for record in reader.records() {
+        if cancel.is_cancelled() {
+            break;
+        }
+        let record = record.map_err(|e| format!("{}: {}", path.display(), e))?;
+        keep(TableRow::from_texts(record.iter().map(Some), columns))?;
+    }
+    Ok(())
+}
+
+/// Stream a table file, keeping only the rows that match `conditions`
+pub fn read_table(
+    source: &TableSource,
+    conditions: &[Condition],
+    cancel: &CancelToken,
+    limits: &QueryLimits,
+) -> Result<Table, String> {
+    let path = &source.path;
+    let columns = match &source.format {
+        TableFormat::Delimited {
+            delimiter,
+            has_header,
+        } => infer_delimited_columns(path, *delimiter, *has_header, cancel)?,
+        TableFormat::Json { records } => {
+            json::infer_columns(path, records.as_ref(), limits.max_rows, cancel)?
+        }
+        TableFormat::Lines { pattern } => logs::infer_columns(path, pattern.as_ref(), cancel)?,
+        TableFormat::Config { .. } => configs::columns(),
+    };
+    for condition in conditions {
+        check_column(&columns, &condition.field)?;
+    }
+    let predicate = compile_conditions_with(conditions, |name| find_column(&columns, name));
+
+    let mut rows = Vec::new();
+    let mut keep = |row: TableRow| {
+        if predicate.matches(&row) {
+            limits.check_rows(rows.len() + 1)?;
+            rows.push(row);
+        }
+        Ok(())
+    };
+    match &source.format {
+        TableFormat::Delimited {
+            delimiter,
+            has_header,
+        } => for_each_delimited_row(path, *delimiter, *has_header, &columns, cancel, &mut keep)?,
+        TableFormat::Json { records } => {
+            let max_rows = limits.max_rows;
+            json::for_each_row(
+                path,
+                records.as_ref(),
+                &columns,
+                max_rows,
+                cancel,
+                &mut keep,
+            )?
+        }
+        TableFormat::Lines { pattern } => {
+            logs::for_each_row(path, pattern.as_ref(), &columns, cancel, &mut keep)?
+        }
+        TableFormat::Config { syntax } => {
+            configs::for_each_row(path, *syntax, &columns, cancel, &mut keep)?
+        }
+    }
+    Ok(Table { columns, rows })
+}
+
+// A misspelled column would otherwise silently match nothing
+fn check_column(columns: &[TableColumn], name: &str) -> Result<(), String> {
+    if name == "*" || find_column(columns, name).is_some() {
+        return Ok(());
+    }
+    let names: Vec<&str> = columns.iter().map(|c| c.name.as_str()).collect();
+    Err(format!(
+        "Unknown column '{}'; the table has {}",
+        name,
+        names.join(", ")
+    ))
+}
+
+/// Run a SELECT over a table file: WHERE while streaming, then ORDER BY and LIMIT
+pub fn execute_table_query(
+    query: &SqlQuery,
+    source: &TableSource,
+    conditions: &[Condition],
+    cancel: &CancelToken,
+    limits: &QueryLimits,
+) -> Result<Table, String> {
+    let table = read_table(source, conditions, cancel, limits)?;
+    order_and_limit(query, table)
+}
+
+/// Keep the rows of a table built in memory that match `conditions`
+pub(crate) fn filter_rows(
+    columns: Vec<TableColumn>,
+    rows: Vec<TableRow>,
+    conditions: &[Condition],
+) -> Result<Table, String> {
+    for condition in conditions {
+        check_column(&columns, &condition.field)?;
+    }
+    let predicate = compile_conditions_with(conditions, |name| find_column(&columns, name));
+    let rows = rows
+        .into_iter()
+        .filter(|row| predicate.matches(row))
+        .collect();
+    Ok(Table { columns, rows })
+}
+
+/// ORDER BY and LIMIT over the rows that passed WHERE
+pub(crate) fn order_and_limit(query: &SqlQuery, mut table: Table) -> Result<Table, String> {
+    for field in query.select_fields.iter().chain(&query.order_by) {
+        let column = parse_aggregate(field).map_or(field.as_str(), |(_, column)| column);
+        check_column(&table.columns, column)?;
+    }
+
+    if let Some(order_by) = &query.order_by {
+        let columns = &table.columns;
+        sort_by_column_with(&mut table.rows, order_by, &query.order_direction, |name| {
+            find_column(columns, name)
+        });
+    }
+    if let Some(limit) = query.limit {
+        if !is_aggregate_query(&query.select_fields) {
+            table.rows.truncate(limit);
+        }
+    }
+    Ok(table)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn condition(field: &str, operator: &str, value: &str) -> Condition {
+        Condition {
+            field: field.to_string(),
+            operator: operator.to_string(),
+            value: value.to_string(),
+            negated: false,
+        }
+    }
+
+    fn texts(table: &Table, field: &str) -> Vec<String> {
+        table
+            .rows
+            .iter()
+            .map(|row| table.display_value(row, field).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_infer_and_filter() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("inventory.csv");
+        fs::write(
+            &path,
+            "SKU,Unit Price,In Stock,Added\n\
+             00123,4.50,true,2025-01-02\n\
+             00456,12,false,2025-03-04\n\
+             00789,,TRUE,2024-12-31\n",
+        )
+        .unwrap();
+        let source = TableSource::new("csv", path.to_str().unwrap(), None, None, &[]).unwrap();
+        let cancel = CancelToken::new();
+        let limits = QueryLimits::default();
+
+        let table = read_table(&source, &[], &cancel, &limits).unwrap();
+        let kinds: Vec<_> = table
+            .columns
+            .iter()
+            .map(|c| (c.name.as_str(), c.kind))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ("sku", ColumnKind::Text),
+                ("unit_price", ColumnKind::Number),
+                ("in_stock", ColumnKind::Boolean),
+                ("added", ColumnKind::Timestamp),
+            ]
+        );
+        assert_eq!(texts(&table, "sku"), vec!["00123", "00456", "00789"]);
+        assert_eq!(texts(&table, "unit_price"), vec!["4.5", "12", "NULL"]);
+
+        // Numbers compare as numbers, not text
+        let cheap = [condition("unit_price", "<", "10")];
+        let table = read_table(&source, &cheap, &cancel, &limits).unwrap();
+        assert_eq!(texts(&table, "sku"), vec!["00123"]);
+        let recent = [
+            condition("added", ">=", "2025-01-01"),
+            condition("in_stock", "=", "true"),
+        ];
+        let table = read_table(&source, &recent, &cancel, &limits).unwrap();
+        assert_eq!(texts(&table, "sku"), vec!["00123"]);
+
+        let typo = [condition("price", "<", "10")];
+        let error = read_table(&source, &typo, &cancel, &limits).unwrap_err();
+        assert!(error.contains("unit_price"));
+    }
+
+    #[test]
+    fn test_delimiters_and_headers() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("hosts.tsv");
+        fs::write(&path, "web1\t10.0.0.1\t8\nweb2\t10.0.0.2\n").unwrap();
+        let options = [("header".to_string(), "false".to_string())];
+        let source = TableSource::new("tsv", path.to_str().unwrap(), None, None, &options).unwrap();
+        let table = read_table(&source, &[], &CancelToken::new(), &QueryLimits::default()).unwrap();
+        let names: Vec<_> = table.columns.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["column1", "column2", "column3"]);
+        assert_eq!(table.columns[1].kind, ColumnKind::Text);
+        assert_eq!(texts(&table, "column3"), vec!["8", "NULL"]);
+
+        // A stray double quote in a TSV cell is kept as is
+        fs::write(&path, "name\tnote\nweb1\t\"big disk\nweb2\tok\n").unwrap();
+        let source = TableSource::new("tsv", path.to_str().unwrap(), None, None, &[]).unwrap();
+        let table = read_table(&source, &[], &CancelToken::new(), &QueryLimits::default()).unwrap();
+        assert_eq!(texts(&table, "name"), vec!["web1", "web2"]);
+        assert_eq!(texts(&table, "note"), vec!["\"big disk", "ok"]);
+
+        let options = [("delimiter".to_string(), ";".to_string())];
+        assert_eq!(
+            TableSource::new("csv", "data.csv", None, None, &options)
+                .unwrap()
+                .format,
+            TableFormat::Delimited {
+                delimiter: b';',
+                has_header: true
+            }
+        );
+        let options = [("delimiter".to_string(), ";;".to_string())];
+        assert!(TableSource::new("csv", "data.csv", None, None, &options).is_err());
+        let options = [("quote".to_string(), "'".to_string())];
+        assert!(TableSource::new("csv", "data.csv", None, None, &options).is_err());
+        assert!(TableSource::new("csv", "data.csv", Some("$.items"), None, &[]).is_err());
+    }
+
+    #[test]
+    fn test_column_names_and_numbers() {
+        let header = csv::StringRecord::from(vec!["Unit Price", "", "unit price", "Größe"]);
+        assert_eq!(
+            column_names(Some(&header), 5),
+            vec!["unit_price", "column2", "unit_price_3", "größe", "column5"]
+        );
+        assert!(is_number("-4.5e3"));
+        assert!(is_number("0.25"));
+        assert!(!is_number("007"));
+        assert!(!is_number("inf"));
+        assert!(!is_number("1234567890123456789"));
+    }
+}
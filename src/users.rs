@@ -0,0 +1,72 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+/// uid -> user name from /etc/passwd, read the first time an owner is looked up
+static USERS: OnceLock<HashMap<u32, String>> = OnceLock::new();
+/// gid -> group name from /etc/group, read the first time a group is looked up
+static GROUPS: OnceLock<HashMap<u32, String>> = OnceLock::new();
+
+/// Name of the user with this uid, or `None` if no account has it
+/// (e.g. files left behind by a deleted user)
+pub fn user_name(uid: u32) -> Option<&'static str> {
+    USERS
+        .get_or_init(|| load_id_file("/etc/passwd"))
+        .get(&uid)
+        .map(String::as_str)
+}
+
+/// Name of the group with this gid, or `None` if no group has it
+pub fn group_name(gid: u32) -> Option<&'static str> {
+    GROUPS
+        .get_or_init(|| load_id_file("/etc/group"))
+        .get(&gid)
+        .map(String::as_str)
+}
+
+fn load_id_file(path: &str) -> HashMap<u32, String> {
+    std::fs::read_to_string(path)
+        .map(|content| parse_id_file(&content))
+        .unwrap_or_default()
+}
+
+/// Parse passwd/group style lines (`name:password:id:...`) into id -> name.
+/// The first entry for an id wins, as with getpwuid/getgrgid.
+pub fn parse_id_file(content: &str) -> HashMap<u32, String> {
+    let mut names = HashMap::new();
+    for line in content.lines() {
+        if line.trim_start().starts_with('#') {
+            continue;
+        }
+        let mut fields = line.split(':');
+        let (Some(name), Some(_), Some(id)) = (fields.next(), fields.next(), fields.next()) else {
+            continue;
+        };
+        if let Ok(id) = id.trim().parse::<u32>() {
+            names.entry(id).or_insert_with(|| name.to_string());
+        }
+    }
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_id_file() {
+        let passwd = "# comment\n\
+                      root:x:0:0:root:/root:/bin/bash\n\
+                      alice:x:1000:1000:Alice:/home/alice:/bin/sh\n\
+                      toor:x:0:0::/root:/bin/sh\n\
+                      broken line\n";
+        let names = parse_id_file(passwd);
+        assert_eq!(names.len(), 2);
+        assert_eq!(names[&0], "root");
+        assert_eq!(names[&1000], "alice");
+
+        let group = "wheel:x:10:alice,bob\nstaff:x:50:\n";
+        let names = parse_id_file(group);
+        assert_eq!(names[&10], "wheel");
+        assert_eq!(names[&50], "staff");
+    }
+}
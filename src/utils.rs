@@ -1,86 +1,21 @@
-use crate::models::{Condition, FileInfo, ProcessInfo};
-use crate::processes::parse_memory;
+use crate::aggregate::{aggregate_row, aggregate_row_with, is_aggregate_query};
+use crate::columns::{display_value, Column, Columns};
+use crate::models::{FileInfo, ProcessInfo};
 use prettytable::{Cell, Row, Table};
 use regex::Regex;
-
-pub fn evaluate_conditions(file: &FileInfo, conditions: &[Condition]) -> bool {
-    // All conditions must be true (AND logic)
-    for condition in conditions {
-        let result = evaluate_single_condition(file, condition);
-        let final_result = if condition.negated { !result } else { result };
-
-        if !final_result {
-            return false;
-        }
-    }
-    true
-}
-
-pub fn evaluate_single_condition(file: &FileInfo, condition: &Condition) -> bool {
-    match condition.field.as_str() {
-        "name" => {
-            if condition.operator == "LIKE" {
-                like_match(&file.name, &condition.value)
-            } else {
-                compare_strings(&file.name, &condition.operator, &condition.value)
-            }
-        }
-        "type" => compare_strings(&file.file_type, &condition.operator, &condition.value),
-        "permissions" => compare_strings(&file.permissions, &condition.operator, &condition.value),
-        "path" => {
-            if condition.operator == "LIKE" {
-                like_match(&file.path, &condition.value)
-            } else {
-                compare_strings(&file.path, &condition.operator, &condition.value)
-            }
-        }
-        "size" => {
-            // For size comparison, extract numeric value
-            if let Ok(file_size) = parse_size(&file.size) {
-                if let Ok(compare_size) = parse_size(&condition.value) {
-                    match condition.operator.as_str() {
-                        "=" => file_size == compare_size,
-                        "!=" => file_size != compare_size,
-                        ">" => file_size > compare_size,
-                        "<" => file_size < compare_size,
-                        ">=" => file_size >= compare_size,
-                        "<=" => file_size <= compare_size,
-                        _ => false,
-                    }
-                } else {
-                    false
-                }
-            } else {
-                false
-            }
-        }
-        "depth" => {
-            // For depth comparison, compare numeric values directly
-            if let Ok(compare_depth) = condition.value.parse::<usize>() {
-                match condition.operator.as_str() {
-                    "=" => file.depth == compare_depth,
-                    "!=" => file.depth != compare_depth,
-                    ">" => file.depth > compare_depth,
-                    "<" => file.depth < compare_depth,
-                    ">=" => file.depth >= compare_depth,
-                    "<=" => file.depth <= compare_depth,
-                    _ => false,
-                }
-            } else {
-                false
-            }
-        }
-        "extension" => {
-            // Handle extension comparison, treating None as "NULL"
-            let file_ext = file.extension.as_deref().unwrap_or("NULL");
-            if condition.operator == "LIKE" {
-                like_match(file_ext, &condition.value)
-            } else {
-                compare_strings(file_ext, &condition.operator, &condition.value)
-            }
-        }
-        _ => false,
-    }
+use std::fs::{File, OpenOptions};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::Path;
+
+/// Open a file for reading if it is a regular file. The open doesn't block,
+/// so FIFOs and devices met during a walk are skipped instead of hanging the query.
+pub fn open_regular_file(path: &Path) -> Option<File> {
+    let file = OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_NONBLOCK)
+        .open(path)
+        .ok()?;
+    file.metadata().ok()?.is_file().then_some(file)
 }
 
 pub fn parse_size(size_str: &str) -> Result<f64, String> {
@@ -107,14 +42,19 @@ pub fn parse_size(size_str: &str) -> Result<f64, String> {
 }
 
 pub fn like_match(text: &str, pattern: &str) -> bool {
-    // Convert SQL LIKE pattern to regex
-    // % matches zero or more characters
-    // _ matches exactly one character
+    match like_to_regex(pattern) {
+        Some(regex) => regex.is_match(text),
+        None => false,
+    }
+}
 
+// Convert SQL LIKE pattern to an anchored regex
+// % matches zero or more characters
+// _ matches exactly one character
+pub fn like_to_regex(pattern: &str) -> Option<Regex> {
     let mut regex_pattern = String::new();
-    let mut chars = pattern.chars().peekable();
 
-    while let Some(ch) = chars.next() {
+    for ch in pattern.chars() {
         match ch {
             '%' => regex_pattern.push_str(".*"),
             '_' => regex_pattern.push('.'),
@@ -132,11 +72,13 @@ pub fn like_match(text: &str, pattern: &str) -> bool {
         }
     }
 
-    if let Ok(regex) = Regex::new(&format!("^{}$", regex_pattern)) {
-        regex.is_match(text)
-    } else {
-        false
-    }
+    Regex::new(&format!("^{}$", regex_pattern)).ok()
+}
+
+/// Compile the pattern of a REGEXP condition. Unlike LIKE it is unanchored,
+/// so `name REGEXP 'test'` matches anywhere in the name.
+pub fn compile_regexp(pattern: &str) -> Result<Regex, String> {
+    Regex::new(pattern).map_err(|e| format!("Invalid regular expression '{}': {}", pattern, e))
 }
 
 pub fn compare_strings(left: &str, operator: &str, right: &str) -> bool {
@@ -152,74 +94,34 @@ pub fn compare_strings(left: &str, operator: &str, right: &str) -> bool {
 }
 
 pub fn sort_results(results: &mut [FileInfo], order_by: &str, direction: &crate::models::SortDirection) -> Result<(), String> {
-    let field = order_by.trim().to_lowercase();
-
-    results.sort_by(|a, b| {
-        let ordering = match field.as_str() {
-            "name" => a.name.cmp(&b.name),
-            "type" => a.file_type.cmp(&b.file_type),
-            "modified_date" => a.modified_date.cmp(&b.modified_date),
-            "permissions" => a.permissions.cmp(&b.permissions),
-            "size" => {
-                let a_size = parse_size(&a.size).unwrap_or(0.0);
-                let b_size = parse_size(&b.size).unwrap_or(0.0);
-                a_size
-                    .partial_cmp(&b_size)
-                    .unwrap_or(std::cmp::Ordering::Equal)
-            }
-            "path" => a.path.cmp(&b.path),
-            "extension" => a.extension.cmp(&b.extension),
-            _ => std::cmp::Ordering::Equal,
-        };
-
-        // Reverse ordering for descending sort
-        match direction {
-            crate::models::SortDirection::Descending => ordering.reverse(),
-            crate::models::SortDirection::Ascending => ordering,
-        }
-    });
-
+    sort_by_column(results, order_by, direction);
     Ok(())
 }
 
 pub fn sort_process_results(results: &mut [ProcessInfo], order_by: &str, direction: &crate::models::SortDirection) -> Result<(), String> {
+    sort_by_column(results, order_by, direction);
+    Ok(())
+}
+
+fn sort_by_column<T: Columns>(results: &mut [T], order_by: &str, direction: &crate::models::SortDirection) {
+    sort_by_column_with(results, order_by, direction, T::column);
+}
+
+// Sort rows by a column resolved once up front; unknown columns keep the current order
+pub fn sort_by_column_with<T>(
+    results: &mut [T],
+    order_by: &str,
+    direction: &crate::models::SortDirection,
+    resolve: impl Fn(&str) -> Option<Column<T>>,
+) {
     let field = order_by.trim().to_lowercase();
+    let column = match resolve(&field) {
+        Some(column) => column,
+        None => return,
+    };
 
     results.sort_by(|a, b| {
-        let ordering = match field.as_str() {
-            "pid" => {
-                let a_pid: u32 = a.pid.parse().unwrap_or(0);
-                let b_pid: u32 = b.pid.parse().unwrap_or(0);
-                a_pid.cmp(&b_pid)
-            }
-            "name" => a.name.cmp(&b.name),
-            "cpu_usage" => {
-                let a_cpu: f32 = a
-                    .cpu_usage
-                    .strip_suffix('%')
-                    .unwrap_or("0")
-                    .parse()
-                    .unwrap_or(0.0);
-                let b_cpu: f32 = b
-                    .cpu_usage
-                    .strip_suffix('%')
-                    .unwrap_or("0")
-                    .parse()
-                    .unwrap_or(0.0);
-                a_cpu
-                    .partial_cmp(&b_cpu)
-                    .unwrap_or(std::cmp::Ordering::Equal)
-            }
-            "memory_usage" => {
-                let a_memory = parse_memory(&a.memory_usage).unwrap_or(0.0);
-                let b_memory = parse_memory(&b.memory_usage).unwrap_or(0.0);
-                a_memory
-                    .partial_cmp(&b_memory)
-                    .unwrap_or(std::cmp::Ordering::Equal)
-            }
-            "status" => a.status.cmp(&b.status),
-            _ => std::cmp::Ordering::Equal,
-        };
+        let ordering = column.get(a).sort_cmp(&column.get(b));
 
         // Reverse ordering for descending sort
         match direction {
@@ -227,11 +129,22 @@ pub fn sort_process_results(results: &mut [ProcessInfo], order_by: &str, directi
             crate::models::SortDirection::Ascending => ordering,
         }
     });
+}
 
-    Ok(())
+// An aggregate SELECT list prints one summary row computed over all the results
+fn display_aggregate_results<T: Columns>(results: &[T], select_fields: &[String]) {
+    let mut table = Table::new();
+    table.add_row(Row::new(select_fields.iter().map(|field| Cell::new(field)).collect()));
+    let values = aggregate_row(results, select_fields);
+    table.add_row(Row::new(values.iter().map(|value| Cell::new(value)).collect()));
+    table.printstd();
 }
 
 pub fn display_results(results: &[FileInfo], select_fields: &[String]) {
+    if is_aggregate_query(select_fields) {
+        return display_aggregate_results(results, select_fields);
+    }
+
     let mut table = Table::new();
 
     // Check if this is web content (has web_content file_type)
@@ -267,16 +180,19 @@ pub fn display_results(results: &[FileInfo], select_fields: &[String]) {
         for file in results {
             let mut row = Row::empty();
             for field in select_fields {
+                let other;
                 let value = match field.as_str() {
                     "name" => &file.name,
                     "type" => &file.file_type,
-                    "modified_date" => &file.modified_date.format("%Y-%m-%d %H:%M:%S").to_string(),
                     "permissions" => &file.permissions,
                     "size" => &file.size,
                     "path" => &file.path,
                     "depth" => &file.depth.to_string(),
                     "extension" => file.extension.as_deref().unwrap_or("NULL"),
-                    _ => "",
+                    _ => {
+                        other = display_value(file, field).unwrap_or_default();
+                        &other
+                    }
                 };
                 row.add_cell(Cell::new(value));
             }
@@ -288,6 +204,10 @@ pub fn display_results(results: &[FileInfo], select_fields: &[String]) {
 }
 
 pub fn display_process_results(results: &[ProcessInfo], select_fields: &[String]) {
+    if is_aggregate_query(select_fields) {
+        return display_aggregate_results(results, select_fields);
+    }
+
     let mut table = Table::new();
 
     // Add header row
@@ -339,7 +259,7 @@ pub fn display_network_results(results: &[crate::models::NetInfo], select_fields
             };
             // Display empty values (NULL) in gray
             if value.is_empty() {
-                row.add_cell(Cell::new(&format!("\x1b[90mNULL\x1b[0m")));
+                row.add_cell(Cell::new("\x1b[90mNULL\x1b[0m"));
             } else {
                 row.add_cell(Cell::new(value));
             }
@@ -374,7 +294,7 @@ pub fn display_application_results(results: &[crate::models::ApplicationInfo], s
             };
             // Display NULL values in gray
             if value == "NULL" || value.is_empty() {
-                row.add_cell(Cell::new(&format!("\x1b[90mNULL\x1b[0m")));
+                row.add_cell(Cell::new("\x1b[90mNULL\x1b[0m"));
             } else {
                 row.add_cell(Cell::new(value));
             }
@@ -385,6 +305,33 @@ pub fn display_application_results(results: &[crate::models::ApplicationInfo], s
     table.printstd();
 }
 
+pub fn display_table_results(results: &crate::tables::Table, select_fields: &[String]) {
+    let fields = results.fields(select_fields);
+    let mut table = Table::new();
+    table.add_row(Row::new(fields.iter().map(|field| Cell::new(field)).collect()));
+
+    if is_aggregate_query(&fields) {
+        let values = aggregate_row_with(&results.rows, &fields, |name| results.column(name));
+        table.add_row(Row::new(values.iter().map(|value| Cell::new(value)).collect()));
+        table.printstd();
+        return;
+    }
+
+    for result in &results.rows {
+        let mut row = Row::empty();
+        for field in &fields {
+            match results.display_value(result, field).as_deref() {
+                // Display NULL values in gray
+                Some("NULL") | None => row.add_cell(Cell::new("\x1b[90mNULL\x1b[0m")),
+                Some(value) => row.add_cell(Cell::new(value)),
+            }
+        }
+        table.add_row(row);
+    }
+
+    table.printstd();
+}
+
 pub fn expand_path(path: &str) -> String {
     if path.starts_with('~') {
         if let Some(home_dir) = dirs::home_dir() {
@@ -402,6 +349,7 @@ pub fn expand_path(path: &str) -> String {
 mod tests {
     use super::*;
     use crate::models::{Condition, FileInfo};
+    use crate::predicate::{compile_condition, compile_conditions};
     use chrono::DateTime;
 
     #[test]
@@ -466,16 +414,18 @@ mod tests {
     }
 
     #[test]
-    fn test_evaluate_conditions() {
+    fn test_file_conditions() {
         let file = FileInfo {
             name: "main.rs".to_string(),
             file_type: "file".to_string(),
             modified_date: DateTime::from(std::time::SystemTime::UNIX_EPOCH),
             permissions: "644".to_string(),
             size: "1024 B".to_string(),
+            size_bytes: 1024,
             path: "src/main.rs".to_string(),
             depth: 2,
             extension: Some("rs".to_string()),
+            ..Default::default()
         };
 
         let conditions = vec![
@@ -493,7 +443,7 @@ mod tests {
             },
         ];
 
-        assert!(evaluate_conditions(&file, &conditions));
+        assert!(compile_conditions::<FileInfo>(&conditions).matches(&file));
 
         // Test with a file that should NOT match
         let bad_file = FileInfo {
@@ -502,12 +452,14 @@ mod tests {
             modified_date: DateTime::from(std::time::SystemTime::UNIX_EPOCH),
             permissions: "644".to_string(),
             size: "1024 B".to_string(),
+            size_bytes: 1024,
             path: "target/debug/main.rs".to_string(), // This should fail the NOT LIKE condition
             depth: 3,
             extension: Some("rs".to_string()),
+            ..Default::default()
         };
 
-        assert!(!evaluate_conditions(&bad_file, &conditions));
+        assert!(!compile_conditions::<FileInfo>(&conditions).matches(&bad_file));
     }
 
     #[test]
@@ -519,9 +471,11 @@ mod tests {
             modified_date: DateTime::from(std::time::SystemTime::UNIX_EPOCH),
             permissions: "644".to_string(),
             size: "1024 B".to_string(),
+            size_bytes: 1024,
             path: "src/main.rs".to_string(),
             depth: 2,
             extension: Some("rs".to_string()),
+            ..Default::default()
         };
 
         // Test file without extension
@@ -531,9 +485,11 @@ mod tests {
             modified_date: DateTime::from(std::time::SystemTime::UNIX_EPOCH),
             permissions: "644".to_string(),
             size: "512 B".to_string(),
+            size_bytes: 512,
             path: "README".to_string(),
             depth: 1,
             extension: None,
+            ..Default::default()
         };
 
         // Test directory
@@ -543,9 +499,11 @@ mod tests {
             modified_date: DateTime::from(std::time::SystemTime::UNIX_EPOCH),
             permissions: "755".to_string(),
             size: "0 B".to_string(),
+            size_bytes: 0,
             path: "src".to_string(),
             depth: 1,
             extension: None,
+            ..Default::default()
         };
 
         // Test filtering by extension
@@ -556,9 +514,10 @@ mod tests {
             negated: false,
         };
 
-        assert!(evaluate_conditions(&rs_file, &[rs_condition.clone()]));
-        assert!(!evaluate_conditions(&no_ext_file, &[rs_condition.clone()]));
-        assert!(!evaluate_conditions(&dir, &[rs_condition.clone()]));
+        let rs_predicate = compile_condition::<FileInfo>(&rs_condition);
+        assert!(rs_predicate.matches(&rs_file));
+        assert!(!rs_predicate.matches(&no_ext_file));
+        assert!(!rs_predicate.matches(&dir));
 
         // Test filtering by NULL extension
         let null_condition = Condition {
@@ -568,9 +527,10 @@ mod tests {
             negated: false,
         };
 
-        assert!(!evaluate_conditions(&rs_file, &[null_condition.clone()]));
-        assert!(evaluate_conditions(&no_ext_file, &[null_condition.clone()]));
-        assert!(evaluate_conditions(&dir, &[null_condition.clone()]));
+        let null_predicate = compile_condition::<FileInfo>(&null_condition);
+        assert!(!null_predicate.matches(&rs_file));
+        assert!(null_predicate.matches(&no_ext_file));
+        assert!(null_predicate.matches(&dir));
 
         // Test LIKE pattern matching for extensions
         let like_condition = Condition {
@@ -580,11 +540,9 @@ mod tests {
             negated: false,
         };
 
-        assert!(evaluate_conditions(&rs_file, &[like_condition.clone()]));
-        assert!(!evaluate_conditions(
-            &no_ext_file,
-            &[like_condition.clone()]
-        ));
+        let like_predicate = compile_condition::<FileInfo>(&like_condition);
+        assert!(like_predicate.matches(&rs_file));
+        assert!(!like_predicate.matches(&no_ext_file));
     }
 
     #[test]
@@ -616,9 +574,11 @@ mod tests {
             modified_date: DateTime::from(std::time::SystemTime::UNIX_EPOCH),
             permissions: "644".to_string(),
             size: "100 B".to_string(),
+            size_bytes: 100,
             path: "a.txt".to_string(),
             depth: 1,
             extension: Some("txt".to_string()),
+            ..Default::default()
         };
 
         let file2 = FileInfo {
@@ -627,9 +587,11 @@ mod tests {
             modified_date: DateTime::from(std::time::SystemTime::UNIX_EPOCH),
             permissions: "644".to_string(),
             size: "200 B".to_string(),
+            size_bytes: 200,
             path: "b.txt".to_string(),
             depth: 1,
             extension: Some("txt".to_string()),
+            ..Default::default()
         };
 
         let file3 = FileInfo {
@@ -638,9 +600,11 @@ mod tests {
             modified_date: DateTime::from(std::time::SystemTime::UNIX_EPOCH),
             permissions: "644".to_string(),
             size: "50 B".to_string(),
+            size_bytes: 50,
             path: "c.txt".to_string(),
             depth: 1,
             extension: Some("txt".to_string()),
+            ..Default::default()
         };
 
         let mut results = vec![file1.clone(), file2.clone(), file3.clone()];
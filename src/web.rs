@@ -1,10 +1,15 @@
+use crate::cancel::CancelToken;
 use crate::models::{QueryResult, SqlQuery};
 use indicatif::{ProgressBar, ProgressStyle};
-use reqwest::blocking::Client;
+use reqwest::blocking::{Client, Response};
 use scraper::{Html, Selector};
+use std::io::Read;
 use std::time::Duration;
 use url::Url;
 
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+const MAX_RESPONSE_BYTES: usize = 10 * 1024 * 1024;
+
 /// Check if a string is a valid HTTP/HTTPS URL
 pub fn is_url(s: &str) -> bool {
     Url::parse(s).is_ok()
@@ -50,11 +55,40 @@ pub fn validate_url(url_str: &str) -> Result<(), String> {
     Ok(())
 }
 
+/// Read the response body in chunks, checking for cancellation between reads
+/// and stopping as soon as the size limit is exceeded
+fn read_body(mut response: Response, cancel: &CancelToken) -> Result<String, String> {
+    let mut body = Vec::new();
+    let mut chunk = [0u8; 64 * 1024];
+    loop {
+        if cancel.is_cancelled() {
+            return Err(cancel.error());
+        }
+        let read = response
+            .read(&mut chunk)
+            .map_err(|e| format!("Failed to read response body: {}", e))?;
+        if read == 0 {
+            break;
+        }
+        body.extend_from_slice(&chunk[..read]);
+
+        // Limit response size to prevent memory exhaustion
+        if body.len() > MAX_RESPONSE_BYTES {
+            return Err("Response too large (>10MB). Use a more specific selector.".to_string());
+        }
+    }
+    Ok(String::from_utf8_lossy(&body).into_owned())
+}
+
 /// Execute a web scraping query
-pub fn execute_web_query(query: &SqlQuery) -> Result<QueryResult, String> {
+pub fn execute_web_query(query: &SqlQuery, cancel: &CancelToken) -> Result<QueryResult, String> {
     // Validate URL
     validate_url(&query.from_path)?;
 
+    if cancel.is_cancelled() {
+        return Err(cancel.error());
+    }
+
     // Create progress bar for user feedback
     let pb = ProgressBar::new_spinner();
     pb.set_style(
@@ -65,17 +99,25 @@ pub fn execute_web_query(query: &SqlQuery) -> Result<QueryResult, String> {
     pb.set_message("Fetching webpage...");
     pb.enable_steady_tick(Duration::from_millis(100));
 
-    // Fetch the webpage
+    // Fetch the webpage. A blocking request can't be interrupted while it waits
+    // for headers, so the query deadline also caps the client timeout.
+    let timeout = cancel
+        .remaining()
+        .map_or(REQUEST_TIMEOUT, |remaining| remaining.min(REQUEST_TIMEOUT));
     let client = Client::builder()
-        .timeout(Duration::from_secs(30))
+        .timeout(timeout)
         .user_agent("query-os/1.0")
         .build()
         .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
 
-    let response = client
-        .get(&query.from_path)
-        .send()
-        .map_err(|e| format!("Failed to fetch URL: {}", e))?;
+    let response = client.get(&query.from_path).send().map_err(|e| {
+        pb.finish_and_clear();
+        if cancel.is_cancelled() {
+            cancel.error()
+        } else {
+            format!("Failed to fetch URL: {}", e)
+        }
+    })?;
 
     if !response.status().is_success() {
         pb.finish_and_clear();
@@ -86,15 +128,7 @@ pub fn execute_web_query(query: &SqlQuery) -> Result<QueryResult, String> {
         ));
     }
 
-    let html_content = response
-        .text()
-        .map_err(|e| format!("Failed to read response body: {}", e))?;
-
-    // Limit response size to prevent memory exhaustion
-    if html_content.len() > 10 * 1024 * 1024 {
-        pb.finish_and_clear();
-        return Err("Response too large (>10MB). Use a more specific selector.".to_string());
-    }
+    let html_content = read_body(response, cancel).inspect_err(|_| pb.finish_and_clear())?;
 
     pb.set_message("Parsing content...");
 
@@ -114,9 +148,11 @@ pub fn execute_web_query(query: &SqlQuery) -> Result<QueryResult, String> {
                 modified_date: chrono::Utc::now(),
                 permissions: "644".to_string(),
                 size: format!("{} bytes", html_content.len()),
+                size_bytes: html_content.len() as u64,
                 path: query.from_path.clone(),
                 depth: 0,
                 extension: None,
+                ..Default::default()
             }]));
         }
 
@@ -162,9 +198,11 @@ pub fn execute_web_query(query: &SqlQuery) -> Result<QueryResult, String> {
             modified_date: chrono::Utc::now(),
             permissions: "644".to_string(),
             size: format!("{} chars", content.len()),
+            size_bytes: content.len() as u64,
             path: content,
             depth: 0,
             extension: None,
+            ..Default::default()
         })
         .collect();
 
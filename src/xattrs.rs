@@ -0,0 +1,261 @@
+use crate::cancel::CancelToken;
+use crate::models::FileInfo;
+use crate::users::{group_name, user_name};
+use rayon::prelude::*;
+use regex::Regex;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+/// The extended attribute holding a file's POSIX access ACL
+const ACL_ACCESS: &str = "system.posix_acl_access";
+
+/// Fields that are computed by reading a file's extended attributes
+pub fn is_xattr_field(field: &str) -> bool {
+    matches!(field, "xattrs" | "acl") || xattr_name(field).is_some()
+}
+
+/// The attribute an `XATTR(path, 'user.tag')` field reads
+pub fn xattr_name(field: &str) -> Option<&str> {
+    static CALL: OnceLock<Regex> = OnceLock::new();
+    let call = CALL.get_or_init(|| {
+        Regex::new(r#"(?i)^XATTR\s*\(\s*\w+\s*,\s*(?:'([^']*)'|"([^"]*)")\s*\)$"#).unwrap()
+    });
+    let captures = call.captures(field)?;
+    let name = captures.get(1).or_else(|| captures.get(2))?;
+    Some(&field[name.range()])
+}
+
+/// The same name with a `'static` lifetime, so a column can keep it.
+/// Each distinct name a query uses is allocated once.
+pub fn intern_name(name: &str) -> &'static str {
+    static NAMES: Mutex<Vec<&'static str>> = Mutex::new(Vec::new());
+    let mut names = NAMES.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(interned) = names.iter().find(|interned| **interned == name) {
+        return interned;
+    }
+    let interned: &'static str = Box::leak(name.to_string().into_boxed_str());
+    names.push(interned);
+    interned
+}
+
+/// An attribute value as text: UTF-8 values without their trailing NUL
+/// (SELinux labels have one), anything else as hex
+pub fn value_text(value: &[u8]) -> String {
+    let trimmed = value.strip_suffix(b"\0").unwrap_or(value);
+    match std::str::from_utf8(trimmed) {
+        Ok(text) if !text.contains('\0') => text.to_string(),
+        _ => {
+            let hex: String = value.iter().map(|byte| format!("{:02x}", byte)).collect();
+            format!("0x{}", hex)
+        }
+    }
+}
+
+/// Every attribute as `name=value`, comma-separated, or `None` without any
+pub fn format_xattrs(attributes: &[(String, Vec<u8>)]) -> Option<String> {
+    if attributes.is_empty() {
+        return None;
+    }
+    let pairs: Vec<String> = attributes
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, value_text(value)))
+        .collect();
+    Some(pairs.join(", "))
+}
+
+/// The access ACL of a file in the short text form `setfacl` accepts,
+/// e.g. `user::rw-,user:alice:r--,group::r--,mask::r--,other::r--`.
+/// `None` when the file has none, so only its mode applies.
+pub fn format_acl(attributes: &[(String, Vec<u8>)]) -> Option<String> {
+    let (_, value) = attributes.iter().find(|(name, _)| name == ACL_ACCESS)?;
+    parse_acl(value)
+}
+
+// The kernel's xattr form: a version, then a tag, permission bits and id per entry
+fn parse_acl(value: &[u8]) -> Option<String> {
+    let version = u32::from_le_bytes(value.get(..4)?.try_into().ok()?);
+    if version != 2 || !(value.len() - 4).is_multiple_of(8) {
+        return None;
+    }
+    let entries: Option<Vec<String>> = value[4..]
+        .chunks_exact(8)
+        .map(|entry| {
+            let tag = u16::from_le_bytes([entry[0], entry[1]]);
+            let perm = u16::from_le_bytes([entry[2], entry[3]]);
+            let id = u32::from_le_bytes([entry[4], entry[5], entry[6], entry[7]]);
+            let (kind, qualifier) = match tag {
+                0x01 => ("user", String::new()),
+                0x02 => ("user", user_name(id).map_or(id.to_string(), str::to_string)),
+                0x04 => ("group", String::new()),
+                0x08 => (
+                    "group",
+                    group_name(id).map_or(id.to_string(), str::to_string),
+                ),
+                0x10 => ("mask", String::new()),
+                0x20 => ("other", String::new()),
+                _ => return None,
+            };
+            let bits: String = [(4, 'r'), (2, 'w'), (1, 'x')]
+                .iter()
+                .map(|&(bit, flag)| if perm & bit != 0 { flag } else { '-' })
+                .collect();
+            Some(format!("{}:{}:{}", kind, qualifier, bits))
+        })
+        .collect();
+    Some(entries?.join(","))
+}
+
+/// The extended attributes of `path` itself (not of a symlink's target),
+/// in the order the filesystem lists them. Empty where the filesystem
+/// doesn't support them or they can't be read.
+#[cfg(target_os = "linux")]
+pub fn read_xattrs(path: &Path) -> Vec<(String, Vec<u8>)> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let Ok(c_path) = CString::new(path.as_os_str().as_bytes()) else {
+        return Vec::new();
+    };
+    // SAFETY: the path is NUL-terminated and the buffer is `size` bytes long
+    let names = read_sized(|buffer, size| unsafe {
+        libc::llistxattr(c_path.as_ptr(), buffer.cast(), size)
+    });
+    let Some(names) = names else {
+        return Vec::new();
+    };
+    names
+        .split(|&byte| byte == 0)
+        .filter(|name| !name.is_empty())
+        .filter_map(|name| {
+            let c_name = CString::new(name).ok()?;
+            // SAFETY: as above, with a NUL-terminated attribute name
+            let value = read_sized(|buffer, size| unsafe {
+                libc::lgetxattr(c_path.as_ptr(), c_name.as_ptr(), buffer.cast(), size)
+            })?;
+            Some((String::from_utf8_lossy(name).into_owned(), value))
+        })
+        .collect()
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn read_xattrs(_path: &Path) -> Vec<(String, Vec<u8>)> {
+    Vec::new()
+}
+
+// Ask for the size first, then read into a buffer of that size, again if the
+// value grew in between
+#[cfg(target_os = "linux")]
+fn read_sized(read: impl Fn(*mut u8, usize) -> isize) -> Option<Vec<u8>> {
+    loop {
+        let size = usize::try_from(read(std::ptr::null_mut(), 0)).ok()?;
+        let mut buffer = vec![0u8; size];
+        match usize::try_from(read(buffer.as_mut_ptr(), buffer.len())) {
+            Ok(length) => {
+                buffer.truncate(length);
+                return Some(buffer);
+            }
+            Err(_) if std::io::Error::last_os_error().raw_os_error() == Some(libc::ERANGE) => {}
+            Err(_) => return None,
+        }
+    }
+}
+
+/// Fill in the extended attributes of every row that doesn't have them yet, in parallel
+pub fn fill_xattrs(rows: &mut [FileInfo], root: &Path, cancel: &CancelToken) {
+    rows.par_iter_mut()
+        .filter(|file| file.xattrs.is_none())
+        .for_each(|file| {
+            if cancel.is_cancelled() {
+                return;
+            }
+            file.xattrs = Some(read_xattrs(&file.full_path(root)));
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_xattr_fields() {
+        assert_eq!(xattr_name("XATTR(path, 'user.tag')"), Some("user.tag"));
+        assert_eq!(
+            xattr_name("xattr( path ,\"user.My-Tag\" )"),
+            Some("user.My-Tag")
+        );
+        assert_eq!(xattr_name("XATTR(path)"), None);
+        assert!(is_xattr_field("acl"));
+        assert!(!is_xattr_field("name"));
+        assert!(std::ptr::eq(
+            intern_name("user.tag"),
+            intern_name("user.tag")
+        ));
+    }
+
+    #[test]
+    fn test_format_xattrs_and_acl() {
+        let mut acl = 2u32.to_le_bytes().to_vec();
+        for (tag, perm, id) in [
+            (0x01u16, 6u16, u32::MAX),
+            (0x02, 4, 4_000_000_000),
+            (0x04, 4, u32::MAX),
+            (0x10, 5, u32::MAX),
+            (0x20, 0, u32::MAX),
+        ] {
+            acl.extend(tag.to_le_bytes());
+            acl.extend(perm.to_le_bytes());
+            acl.extend(id.to_le_bytes());
+        }
+        let attributes = vec![
+            ("user.tag".to_string(), b"blue".to_vec()),
+            (
+                "security.selinux".to_string(),
+                b"system_u:object_r:etc_t:s0\0".to_vec(),
+            ),
+            (ACL_ACCESS.to_string(), acl.clone()),
+        ];
+        assert_eq!(
+            format_acl(&attributes).unwrap(),
+            "user::rw-,user:4000000000:r--,group::r--,mask::r-x,other::---"
+        );
+        assert_eq!(
+            format_xattrs(&attributes).unwrap(),
+            format!(
+                "user.tag=blue, security.selinux=system_u:object_r:etc_t:s0, {}={}",
+                ACL_ACCESS,
+                value_text(&acl)
+            )
+        );
+        assert!(value_text(&acl).starts_with("0x0200000001000600"));
+
+        assert_eq!(format_xattrs(&[]), None);
+        assert_eq!(format_acl(&attributes[..2]), None);
+        assert_eq!(parse_acl(&acl[..7]), None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_read_xattrs() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("tagged.txt");
+        std::fs::write(&path, "x").unwrap();
+        let c_path = std::ffi::CString::new(path.to_str().unwrap()).unwrap();
+        // SAFETY: both strings are NUL-terminated and the value is 4 bytes long
+        let set = unsafe {
+            libc::setxattr(
+                c_path.as_ptr(),
+                c"user.tag".as_ptr(),
+                b"blue".as_ptr().cast(),
+                4,
+                0,
+            )
+        };
+        // Some filesystems (tmpfs on older kernels) don't take user attributes
+        if set != 0 {
+            return;
+        }
+        let attributes = read_xattrs(&path);
+        assert!(attributes.contains(&("user.tag".to_string(), b"blue".to_vec())));
+        assert!(read_xattrs(&temp_dir.path().join("missing")).is_empty());
+    }
+}